@@ -0,0 +1,287 @@
+use core::marker::PhantomData;
+
+use crate::disk::{Disk, DISK_SIZE};
+use crate::gamepad::{Gamepad, GamepadButton};
+use crate::inputs::Inputs;
+use crate::mouse::MouseButton;
+use crate::player::Player;
+
+/// Number of players, one for every gamepad
+pub const PLAYERS: usize = Player::ALL.len();
+/// Number of bindings of every action, the primary and the alternative one
+pub const SLOTS: usize = 2;
+
+/// Logical action of the game, usually a field-less enum:
+///
+/// ```ignore
+/// #[derive(Copy, Clone)]
+/// enum Action { Jump, Attack }
+///
+/// impl wasm4::actions::Action for Action {
+///     fn index(self) -> usize { self as usize }
+/// }
+/// ```
+pub trait Action: Copy {
+    /// Index of the action, between 0 and the number of actions of the `ActionMap`
+    fn index(self) -> usize;
+}
+
+/// Physical input an action is bound to
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Binding {
+    None,
+    Button(GamepadButton),
+    /// Mouse button, there is only one mouse so it works for every player bound to it
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    fn encode(&self) -> u8 {
+        match self {
+            Binding::None => 0,
+            Binding::Button(button) => 0x10 | (*button as u8).trailing_zeros() as u8,
+            Binding::Mouse(button) => 0x20 | (*button as u8).trailing_zeros() as u8,
+        }
+    }
+
+    fn decode(byte: u8) -> Option<Self> {
+        let bit = 1u8.checked_shl((byte & 0x0f) as u32)?;
+        match byte & 0xf0 {
+            0x00 if byte == 0 => Some(Binding::None),
+            0x10 => GamepadButton::ALL.into_iter()
+                .find(|button| *button as u8 == bit)
+                .map(Binding::Button),
+            0x20 => MouseButton::ALL.into_iter()
+                .find(|button| *button as u8 == bit)
+                .map(Binding::Mouse),
+            _ => None,
+        }
+    }
+
+    fn is_pressed(&self, gamepad: &Gamepad, inputs: &Inputs) -> bool {
+        match self {
+            Binding::None => false,
+            Binding::Button(button) => gamepad.is_pressed(*button),
+            Binding::Mouse(button) => inputs.mouse.is_pressed(*button),
+        }
+    }
+
+    fn is_held(&self, gamepad: &Gamepad, inputs: &Inputs) -> bool {
+        match self {
+            Binding::None => false,
+            Binding::Button(button) => gamepad.is_held(*button),
+            Binding::Mouse(button) => inputs.mouse.is_held(*button),
+        }
+    }
+
+    fn is_released(&self, gamepad: &Gamepad, inputs: &Inputs) -> bool {
+        match self {
+            Binding::None => false,
+            Binding::Button(button) => gamepad.is_released(*button),
+            Binding::Mouse(button) => inputs.mouse.is_released(*button),
+        }
+    }
+}
+
+/// Bindings of `N` actions for every player, which can be changed and saved on the disk
+pub struct ActionMap<A: Action, const N: usize> {
+    defaults: [[Binding; SLOTS]; N],
+    bindings: [[[Binding; SLOTS]; N]; PLAYERS],
+    action: PhantomData<A>,
+}
+
+impl<A: Action, const N: usize> ActionMap<A, N> {
+    /// Marks the start of the saved bindings, changed when the format changes
+    const SAVE_VERSION: u8 = 0xa1;
+
+    /// Number of bytes taken by the bindings on the disk
+    pub const SAVE_SIZE: usize = 2 + PLAYERS * N * SLOTS;
+
+    /// * `defaults` - Primary and alternative bindings of every action, the same for every player
+    pub const fn new(defaults: [[Binding; SLOTS]; N]) -> Self {
+        assert!(Self::SAVE_SIZE <= DISK_SIZE, "too many actions to fit on the disk");
+        Self {
+            defaults,
+            bindings: [defaults; PLAYERS],
+            action: PhantomData,
+        }
+    }
+
+    /// Action has been pressed in this frame by the `player`
+    pub fn is_pressed(&self, inputs: &Inputs, player: Player, action: A) -> bool {
+        let gamepad = inputs.gamepad(player);
+        self.bindings(player, action).iter().any(|binding| binding.is_pressed(gamepad, inputs))
+    }
+
+    /// Action is held down by the `player`
+    pub fn is_held(&self, inputs: &Inputs, player: Player, action: A) -> bool {
+        let gamepad = inputs.gamepad(player);
+        self.bindings(player, action).iter().any(|binding| binding.is_held(gamepad, inputs))
+    }
+
+    /// Action has been released in this frame by the `player`
+    pub fn is_released(&self, inputs: &Inputs, player: Player, action: A) -> bool {
+        let gamepad = inputs.gamepad(player);
+        let bindings = self.bindings(player, action);
+        bindings.iter().any(|binding| binding.is_released(gamepad, inputs))
+            && !bindings.iter().any(|binding| binding.is_held(gamepad, inputs))
+    }
+
+    pub fn bindings(&self, player: Player, action: A) -> [Binding; SLOTS] {
+        self.bindings[player.index()][action.index()]
+    }
+
+    /// * `slot` - 0 for the primary binding, 1 for the alternative one
+    pub fn bind(&mut self, player: Player, action: A, slot: usize, binding: Binding) {
+        assert!(slot < SLOTS, "slot must be 0 for the primary binding or 1 for the alternative one");
+        self.bindings[player.index()][action.index()][slot] = binding;
+    }
+
+    /// Binds the `binding` to the primary slot of the `action`. An action already using
+    /// the `binding` gets the previous binding instead, so e.g. X and Y get swapped.
+    pub fn rebind(&mut self, player: Player, action: A, binding: Binding) {
+        let previous = self.bindings[player.index()][action.index()][0];
+        if binding != Binding::None {
+            for slots in self.bindings[player.index()].iter_mut() {
+                for slot in slots.iter_mut().filter(|slot| **slot == binding) {
+                    *slot = previous;
+                }
+            }
+        }
+        self.bindings[player.index()][action.index()][0] = binding;
+    }
+
+    /// Action bound to the `binding`, as index of the action
+    pub fn bound_action(&self, player: Player, binding: Binding) -> Option<usize> {
+        self.bindings[player.index()].iter().position(|slots| slots.contains(&binding))
+    }
+
+    /// Input pressed in this frame by the `player`, to be assigned in an options screen
+    pub fn capture(inputs: &Inputs, player: Player) -> Option<Binding> {
+        let gamepad = inputs.gamepad(player);
+        GamepadButton::ALL.into_iter()
+            .find(|button| gamepad.is_pressed(*button))
+            .map(Binding::Button)
+            .or_else(|| MouseButton::ALL.into_iter()
+                .find(|button| inputs.mouse.is_pressed(*button))
+                .map(Binding::Mouse))
+    }
+
+    /// Restores the default bindings of the `player`
+    pub fn reset(&mut self, player: Player) {
+        self.bindings[player.index()] = self.defaults;
+    }
+
+    pub fn reset_all(&mut self) {
+        self.bindings = [self.defaults; PLAYERS];
+    }
+
+    /// Writes the bindings of every player into the `buffer`, which needs `SAVE_SIZE` bytes
+    pub fn encode(&self, buffer: &mut [u8]) {
+        buffer[0] = Self::SAVE_VERSION;
+        buffer[1] = N as u8;
+        let bindings = self.bindings.iter().flatten().flatten();
+        for (byte, binding) in buffer[2..Self::SAVE_SIZE].iter_mut().zip(bindings) {
+            *byte = binding.encode();
+        }
+    }
+
+    /// Reads the bindings written by `encode`, returns `false` and keeps the current
+    /// bindings when the data is missing or was saved for a different set of actions
+    pub fn decode(&mut self, buffer: &[u8]) -> bool {
+        if buffer.len() < Self::SAVE_SIZE || buffer[0] != Self::SAVE_VERSION || buffer[1] != N as u8 {
+            return false;
+        }
+        let mut bindings = self.bindings;
+        for (binding, byte) in bindings.iter_mut().flatten().flatten().zip(&buffer[2..Self::SAVE_SIZE]) {
+            match Binding::decode(*byte) {
+                Some(decoded) => *binding = decoded,
+                None => return false,
+            }
+        }
+        self.bindings = bindings;
+        true
+    }
+
+    /// Saves the bindings on the disk at the `offset`, keeping the rest of the saved data
+    pub fn save(&self, disk: &Disk, offset: usize) {
+        assert!(offset + Self::SAVE_SIZE <= DISK_SIZE, "bindings don't fit on the disk at the offset");
+        disk.write_at_with(offset, Self::SAVE_SIZE, |buffer| self.encode(buffer));
+    }
+
+    /// Loads the bindings saved on the disk at the `offset`, see `decode`
+    pub fn load(&mut self, disk: &Disk, offset: usize) -> bool {
+        let mut buffer = [0u8; DISK_SIZE];
+        let length = disk.read(&mut buffer);
+        self.decode(buffer.get(offset..length).unwrap_or(&[]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone)]
+    enum Test {
+        Jump,
+        Attack,
+    }
+
+    impl Action for Test {
+        fn index(self) -> usize {
+            self as usize
+        }
+    }
+
+    const DEFAULTS: [[Binding; SLOTS]; 2] = [
+        [Binding::Button(GamepadButton::ButtonX), Binding::Button(GamepadButton::DPadUp)],
+        [Binding::Button(GamepadButton::ButtonY), Binding::Mouse(MouseButton::Left)],
+    ];
+
+    #[test]
+    fn rebind_swaps_the_bindings() {
+        let mut map = ActionMap::<Test, 2>::new(DEFAULTS);
+        map.rebind(Player::One, Test::Jump, Binding::Button(GamepadButton::ButtonY));
+        assert_eq!(map.bindings(Player::One, Test::Jump)[0], Binding::Button(GamepadButton::ButtonY));
+        assert_eq!(map.bindings(Player::One, Test::Attack)[0], Binding::Button(GamepadButton::ButtonX));
+        assert_eq!(map.bindings(Player::Two, Test::Jump), DEFAULTS[0]);
+        assert_eq!(map.bound_action(Player::One, Binding::Mouse(MouseButton::Left)), Some(1));
+        map.reset(Player::One);
+        assert_eq!(map.bindings(Player::One, Test::Jump), DEFAULTS[0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "slot must be 0")]
+    fn bind_rejects_slots_past_the_alternative_one() {
+        ActionMap::<Test, 2>::new(DEFAULTS).bind(Player::One, Test::Jump, SLOTS, Binding::None);
+    }
+
+    #[test]
+    fn encoded_bindings_decode_to_the_same() {
+        let mut map = ActionMap::<Test, 2>::new(DEFAULTS);
+        map.bind(Player::Three, Test::Attack, 1, Binding::Mouse(MouseButton::Middle));
+        map.bind(Player::Four, Test::Jump, 0, Binding::None);
+        let mut buffer = [0; ActionMap::<Test, 2>::SAVE_SIZE];
+        map.encode(&mut buffer);
+
+        let mut decoded = ActionMap::<Test, 2>::new(DEFAULTS);
+        assert!(decoded.decode(&buffer));
+        for player in Player::ALL {
+            for action in [Test::Jump, Test::Attack] {
+                assert_eq!(decoded.bindings(player, action), map.bindings(player, action));
+            }
+        }
+    }
+
+    #[test]
+    fn decode_keeps_the_bindings_of_foreign_data() {
+        let mut map = ActionMap::<Test, 2>::new(DEFAULTS);
+        let mut buffer = [0; ActionMap::<Test, 2>::SAVE_SIZE];
+        map.encode(&mut buffer);
+        assert!(!map.decode(&buffer[..buffer.len() - 1]));
+        assert!(!ActionMap::<Test, 1>::new([DEFAULTS[0]]).decode(&buffer));
+        buffer[2] = 0x1f;
+        assert!(!map.decode(&buffer));
+        assert_eq!(map.bindings(Player::One, Test::Jump), DEFAULTS[0]);
+    }
+}
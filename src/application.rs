@@ -1,3 +1,4 @@
+use crate::clock::Clock;
 use crate::framebuffer::Framebuffer;
 use crate::inputs::Inputs;
 
@@ -14,6 +15,7 @@ pub trait Application {
 
 static mut INPUTS: Inputs = unsafe { Inputs::new() };
 static mut FRAMEBUFFER: Framebuffer = unsafe { Framebuffer::new() };
+static mut CLOCK: Clock = Clock::new();
 
 pub unsafe fn get_shared_inputs() -> &'static mut Inputs {
     &mut INPUTS
@@ -23,6 +25,12 @@ pub unsafe fn get_shared_framebuffer() -> &'static mut Framebuffer {
     &mut FRAMEBUFFER
 }
 
+/// # Safety
+/// The clock is shared mutable state, it must not be ticked while a reference to it is held
+pub unsafe fn get_shared_clock() -> &'static mut Clock {
+    &mut *core::ptr::addr_of_mut!(CLOCK)
+}
+
 #[macro_export]
 macro_rules! main_application {
     ($application:ty) => {
@@ -41,11 +49,14 @@ macro_rules! main_application {
             let application = unsafe { MAIN_APPLICATION.assume_init_mut() };
 
             let inputs = unsafe { $crate::application::get_shared_inputs() };
+            unsafe { inputs.early_update() };
             application.update(inputs);
             unsafe { inputs.late_update() };
 
             let framebuffer = unsafe { $crate::application::get_shared_framebuffer() };
             application.render(framebuffer);
+
+            unsafe { $crate::application::get_shared_clock().tick() };
         }
     };
 }
\ No newline at end of file
@@ -2,6 +2,32 @@ use crate::system;
 
 pub struct Audio;
 
+/// Destination of the tones, the WASM-4 sound chip through `Audio`,
+/// or e.g. `synthesizer::Synthesizer` rendering them offline
+pub trait ToneOutput {
+    fn tone(&self, frequency: Frequency, duration: ADSRDuration, volume: Volume, flags: Flags);
+}
+
+/// Tone output of the tests, recording the raw values given to `tone`
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct Tones(core::cell::RefCell<alloc::vec::Vec<(u32, u32, u32, u32)>>);
+
+#[cfg(test)]
+impl Tones {
+    /// Frequency, duration, volume and flags of the tones played since the last call
+    pub(crate) fn take(&self) -> alloc::vec::Vec<(u32, u32, u32, u32)> {
+        core::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+impl ToneOutput for Tones {
+    fn tone(&self, frequency: Frequency, duration: ADSRDuration, volume: Volume, flags: Flags) {
+        self.0.borrow_mut().push((frequency.into(), duration.into(), volume.into(), flags.into()));
+    }
+}
+
 impl Audio {
     pub const fn shared() -> Self {
         Self
@@ -21,6 +47,12 @@ impl Audio {
     }
 }
 
+impl ToneOutput for Audio {
+    fn tone(&self, frequency: Frequency, duration: ADSRDuration, volume: Volume, flags: Flags) {
+        Audio::tone(self, frequency, duration, volume, flags)
+    }
+}
+
 /// Wave frequency
 pub struct Frequency(u32);
 
@@ -123,6 +155,56 @@ pub enum Channel {
     Noise = 0b11,
 }
 
+impl Channel {
+    pub const ALL: [Channel; 4] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise];
+}
+
+/// Set of channels, e.g. the channels a sound can be played on
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct ChannelSet(u8);
+
+impl ChannelSet {
+    pub const EMPTY: ChannelSet = ChannelSet(0);
+    pub const ALL: ChannelSet = ChannelSet(0b1111);
+    /// Both square wave channels
+    pub const PULSE: ChannelSet = ChannelSet::of(Channel::Pulse1).with(Channel::Pulse2);
+
+    pub const fn of(channel: Channel) -> Self {
+        Self(1 << channel as u8)
+    }
+
+    pub const fn with(self, channel: Channel) -> Self {
+        Self(self.0 | 1 << channel as u8)
+    }
+
+    pub const fn without(self, channel: Channel) -> Self {
+        Self(self.0 & !(1 << channel as u8))
+    }
+
+    pub const fn contains(&self, channel: Channel) -> bool {
+        self.0 & 1 << channel as u8 != 0
+    }
+
+    pub const fn union(self, other: ChannelSet) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=Channel> {
+        let set = *self;
+        Channel::ALL.into_iter().filter(move |channel| set.contains(*channel))
+    }
+}
+
+impl From<Channel> for ChannelSet {
+    fn from(channel: Channel) -> Self {
+        Self::of(channel)
+    }
+}
+
 /// For pulse channels, the pulse wave duty cycle.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum DutyCycle {
@@ -0,0 +1,276 @@
+//! Frame counter advanced by `main_application!` after every update, with timers,
+//! cooldowns and scheduled events measured in frames.
+
+use crate::application::get_shared_clock;
+
+/// Frames per second of the WASM-4 runtime
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+/// Number of frames since the start of the game
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Clock {
+    frame: u32,
+}
+
+impl Clock {
+    pub const fn new() -> Self {
+        Self { frame: 0 }
+    }
+
+    /// Moves on to the next frame
+    pub fn tick(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    /// Frame being updated, 0 during the first update
+    pub const fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Whole seconds since the start of the game
+    pub const fn seconds(&self) -> u32 {
+        self.frame / FRAMES_PER_SECOND
+    }
+
+    /// Whether this is one of every `frames` frames, e.g. `every(30)` twice per second
+    pub const fn every(&self, frames: u32) -> bool {
+        self.every_offset(frames, 0)
+    }
+
+    /// Like `every`, shifted by the `offset` frames to spread the work of several objects
+    pub const fn every_offset(&self, frames: u32, offset: u32) -> bool {
+        frames > 0 && self.frame.wrapping_sub(offset).is_multiple_of(frames)
+    }
+}
+
+/// Frame being updated, 0 during the first update
+pub fn frame() -> u32 {
+    unsafe { get_shared_clock() }.frame()
+}
+
+/// Whether this is one of every `frames` frames of the shared clock
+pub fn every(frames: u32) -> bool {
+    unsafe { get_shared_clock() }.every(frames)
+}
+
+/// Converts the `seconds` to frames
+pub const fn seconds(seconds: u32) -> u32 {
+    seconds * FRAMES_PER_SECOND
+}
+
+/// Countdown ending a number of frames after it was started, on the shared clock
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Timer {
+    start: u32,
+    duration: u32,
+}
+
+impl Timer {
+    /// Timer ending in `frames` frames from now
+    pub fn start(frames: u32) -> Self {
+        Self { start: frame(), duration: frames }
+    }
+
+    /// Timer which has already ended
+    pub const fn finished() -> Self {
+        Self { start: 0, duration: 0 }
+    }
+
+    pub fn elapsed(&self) -> u32 {
+        frame().wrapping_sub(self.start).min(self.duration)
+    }
+
+    /// Frames left until the end
+    pub fn remaining(&self) -> u32 {
+        self.duration - self.elapsed()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed() >= self.duration
+    }
+
+    /// Whether the timer ends in this frame, so the action runs only once
+    pub fn just_finished(&self) -> bool {
+        self.duration > 0 && frame().wrapping_sub(self.start) == self.duration
+    }
+
+    /// Elapsed part of the duration, between 0 and `scale`, e.g. for a progress bar
+    pub fn progress(&self, scale: u32) -> u32 {
+        if self.duration == 0 {
+            return scale;
+        }
+        (self.elapsed() as u64 * scale as u64 / self.duration as u64) as u32
+    }
+
+    /// Starts the timer again with the same duration
+    pub fn restart(&mut self) {
+        self.start = frame();
+    }
+}
+
+/// Limits how often an action can happen, e.g. one shot every 10 frames
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Cooldown {
+    timer: Timer,
+}
+
+impl Cooldown {
+    pub const fn new() -> Self {
+        Self { timer: Timer::finished() }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.timer.is_finished()
+    }
+
+    /// Starts the cooldown of `frames` if it's ready, returns whether the action can happen
+    pub fn trigger(&mut self, frames: u32) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+        self.timer = Timer::start(frames);
+        true
+    }
+
+    /// Frames left until it's ready again
+    pub fn remaining(&self) -> u32 {
+        self.timer.remaining()
+    }
+
+    /// Makes it ready immediately
+    pub fn reset(&mut self) {
+        self.timer = Timer::finished();
+    }
+}
+
+/// Events to happen once at a given frame of the shared clock, at most `N` pending at a time.
+/// The event can be any value, e.g. an enum of the game or a `fn(&mut Game)` callback.
+pub struct Schedule<E, const N: usize> {
+    events: [Option<(u32, E)>; N],
+}
+
+impl<E, const N: usize> Schedule<E, N> {
+    pub const fn new() -> Self {
+        Self { events: [const { None }; N] }
+    }
+
+    /// Schedules the `event` in `frames` frames from now, returns `false` when the schedule is full
+    pub fn schedule_in(&mut self, frames: u32, event: E) -> bool {
+        self.schedule_at(frame().wrapping_add(frames), event)
+    }
+
+    /// Schedules the `event` at the `frame`, returns `false` when the schedule is full
+    pub fn schedule_at(&mut self, frame: u32, event: E) -> bool {
+        match self.events.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => {
+                *slot = Some((frame, event));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes and returns an event whose frame has come, the earliest first.
+    /// Called in a loop every update: `while let Some(event) = schedule.poll() { ... }`
+    pub fn poll(&mut self) -> Option<E> {
+        let now = frame();
+        let due = |at: u32| now.wrapping_sub(at) < u32::MAX / 2;
+        let (index, _) = self.events.iter().enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(|(at, _)| (index, *at)))
+            .filter(|(_, at)| due(*at))
+            .min_by_key(|(_, at)| u32::MAX - now.wrapping_sub(*at))?;
+        self.events[index].take().map(|(_, event)| event)
+    }
+
+    /// Removes the pending events matching the `predicate`
+    pub fn cancel(&mut self, predicate: impl Fn(&E) -> bool) {
+        for slot in self.events.iter_mut() {
+            if slot.as_ref().is_some_and(|(_, event)| predicate(event)) {
+                *slot = None;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.events = [const { None }; N];
+    }
+
+    /// Number of pending events
+    pub fn len(&self) -> usize {
+        self.events.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<E, const N: usize> Default for Schedule<E, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(frames: u32) {
+        for _ in 0..frames {
+            unsafe { get_shared_clock() }.tick();
+        }
+    }
+
+    #[test]
+    fn clock_counts_frames_and_seconds() {
+        let mut clock = Clock::new();
+        assert!(clock.every(30) && !clock.every(0));
+        for _ in 0..seconds(2) + 5 {
+            clock.tick();
+        }
+        assert_eq!(clock.frame(), 125);
+        assert_eq!(clock.seconds(), 2);
+        assert!(clock.every(5) && !clock.every(10));
+        assert!(clock.every_offset(10, 5) && !clock.every_offset(10, 4));
+    }
+
+    /// The only test reading or ticking the shared clock, as the tests run in parallel
+    #[test]
+    fn shared_clock_drives_timers_cooldowns_and_schedules() {
+        let timer = Timer::start(4);
+        assert_eq!((timer.elapsed(), timer.remaining(), timer.progress(100)), (0, 4, 0));
+        tick(3);
+        assert!(!timer.is_finished() && !timer.just_finished());
+        assert_eq!(timer.progress(100), 75);
+        tick(1);
+        assert!(timer.is_finished() && timer.just_finished());
+        tick(1);
+        assert!(timer.is_finished() && !timer.just_finished());
+        assert_eq!(timer.remaining(), 0);
+        assert_eq!(Timer::finished().progress(100), 100);
+
+        let mut cooldown = Cooldown::new();
+        assert!(cooldown.trigger(3));
+        tick(1);
+        assert!(!cooldown.trigger(3));
+        assert_eq!(cooldown.remaining(), 2);
+        tick(2);
+        assert!(cooldown.trigger(3));
+        cooldown.reset();
+        assert!(cooldown.is_ready());
+
+        let mut schedule = Schedule::<char, 3>::new();
+        assert!(schedule.schedule_in(5, 'c'));
+        assert!(schedule.schedule_in(2, 'b'));
+        assert!(schedule.schedule_in(2, 'x'));
+        assert!(!schedule.schedule_in(1, 'd'));
+        schedule.cancel(|event| *event == 'x');
+        assert!(schedule.schedule_at(frame(), 'a'));
+        assert_eq!(schedule.poll(), Some('a'));
+        assert_eq!(schedule.poll(), None);
+        tick(5);
+        assert_eq!(schedule.poll(), Some('b'));
+        assert_eq!(schedule.poll(), Some('c'));
+        assert!(schedule.is_empty());
+    }
+}
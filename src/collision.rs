@@ -0,0 +1,430 @@
+//! Collision tests between points, rectangles, circles, line segments and sprite pixel masks.
+//!
+//! Rectangles cover whole pixels, like `Rect::contains`, while segments and circles
+//! are measured between pixel positions, so the math stays on integers.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::framebuffer::PaletteIndex;
+use crate::geometry::{Point, Rect, Size, Vector};
+use crate::sprite::{Flags, Sprite};
+use crate::system;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: u32,
+}
+
+/// Line segment between two pixel positions, both included
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Segment {
+    pub start: Point,
+    pub end: Point,
+}
+
+/// Hitbox of any of the supported shapes
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Shape {
+    Point(Point),
+    Rect(Rect),
+    Circle(Circle),
+    Segment(Segment),
+}
+
+impl Circle {
+    pub const fn new(center: Point, radius: u32) -> Self {
+        Self { center, radius }
+    }
+
+    pub const fn bounds(&self) -> Rect {
+        let radius = self.radius as i32;
+        Rect::new(self.center.x - radius, self.center.y - radius, self.radius * 2 + 1, self.radius * 2 + 1)
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        distance_squared(self.center, point) <= square(self.radius as i64)
+    }
+
+    pub fn intersects_circle(&self, other: &Circle) -> bool {
+        distance_squared(self.center, other.center) <= square(self.radius as i64 + other.radius as i64)
+    }
+
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        !rect.is_empty() && self.contains(rect.clamp_point(self.center))
+    }
+}
+
+impl Segment {
+    pub const fn new(start: Point, end: Point) -> Self {
+        Self { start, end }
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        cross(self.start, self.end, point) == 0 && within_bounds(self.start, self.end, point)
+    }
+
+    pub fn intersects_segment(&self, other: &Segment) -> bool {
+        let (a, b, c, d) = (self.start, self.end, other.start, other.end);
+        let (d1, d2) = (cross(c, d, a).signum(), cross(c, d, b).signum());
+        let (d3, d4) = (cross(a, b, c).signum(), cross(a, b, d).signum());
+        if d1 * d2 < 0 && d3 * d4 < 0 {
+            return true;
+        }
+        self.contains(c) || self.contains(d) || other.contains(a) || other.contains(b)
+    }
+
+    pub fn intersects_rect(&self, rect: &Rect) -> bool {
+        if rect.is_empty() {
+            return false;
+        }
+        if rect.contains(self.start) || rect.contains(self.end) {
+            return true;
+        }
+        let (left, top) = (rect.left(), rect.top());
+        let (right, bottom) = (rect.right() - 1, rect.bottom() - 1);
+        let corners = [
+            Point::new(left, top),
+            Point::new(right, top),
+            Point::new(right, bottom),
+            Point::new(left, bottom),
+        ];
+        (0..4).any(|index| self.intersects_segment(&Segment::new(corners[index], corners[(index + 1) % 4])))
+    }
+
+    pub fn intersects_circle(&self, circle: &Circle) -> bool {
+        let (a, b, center) = (self.start, self.end, circle.center);
+        let radius_squared = square(circle.radius as i64);
+        let direction = b - a;
+        let length_squared = direction.x as i64 * direction.x as i64 + direction.y as i64 * direction.y as i64;
+        let offset = center - a;
+        let projection = direction.x as i64 * offset.x as i64 + direction.y as i64 * offset.y as i64;
+        if projection <= 0 || length_squared == 0 {
+            distance_squared(a, center) <= radius_squared
+        } else if projection >= length_squared {
+            distance_squared(b, center) <= radius_squared
+        } else {
+            square(cross(a, b, center)) <= radius_squared * length_squared
+        }
+    }
+}
+
+impl Shape {
+    /// Smallest rectangle containing the shape
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Shape::Point(point) => Rect::new(point.x, point.y, 1, 1),
+            Shape::Rect(rect) => *rect,
+            Shape::Circle(circle) => circle.bounds(),
+            Shape::Segment(segment) => Rect::from_corners(segment.start, segment.end),
+        }
+    }
+
+    pub fn translate(&self, offset: Vector) -> Shape {
+        match self {
+            Shape::Point(point) => Shape::Point(*point + offset),
+            Shape::Rect(rect) => Shape::Rect(rect.translate(offset)),
+            Shape::Circle(circle) => Shape::Circle(Circle::new(circle.center + offset, circle.radius)),
+            Shape::Segment(segment) => Shape::Segment(Segment::new(segment.start + offset, segment.end + offset)),
+        }
+    }
+
+    pub fn intersects(&self, other: &Shape) -> bool {
+        match (self, other) {
+            (Shape::Point(a), Shape::Point(b)) => a == b,
+            (Shape::Point(point), Shape::Rect(rect)) | (Shape::Rect(rect), Shape::Point(point)) =>
+                rect.contains(*point),
+            (Shape::Point(point), Shape::Circle(circle)) | (Shape::Circle(circle), Shape::Point(point)) =>
+                circle.contains(*point),
+            (Shape::Point(point), Shape::Segment(segment)) | (Shape::Segment(segment), Shape::Point(point)) =>
+                segment.contains(*point),
+            (Shape::Rect(a), Shape::Rect(b)) => a.intersects(b),
+            (Shape::Rect(rect), Shape::Circle(circle)) | (Shape::Circle(circle), Shape::Rect(rect)) =>
+                circle.intersects_rect(rect),
+            (Shape::Rect(rect), Shape::Segment(segment)) | (Shape::Segment(segment), Shape::Rect(rect)) =>
+                segment.intersects_rect(rect),
+            (Shape::Circle(a), Shape::Circle(b)) => a.intersects_circle(b),
+            (Shape::Circle(circle), Shape::Segment(segment)) | (Shape::Segment(segment), Shape::Circle(circle)) =>
+                segment.intersects_circle(circle),
+            (Shape::Segment(a), Shape::Segment(b)) => a.intersects_segment(b),
+        }
+    }
+}
+
+/// The shortest move of the rectangle `a` out of the rectangle `b`, `None` when they don't overlap
+pub fn penetration(a: &Rect, b: &Rect) -> Option<Vector> {
+    if !a.intersects(b) {
+        return None;
+    }
+    let left = b.left() - a.right();
+    let right = b.right() - a.left();
+    let up = b.top() - a.bottom();
+    let down = b.bottom() - a.top();
+    let x = if -left < right { left } else { right };
+    let y = if -up < down { up } else { down };
+    if x.abs() < y.abs() {
+        Some(Vector::new(x, 0))
+    } else {
+        Some(Vector::new(0, y))
+    }
+}
+
+/// Flips and rotation of a sprite, applied the same way as by `blit`
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Orientation {
+    pub flip_x: bool,
+    pub flip_y: bool,
+    /// Rotation by 90 degrees counter-clockwise
+    pub rotate: bool,
+}
+
+impl Orientation {
+    pub const NONE: Orientation = Orientation { flip_x: false, flip_y: false, rotate: false };
+
+    /// * `flags` - Flags of `blit`, e.g. `BLIT_FLIP_X | BLIT_ROTATE`
+    pub const fn from_blit_flags(flags: u32) -> Self {
+        Self {
+            flip_x: flags & system::BLIT_FLIP_X != 0,
+            flip_y: flags & system::BLIT_FLIP_Y != 0,
+            rotate: flags & system::BLIT_ROTATE != 0,
+        }
+    }
+}
+
+/// Opaque pixels of a sprite, for pixel-perfect collisions
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SpriteMask {
+    width: u32,
+    height: u32,
+    /// One bit per pixel, every row starting at a new word
+    words: Vec<u32>,
+}
+
+impl SpriteMask {
+    /// Mask of the pixels drawn by `blit` with the `draw_colors`, skipping the transparent ones
+    /// * `draw_colors` - Draw colors the sprite is drawn with, e.g. `[Transparent, Palette2, ..]`
+    ///   to leave out the background of a 1BPP sprite
+    pub fn from_sprite(sprite: &Sprite, draw_colors: [PaletteIndex; 4]) -> Self {
+        let mut mask = Self::empty(sprite.width, sprite.height);
+        for y in 0..sprite.height {
+            for x in 0..sprite.width {
+                let index = (y * sprite.width + x) as usize;
+                let value = match sprite.flags {
+                    Flags::BLIT_2BPP => sprite.bytes[index >> 2] >> (6 - ((index & 3) << 1)) & 0b11,
+                    _ => sprite.bytes[index >> 3] >> (7 - (index & 7)) & 1,
+                };
+                if !matches!(draw_colors[value as usize], PaletteIndex::Transparent) {
+                    mask.set(x, y);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Mask with every pixel of the `size` opaque
+    pub fn filled(size: Size) -> Self {
+        let mut mask = Self::empty(size.width, size.height);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                mask.set(x, y);
+            }
+        }
+        mask
+    }
+
+    fn empty(width: u32, height: u32) -> Self {
+        let words_per_row = width.div_ceil(32);
+        Self { width, height, words: vec![0; (words_per_row * height) as usize] }
+    }
+
+    fn set(&mut self, x: u32, y: u32) {
+        let index = (y * self.width.div_ceil(32) + x / 32) as usize;
+        self.words[index] |= 1 << (x % 32);
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the pixel of the sprite, before any flip or rotation, is opaque
+    pub fn is_opaque(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let index = (y * self.width.div_ceil(32) + x / 32) as usize;
+        self.words[index] & 1 << (x % 32) != 0
+    }
+
+    /// The mask drawn at the `position`, like the sprite given to `blit`
+    pub fn at(&self, position: Point, orientation: Orientation) -> PlacedMask<'_> {
+        PlacedMask { mask: self, position, orientation }
+    }
+}
+
+/// Sprite mask at a position on the screen
+#[derive(Copy, Clone, Debug)]
+pub struct PlacedMask<'a> {
+    pub mask: &'a SpriteMask,
+    pub position: Point,
+    pub orientation: Orientation,
+}
+
+impl PlacedMask<'_> {
+    /// Area covered on the screen, with width and height swapped by the rotation
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = if self.orientation.rotate {
+            (self.mask.height, self.mask.width)
+        } else {
+            (self.mask.width, self.mask.height)
+        };
+        Rect::new(self.position.x, self.position.y, width, height)
+    }
+
+    /// Whether the pixel at the screen `point` is opaque
+    pub fn contains(&self, point: Point) -> bool {
+        if !self.bounds().contains(point) {
+            return false;
+        }
+        let (mut x, mut y) = ((point.x - self.position.x) as u32, (point.y - self.position.y) as u32);
+        let orientation = self.orientation;
+        if orientation.rotate {
+            (x, y) = (y, x);
+        }
+        if orientation.flip_x != orientation.rotate {
+            x = self.mask.width - x - 1;
+        }
+        if orientation.flip_y {
+            y = self.mask.height - y - 1;
+        }
+        self.mask.is_opaque(x, y)
+    }
+
+    /// Whether an opaque pixel of both masks is at the same position
+    pub fn overlaps(&self, other: &PlacedMask) -> bool {
+        let Some(area) = self.bounds().intersection(&other.bounds()) else { return false };
+        (area.top()..area.bottom()).any(|y| (area.left()..area.right())
+            .any(|x| self.contains(Point::new(x, y)) && other.contains(Point::new(x, y))))
+    }
+
+    /// Whether an opaque pixel is inside the `rect`, e.g. an attack hitbox
+    pub fn overlaps_rect(&self, rect: &Rect) -> bool {
+        let Some(area) = self.bounds().intersection(rect) else { return false };
+        (area.top()..area.bottom()).any(|y| (area.left()..area.right())
+            .any(|x| self.contains(Point::new(x, y))))
+    }
+}
+
+fn square(value: i64) -> i64 {
+    value * value
+}
+
+fn distance_squared(a: Point, b: Point) -> i64 {
+    square(a.x as i64 - b.x as i64) + square(a.y as i64 - b.y as i64)
+}
+
+/// Cross product of `b - a` and `c - a`, positive when `c` is on one side of the line and negative on the other
+fn cross(a: Point, b: Point, c: Point) -> i64 {
+    (b.x as i64 - a.x as i64) * (c.y as i64 - a.y as i64) - (b.y as i64 - a.y as i64) * (c.x as i64 - a.x as i64)
+}
+
+/// Whether the `point` is within the rectangle spanned by `a` and `b`
+fn within_bounds(a: Point, b: Point, point: Point) -> bool {
+    point.x >= a.x.min(b.x) && point.x <= a.x.max(b.x) && point.y >= a.y.min(b.y) && point.y <= a.y.max(b.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circles_include_their_edge() {
+        let circle = Circle::new(Point::new(10, 10), 5);
+        assert!(circle.contains(Point::new(13, 14)));
+        assert!(!circle.contains(Point::new(14, 14)));
+        assert_eq!(circle.bounds(), Rect::new(5, 5, 11, 11));
+        assert!(circle.intersects_circle(&Circle::new(Point::new(20, 10), 5)));
+        assert!(!circle.intersects_circle(&Circle::new(Point::new(21, 10), 5)));
+        assert!(circle.intersects_rect(&Rect::new(15, 0, 4, 20)));
+        assert!(!circle.intersects_rect(&Rect::new(14, 14, 4, 4)));
+        assert!(!circle.intersects_rect(&Rect::new(10, 10, 0, 0)));
+    }
+
+    #[test]
+    fn segments_cross_touch_or_miss() {
+        let segment = Segment::new(Point::new(0, 0), Point::new(10, 10));
+        assert!(segment.contains(Point::new(4, 4)));
+        assert!(!segment.contains(Point::new(11, 11)));
+        assert!(segment.intersects_segment(&Segment::new(Point::new(0, 10), Point::new(10, 0))));
+        assert!(segment.intersects_segment(&Segment::new(Point::new(10, 10), Point::new(20, 0))));
+        assert!(segment.intersects_segment(&Segment::new(Point::new(5, 5), Point::new(20, 20))));
+        assert!(!segment.intersects_segment(&Segment::new(Point::new(11, 11), Point::new(20, 20))));
+        assert!(!segment.intersects_segment(&Segment::new(Point::new(1, 0), Point::new(11, 10))));
+    }
+
+    #[test]
+    fn segments_against_rects_and_circles() {
+        let segment = Segment::new(Point::new(-5, 5), Point::new(20, 5));
+        assert!(segment.intersects_rect(&Rect::new(0, 0, 10, 10)));
+        assert!(segment.intersects_rect(&Rect::new(0, 5, 10, 1)));
+        assert!(!segment.intersects_rect(&Rect::new(0, 6, 10, 4)));
+        assert!(segment.intersects_circle(&Circle::new(Point::new(8, 8), 3)));
+        assert!(!segment.intersects_circle(&Circle::new(Point::new(8, 9), 3)));
+        assert!(segment.intersects_circle(&Circle::new(Point::new(23, 9), 5)));
+        assert!(!segment.intersects_circle(&Circle::new(Point::new(24, 9), 5)));
+        let point = Segment::new(Point::new(3, 3), Point::new(3, 3));
+        assert!(point.intersects_circle(&Circle::new(Point::new(3, 5), 2)));
+    }
+
+    #[test]
+    fn shapes_dispatch_both_ways() {
+        let rect = Shape::Rect(Rect::new(0, 0, 4, 4));
+        let circle = Shape::Circle(Circle::new(Point::new(5, 2), 2));
+        let point = Shape::Point(Point::new(3, 3));
+        assert!(rect.intersects(&circle) && circle.intersects(&rect));
+        assert!(rect.intersects(&point) && point.intersects(&rect));
+        assert!(!circle.intersects(&point));
+        assert_eq!(circle.translate(Vector::new(-1, 1)).bounds(), Rect::new(2, 1, 5, 5));
+        assert_eq!(Shape::Segment(Segment::new(Point::new(4, 1), Point::new(0, 3))).bounds(), Rect::new(0, 1, 5, 3));
+    }
+
+    #[test]
+    fn penetration_is_the_shortest_way_out() {
+        let wall = Rect::new(10, 0, 10, 100);
+        assert_eq!(penetration(&Rect::new(8, 50, 4, 4), &wall), Some(Vector::new(-2, 0)));
+        assert_eq!(penetration(&Rect::new(19, 50, 4, 4), &wall), Some(Vector::new(1, 0)));
+        let floor = Rect::new(0, 10, 100, 10);
+        assert_eq!(penetration(&Rect::new(50, 7, 4, 4), &floor), Some(Vector::new(0, -1)));
+        assert_eq!(penetration(&Rect::new(50, 6, 4, 4), &floor), None);
+    }
+
+    #[test]
+    fn sprite_masks_follow_the_orientation() {
+        // XX.
+        // X..
+        let sprite = Sprite { width: 3, height: 2, flags: Flags::BLIT_1BPP, bytes: &[0b1101_0000] };
+        let mask = SpriteMask::from_sprite(&sprite, [PaletteIndex::Transparent, PaletteIndex::Palette2, PaletteIndex::Palette3, PaletteIndex::Palette4]);
+        assert!(mask.is_opaque(1, 0) && !mask.is_opaque(2, 0) && !mask.is_opaque(1, 1));
+
+        let origin = Point::new(10, 10);
+        let flipped = mask.at(origin, Orientation::from_blit_flags(system::BLIT_FLIP_X));
+        assert!(flipped.contains(Point::new(12, 11)) && !flipped.contains(Point::new(10, 10)));
+        // .. / X. / XX
+        let rotated = mask.at(origin, Orientation::from_blit_flags(system::BLIT_ROTATE));
+        assert_eq!(rotated.bounds(), Rect::new(10, 10, 2, 3));
+        let opaque: Vec<bool> = (10..13).flat_map(|y| (10..12).map(move |x| (x, y)))
+            .map(|(x, y)| rotated.contains(Point::new(x, y)))
+            .collect();
+        assert_eq!(opaque, [false, false, true, false, true, true]);
+
+        assert!(mask.at(origin, Orientation::NONE).overlaps_rect(&Rect::new(10, 11, 1, 1)));
+        assert!(!mask.at(origin, Orientation::NONE).overlaps_rect(&Rect::new(11, 11, 5, 5)));
+        let block = SpriteMask::filled(Size::new(2, 2));
+        assert!(!mask.at(origin, Orientation::NONE).overlaps(&block.at(Point::new(11, 11), Orientation::NONE)));
+        assert!(mask.at(origin, Orientation::NONE).overlaps(&block.at(Point::new(9, 11), Orientation::NONE)));
+    }
+}
@@ -0,0 +1,351 @@
+//! Overlay showing the state of the game on top of it, toggled with a chord of buttons
+//! of the first gamepad, and helpers to draw hitboxes while it's shown.
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator<MyAllocator> = CountingAllocator::new(MyAllocator::new());
+//!
+//! // update
+//! self.debug.update(inputs);
+//! self.debug.watch("speed", self.player.velocity.x);
+//!
+//! // render, last
+//! self.debug.rect(framebuffer, self.player.bounds());
+//! self.debug.render(framebuffer);
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::Display;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::clock;
+use crate::collision::Shape;
+use crate::framebuffer::{Framebuffer, PaletteIndex};
+use crate::gamepad::{Gamepad, GamepadButton};
+use crate::geometry::{Point, Rect};
+use crate::input_history::Buttons;
+use crate::inputs::Inputs;
+use crate::mouse::MouseButton;
+use crate::player::Player;
+use crate::replay::InputFrame;
+use crate::system;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Global allocator counting the memory allocated through the `inner` one, read with `heap_usage`
+pub struct CountingAllocator<A> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    pub const fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn allocated(size: usize) {
+        let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = self.inner.alloc(layout);
+        if !pointer.is_null() {
+            Self::allocated(layout.size());
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        self.inner.dealloc(pointer, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        ALLOCATIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let pointer = self.inner.alloc_zeroed(layout);
+        if !pointer.is_null() {
+            Self::allocated(layout.size());
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let reallocated = self.inner.realloc(pointer, layout, new_size);
+        if !reallocated.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            Self::allocated(new_size);
+        }
+        reallocated
+    }
+}
+
+/// Memory allocated through the `CountingAllocator`, all zero when it isn't the global allocator
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct HeapUsage {
+    /// Bytes currently allocated
+    pub allocated: usize,
+    /// Most bytes allocated at once since the start
+    pub peak: usize,
+    /// Number of live allocations
+    pub allocations: usize,
+}
+
+pub fn heap_usage() -> HeapUsage {
+    HeapUsage {
+        allocated: ALLOCATED.load(Ordering::Relaxed),
+        peak: PEAK.load(Ordering::Relaxed),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+    }
+}
+
+/// Buttons held together on the first gamepad to show or hide the overlay by default
+pub const DEFAULT_CHORD: Buttons = Buttons::of(GamepadButton::ButtonX).with(GamepadButton::ButtonY).with(GamepadButton::DPadDown);
+
+const CHAR_WIDTH: i32 = system::CHAR_WIDTH as i32;
+const CHAR_HEIGHT: i32 = system::CHAR_HEIGHT as i32;
+
+pub struct DebugOverlay {
+    pub chord: Buttons,
+    /// Color of the text and of the hitboxes, drawn on `background`
+    pub color: PaletteIndex,
+    pub background: PaletteIndex,
+    visible: bool,
+    inputs: InputFrame,
+    watches: Vec<(&'static str, String)>,
+}
+
+impl DebugOverlay {
+    /// * `chord` - Buttons of the first gamepad toggling the overlay when all held
+    pub const fn new(chord: Buttons) -> Self {
+        assert!(!chord.is_empty(), "chord must have a button");
+        Self {
+            chord,
+            color: PaletteIndex::Palette4,
+            background: PaletteIndex::Palette1,
+            visible: false,
+            inputs: InputFrame { gamepads: [0; 4], mouse_x: 0, mouse_y: 0, mouse_buttons: 0 },
+            watches: Vec::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Toggles the overlay on the chord and clears the watches of the previous frame,
+    /// to be called first in `Application::update`
+    pub fn update(&mut self, inputs: &Inputs) {
+        let gamepad = inputs.gamepad(Player::One);
+        if self.is_chord_pressed(gamepad) {
+            self.visible = !self.visible;
+        }
+        self.inputs = inputs.frame();
+        self.watches.clear();
+    }
+
+    /// Shows the `value` next to the `name` during this frame
+    pub fn watch(&mut self, name: &'static str, value: impl Display) {
+        if self.visible {
+            self.watches.push((name, format!("{value}")));
+        }
+    }
+
+    /// Outline of the `rect`, e.g. a hitbox, drawn only while the overlay is visible
+    pub fn rect(&self, framebuffer: &Framebuffer, rect: Rect) {
+        if self.visible {
+            self.with_colors(framebuffer, [Some(PaletteIndex::Transparent), Some(self.color), None, None], || {
+                framebuffer.rectangle_in(rect);
+            });
+        }
+    }
+
+    /// Outline of the `shape`, drawn only while the overlay is visible
+    pub fn shape(&self, framebuffer: &Framebuffer, shape: &Shape) {
+        if !self.visible {
+            return;
+        }
+        match shape {
+            Shape::Point(point) => self.point(framebuffer, *point),
+            Shape::Rect(rect) => self.rect(framebuffer, *rect),
+            Shape::Circle(circle) => {
+                self.with_colors(framebuffer, [Some(PaletteIndex::Transparent), Some(self.color), None, None], || {
+                    framebuffer.oval_in(circle.bounds());
+                });
+            }
+            Shape::Segment(segment) => {
+                self.with_colors(framebuffer, [Some(self.color), None, None, None], || {
+                    framebuffer.line_between(segment.start, segment.end);
+                });
+            }
+        }
+    }
+
+    /// Small cross centered on the `point`, drawn only while the overlay is visible
+    pub fn point(&self, framebuffer: &Framebuffer, point: Point) {
+        if self.visible {
+            for offset in -2..=2 {
+                framebuffer.set_pixel(point.x + offset, point.y, self.color);
+                framebuffer.set_pixel(point.x, point.y + offset, self.color);
+            }
+        }
+    }
+
+    /// Draws the overlay over the game, to be called last in `Application::render`
+    pub fn render(&self, framebuffer: &Framebuffer) {
+        if !self.visible {
+            return;
+        }
+        let draw_colors = framebuffer.get_draw_colors();
+        let heap = heap_usage();
+        let mut lines = Vec::new();
+        lines.push(format!("F {}", clock::frame()));
+        lines.push(format!("H {}/{} #{}", heap.allocated, heap.peak, heap.allocations));
+        lines.push(format!("DC {}{}{}{}", draw_colors[0] as u8, draw_colors[1] as u8, draw_colors[2] as u8, draw_colors[3] as u8));
+        // Room for the palette swatches
+        lines.push(String::from("P"));
+        for player in Player::ALL {
+            let state = self.inputs.gamepads[player.index()];
+            let buttons: String = GamepadButton::ALL.iter()
+                .map(|button| if state & *button as u8 != 0 { button_char(*button) } else { '.' })
+                .collect();
+            lines.push(format!("{} {}", player.number(), buttons));
+        }
+        let mouse_buttons: String = MouseButton::ALL.iter()
+            .map(|button| if self.inputs.mouse_buttons & *button as u8 != 0 { mouse_button_char(*button) } else { '.' })
+            .collect();
+        lines.push(format!("M {},{} {}", self.inputs.mouse_x, self.inputs.mouse_y, mouse_buttons));
+        for (name, value) in &self.watches {
+            lines.push(format!("{name} {value}"));
+        }
+
+        let width = lines.iter().map(|line| line.len()).max().unwrap_or(0).max(6) as i32 * CHAR_WIDTH + 2;
+        let height = lines.len() as i32 * CHAR_HEIGHT + 2;
+        self.with_colors(framebuffer, [Some(self.background), Some(self.color), None, None], || {
+            framebuffer.rectangle(0, 0, width as u32, height as u32);
+        });
+        self.with_colors(framebuffer, [Some(self.color), Some(PaletteIndex::Transparent), None, None], || {
+            for (index, line) in lines.iter().enumerate() {
+                framebuffer.text(line, 1, 1 + index as i32 * CHAR_HEIGHT);
+            }
+        });
+        let swatches_y = 1 + 3 * CHAR_HEIGHT;
+        let palette = [PaletteIndex::Palette1, PaletteIndex::Palette2, PaletteIndex::Palette3, PaletteIndex::Palette4];
+        for (index, color) in palette.into_iter().enumerate() {
+            let x = 1 + (2 + index as i32) * CHAR_WIDTH;
+            self.with_colors(framebuffer, [Some(color), Some(self.color), None, None], || {
+                framebuffer.rectangle(x, swatches_y, system::CHAR_WIDTH - 1, system::CHAR_HEIGHT - 1);
+            });
+        }
+    }
+
+    fn is_chord_pressed(&self, gamepad: &Gamepad) -> bool {
+        let held = gamepad.buttons() & self.chord.bits() == self.chord.bits();
+        held && GamepadButton::ALL.iter().any(|button| self.chord.contains(*button) && gamepad.is_pressed(*button))
+    }
+
+    /// Runs the `draw` with the draw colors changed, restoring them after
+    fn with_colors(&self, framebuffer: &Framebuffer, colors: [Option<PaletteIndex>; 4], draw: impl FnOnce()) {
+        let draw_colors = framebuffer.get_draw_colors();
+        framebuffer.set_draw_colors(colors);
+        draw();
+        framebuffer.set_draw_colors(draw_colors.map(Some));
+    }
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHORD)
+    }
+}
+
+fn button_char(button: GamepadButton) -> char {
+    match button {
+        GamepadButton::ButtonX => 'X',
+        GamepadButton::ButtonY => 'Y',
+        GamepadButton::DPadLeft => 'L',
+        GamepadButton::DPadRight => 'R',
+        GamepadButton::DPadUp => 'U',
+        GamepadButton::DPadDown => 'D',
+    }
+}
+
+fn mouse_button_char(button: MouseButton) -> char {
+    match button {
+        MouseButton::Left => 'L',
+        MouseButton::Right => 'R',
+        MouseButton::Middle => 'M',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chord_toggles_the_overlay_once_all_buttons_are_held() {
+        let mut inputs = unsafe { Inputs::new() };
+        let mut overlay = DebugOverlay::default();
+        inputs.gamepad1.early_update(GamepadButton::ButtonX as u8 | GamepadButton::ButtonY as u8);
+        overlay.update(&inputs);
+        assert!(!overlay.is_visible());
+
+        unsafe { inputs.gamepad1.late_update() };
+        inputs.gamepad1.early_update(DEFAULT_CHORD.bits());
+        overlay.update(&inputs);
+        assert!(overlay.is_visible());
+
+        unsafe { inputs.gamepad1.late_update() };
+        overlay.update(&inputs);
+        assert!(overlay.is_visible());
+
+        unsafe { inputs.gamepad1.late_update() };
+        inputs.gamepad1.early_update(0);
+        overlay.update(&inputs);
+        unsafe { inputs.gamepad1.late_update() };
+        inputs.gamepad1.early_update(DEFAULT_CHORD.bits());
+        overlay.update(&inputs);
+        assert!(!overlay.is_visible());
+    }
+
+    #[test]
+    fn watches_last_one_frame_while_visible() {
+        let inputs = unsafe { Inputs::new() };
+        let mut overlay = DebugOverlay::default();
+        overlay.watch("hidden", 1);
+        assert!(overlay.watches.is_empty());
+        overlay.set_visible(true);
+        overlay.watch("speed", -1.5);
+        assert_eq!(overlay.watches, [("speed", String::from("-1.5"))]);
+        overlay.update(&inputs);
+        assert!(overlay.watches.is_empty());
+    }
+
+    #[test]
+    fn counting_allocator_tracks_live_and_peak_bytes() {
+        let allocator = CountingAllocator::new(std::alloc::System);
+        let before = heap_usage();
+        let small = Layout::from_size_align(16, 8).unwrap();
+        unsafe {
+            let pointer = allocator.alloc(small);
+            let pointer = allocator.realloc(pointer, small, 64);
+            assert_eq!(heap_usage().allocated, before.allocated + 64);
+            assert_eq!(heap_usage().allocations, before.allocations + 1);
+            allocator.dealloc(pointer, Layout::from_size_align(64, 8).unwrap());
+        }
+        assert_eq!(heap_usage().allocated, before.allocated);
+        assert!(heap_usage().peak >= before.allocated + 64);
+    }
+}
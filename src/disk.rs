@@ -0,0 +1,52 @@
+use crate::system;
+
+/// Size of the persistent storage in bytes
+pub const DISK_SIZE: usize = 1024;
+
+/// Persistent storage of the cartridge, shared by all the saved data of the game
+pub struct Disk;
+
+impl Disk {
+    pub const fn shared() -> Self {
+        Self
+    }
+
+    /// Reads the saved data into the `buffer`, returns the number of bytes read
+    pub fn read(&self, buffer: &mut [u8]) -> usize {
+        unsafe { system::diskr(buffer.as_mut_ptr(), buffer.len()) as usize }
+    }
+
+    /// Replaces the whole saved data with the `data`, returns the number of bytes written
+    pub fn write(&self, data: &[u8]) -> usize {
+        unsafe { system::diskw(data.as_ptr(), data.len()) as usize }
+    }
+
+    /// Reads the saved data starting at the `offset` into the `buffer`, returns the number of bytes read
+    pub fn read_at(&self, offset: usize, buffer: &mut [u8]) -> usize {
+        let mut disk = [0u8; DISK_SIZE];
+        let length = self.read(&mut disk);
+        let available = disk.get(offset..length).unwrap_or(&[]);
+        let count = available.len().min(buffer.len());
+        buffer[..count].copy_from_slice(&available[..count]);
+        count
+    }
+
+    /// Replaces the part of the saved data starting at the `offset`, keeping the rest of it.
+    /// Returns the number of bytes written from the `data`.
+    pub fn write_at(&self, offset: usize, data: &[u8]) -> usize {
+        self.write_at_with(offset, data.len(), |bytes| bytes.copy_from_slice(&data[..bytes.len()]))
+    }
+
+    /// Like `write_at`, with the `encode` filling the bytes straight in the saved data,
+    /// so no other buffer of the data is needed on the stack.
+    /// Returns the number of bytes written, less than the `length` past the end of the disk.
+    pub fn write_at_with(&self, offset: usize, length: usize, encode: impl FnOnce(&mut [u8])) -> usize {
+        let mut disk = [0u8; DISK_SIZE];
+        let saved = self.read(&mut disk);
+        let offset = offset.min(DISK_SIZE);
+        let count = length.min(DISK_SIZE - offset);
+        encode(&mut disk[offset..offset + count]);
+        self.write(&disk[..saved.max(offset + count)]);
+        count
+    }
+}
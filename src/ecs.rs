@@ -0,0 +1,449 @@
+//! Entities with components stored in fixed-capacity arrays, without any allocation.
+//!
+//! The `world!` macro declares the storage of every component, and systems are plain functions
+//! over the world, run in order by `Systems`:
+//!
+//! ```ignore
+//! wasm4::world! {
+//!     pub struct World<64> {
+//!         positions: FixedVector,
+//!         velocities: FixedVector,
+//!         sprites: &'static Sprite,
+//!     }
+//! }
+//!
+//! fn movement(world: &mut World, _inputs: &Inputs) {
+//!     for (_, position, velocity) in world.positions.join_mut(&world.velocities) {
+//!         *position += *velocity;
+//!     }
+//! }
+//!
+//! let bullet = world.spawn().unwrap();
+//! world.positions.insert(&world.entities, bullet, position);
+//! world.velocities.insert(&world.entities, bullet, velocity);
+//! ```
+
+use crate::framebuffer::Framebuffer;
+use crate::inputs::Inputs;
+
+/// Identifier of an entity. The generation tells apart entities which reused
+/// the same index, so an identifier kept after its entity was despawned matches nothing.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Entity {
+    index: u16,
+    generation: u16,
+}
+
+impl Entity {
+    /// Index of the entity in the storages, below their capacity
+    pub const fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    pub const fn generation(&self) -> u16 {
+        self.generation
+    }
+}
+
+/// Allocator of up to `N` living entities
+pub struct Entities<const N: usize> {
+    generations: [u16; N],
+    alive: [bool; N],
+    count: usize,
+}
+
+impl<const N: usize> Entities<N> {
+    pub const fn new() -> Self {
+        assert!(N <= u16::MAX as usize, "too many entities");
+        Self { generations: [0; N], alive: [false; N], count: 0 }
+    }
+
+    /// New entity without any components, `None` when all `N` are alive
+    pub fn spawn(&mut self) -> Option<Entity> {
+        let index = self.alive.iter().position(|alive| !alive)?;
+        self.alive[index] = true;
+        self.count += 1;
+        Some(Entity { index: index as u16, generation: self.generations[index] })
+    }
+
+    /// Frees the entity, returns `false` when it was already despawned
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let index = entity.index();
+        self.alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.count -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index();
+        index < N && self.alive[index] && self.generations[index] == entity.generation
+    }
+
+    /// Number of living entities
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=Entity> + '_ {
+        (0..N).filter(|index| self.alive[*index])
+            .map(|index| Entity { index: index as u16, generation: self.generations[index] })
+    }
+
+    pub fn clear(&mut self) {
+        for index in 0..N {
+            if self.alive[index] {
+                self.alive[index] = false;
+                self.generations[index] = self.generations[index].wrapping_add(1);
+            }
+        }
+        self.count = 0;
+    }
+}
+
+impl<const N: usize> Default for Entities<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Components of type `T` of up to `N` entities, indexed by the entity
+pub struct Components<T, const N: usize> {
+    slots: [Option<(u16, T)>; N],
+}
+
+impl<T, const N: usize> Components<T, N> {
+    pub const fn new() -> Self {
+        Self { slots: [const { None }; N] }
+    }
+
+    /// Adds the component to the entity, returns the component it replaced
+    /// * `entities` - Entities the `entity` must be alive in
+    pub fn insert(&mut self, entities: &Entities<N>, entity: Entity, component: T) -> Option<T> {
+        assert!(entities.is_alive(entity), "entity must be alive to get a component");
+        let previous = self.remove(entity);
+        self.slots[entity.index()] = Some((entity.generation, component));
+        previous
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index())?;
+        match slot {
+            Some((generation, _)) if *generation == entity.generation => slot.take().map(|(_, component)| component),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index())? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index())? {
+            Some((generation, component)) if *generation == entity.generation => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=(Entity, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| slot.as_ref()
+            .map(|(generation, component)| (Entity { index: index as u16, generation: *generation }, component)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=(Entity, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| slot.as_mut()
+            .map(|(generation, component)| (Entity { index: index as u16, generation: *generation }, component)))
+    }
+
+    /// Entities having both this component and the `other` one
+    pub fn join<'a, U>(&'a self, other: &'a Components<U, N>) -> impl Iterator<Item=(Entity, &'a T, &'a U)> {
+        self.iter().filter_map(|(entity, component)| other.get(entity).map(|other| (entity, component, other)))
+    }
+
+    /// Like `join`, with this component mutable
+    pub fn join_mut<'a, U>(&'a mut self, other: &'a Components<U, N>) -> impl Iterator<Item=(Entity, &'a mut T, &'a U)> {
+        self.iter_mut().filter_map(|(entity, component)| other.get(entity).map(|other| (entity, component, other)))
+    }
+
+    /// Entities having this component and both of the others
+    pub fn join3<'a, U, V>(&'a self, second: &'a Components<U, N>, third: &'a Components<V, N>)
+                           -> impl Iterator<Item=(Entity, &'a T, &'a U, &'a V)> {
+        self.join(second).filter_map(|(entity, first, second)| third.get(entity)
+            .map(|third| (entity, first, second, third)))
+    }
+
+    /// Like `join3`, with this component mutable
+    pub fn join3_mut<'a, U, V>(&'a mut self, second: &'a Components<U, N>, third: &'a Components<V, N>)
+                               -> impl Iterator<Item=(Entity, &'a mut T, &'a U, &'a V)> {
+        self.join_mut(second).filter_map(|(entity, first, second)| third.get(entity)
+            .map(|third| (entity, first, second, third)))
+    }
+
+    /// Number of entities having the component
+    pub fn len(&self) -> usize {
+        self.slots.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_none())
+    }
+
+    pub fn clear(&mut self) {
+        self.slots = [const { None }; N];
+    }
+}
+
+impl<T, const N: usize> Default for Components<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Declares a world struct with the entities and a `Components` storage for every field.
+/// Entities are only spawned and despawned through the world, so its `despawn` and `clear`
+/// remove their components from every storage. Outside the module declaring the world,
+/// the entities are read with `entities()`.
+#[macro_export]
+macro_rules! world {
+    ($visibility:vis struct $name:ident<$capacity:literal> { $($field:ident: $component:ty),* $(,)? }) => {
+        $visibility struct $name {
+            entities: $crate::ecs::Entities<$capacity>,
+            $(pub $field: $crate::ecs::Components<$component, $capacity>,)*
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            pub const fn new() -> Self {
+                Self {
+                    entities: $crate::ecs::Entities::new(),
+                    $($field: $crate::ecs::Components::new(),)*
+                }
+            }
+
+            pub fn entities(&self) -> &$crate::ecs::Entities<$capacity> {
+                &self.entities
+            }
+
+            pub fn spawn(&mut self) -> Option<$crate::ecs::Entity> {
+                self.entities.spawn()
+            }
+
+            pub fn despawn(&mut self, entity: $crate::ecs::Entity) -> bool {
+                if !self.entities.despawn(entity) {
+                    return false;
+                }
+                $(self.$field.remove(entity);)*
+                true
+            }
+
+            pub fn is_alive(&self, entity: $crate::ecs::Entity) -> bool {
+                self.entities.is_alive(entity)
+            }
+
+            pub fn clear(&mut self) {
+                self.entities.clear();
+                $(self.$field.clear();)*
+            }
+        }
+    };
+}
+
+/// System updating the world every frame
+pub type UpdateSystem<W> = fn(&mut W, &Inputs);
+/// System drawing the world every frame
+pub type RenderSystem<W> = fn(&W, &Framebuffer);
+
+/// Systems of the world run in the order they were added, at most `N` of each kind,
+/// called from `Application::update` and `Application::render`
+pub struct Systems<W, const N: usize> {
+    update: [Option<UpdateSystem<W>>; N],
+    render: [Option<RenderSystem<W>>; N],
+}
+
+impl<W, const N: usize> Systems<W, N> {
+    pub const fn new() -> Self {
+        Self { update: [None; N], render: [None; N] }
+    }
+
+    pub const fn with_update(mut self, system: UpdateSystem<W>) -> Self {
+        let mut index = 0;
+        while index < N {
+            if self.update[index].is_none() {
+                self.update[index] = Some(system);
+                return self;
+            }
+            index += 1;
+        }
+        panic!("too many update systems");
+    }
+
+    pub const fn with_render(mut self, system: RenderSystem<W>) -> Self {
+        let mut index = 0;
+        while index < N {
+            if self.render[index].is_none() {
+                self.render[index] = Some(system);
+                return self;
+            }
+            index += 1;
+        }
+        panic!("too many render systems");
+    }
+
+    pub fn update(&self, world: &mut W, inputs: &Inputs) {
+        for system in self.update.iter().flatten() {
+            system(world, inputs);
+        }
+    }
+
+    pub fn render(&self, world: &W, framebuffer: &Framebuffer) {
+        for system in self.render.iter().flatten() {
+            system(world, framebuffer);
+        }
+    }
+}
+
+impl<W, const N: usize> Default for Systems<W, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::world! {
+        struct World<4> {
+            positions: i32,
+            velocities: i32,
+            names: &'static str,
+        }
+    }
+
+    #[test]
+    fn despawned_entities_are_reused_with_a_new_generation() {
+        let mut entities = Entities::<2>::new();
+        let first = entities.spawn().unwrap();
+        let second = entities.spawn().unwrap();
+        assert_eq!(entities.spawn(), None);
+        assert!(entities.despawn(first));
+        assert!(!entities.despawn(first));
+        let third = entities.spawn().unwrap();
+        assert_eq!((third.index(), third.generation()), (first.index(), 1));
+        assert!(!entities.is_alive(first) && entities.is_alive(third));
+        assert_eq!(entities.iter().collect::<alloc::vec::Vec<_>>(), [third, second]);
+        entities.clear();
+        assert!(entities.is_empty() && !entities.is_alive(second));
+    }
+
+    #[test]
+    fn components_belong_to_one_generation() {
+        let mut world = World::new();
+        let entity = world.spawn().unwrap();
+        assert_eq!(world.positions.insert(&world.entities, entity, 1), None);
+        assert_eq!(world.positions.insert(&world.entities, entity, 2), Some(1));
+        *world.positions.get_mut(entity).unwrap() += 1;
+        assert_eq!(world.positions.get(entity), Some(&3));
+        assert!(world.despawn(entity));
+        assert!(world.positions.is_empty());
+        let reused = world.spawn().unwrap();
+        assert_eq!(reused.index(), entity.index());
+        world.positions.insert(&world.entities, reused, 5);
+        assert_eq!(world.positions.get(entity), None);
+        assert_eq!(world.positions.remove(entity), None);
+        assert_eq!(world.positions.remove(reused), Some(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "entity must be alive")]
+    fn components_are_not_inserted_for_despawned_entities() {
+        let mut world = World::new();
+        let entity = world.spawn().unwrap();
+        world.despawn(entity);
+        world.positions.insert(&world.entities, entity, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "entity must be alive")]
+    fn components_are_not_inserted_past_the_capacity() {
+        let mut large = Entities::<8>::new();
+        let entity = (0..8).filter_map(|_| large.spawn()).last().unwrap();
+        let world = World::new();
+        Components::<i32, 4>::new().insert(&world.entities, entity, 1);
+    }
+
+    #[test]
+    fn joins_yield_entities_with_every_component() {
+        let mut world = World::new();
+        let entities: [Entity; 3] = core::array::from_fn(|_| world.spawn().unwrap());
+        for (index, entity) in entities.iter().enumerate() {
+            world.positions.insert(&world.entities, *entity, index as i32 * 10);
+            if index != 1 {
+                world.velocities.insert(&world.entities, *entity, index as i32 + 1);
+            }
+        }
+        world.names.insert(&world.entities, entities[2], "last");
+        for (_, position, velocity) in world.positions.join_mut(&world.velocities) {
+            *position += *velocity;
+        }
+        assert_eq!(world.positions.iter().map(|(_, position)| *position).collect::<alloc::vec::Vec<_>>(), [1, 10, 23]);
+        let joined: alloc::vec::Vec<_> = world.positions.join3(&world.velocities, &world.names).collect();
+        assert_eq!(joined, [(entities[2], &23, &3, &"last")]);
+        world.clear();
+        assert!(world.positions.is_empty() && world.entities.is_empty());
+    }
+
+    #[test]
+    fn despawning_through_the_world_removes_every_component() {
+        let mut world = World::new();
+        let kept = world.spawn().unwrap();
+        let despawned = world.spawn().unwrap();
+        for entity in [kept, despawned] {
+            world.positions.insert(&world.entities, entity, 1);
+            world.names.insert(&world.entities, entity, "name");
+        }
+        assert!(world.despawn(despawned));
+        assert!(!world.entities().is_alive(despawned));
+        assert_eq!(world.entities().iter().collect::<alloc::vec::Vec<_>>(), [kept]);
+        let joined: alloc::vec::Vec<_> = world.positions.join(&world.names).map(|(entity, _, _)| entity).collect();
+        assert_eq!(joined, [kept]);
+        world.clear();
+        assert!(world.entities().is_empty() && world.names.iter().next().is_none());
+    }
+
+    #[test]
+    fn systems_run_in_order() {
+        fn double(world: &mut World, _: &Inputs) {
+            for (_, position) in world.positions.iter_mut() {
+                *position *= 2;
+            }
+        }
+        fn increment(world: &mut World, _: &Inputs) {
+            for (_, position) in world.positions.iter_mut() {
+                *position += 1;
+            }
+        }
+        let systems = Systems::<World, 2>::new().with_update(double).with_update(increment);
+        let mut world = World::new();
+        let entity = world.spawn().unwrap();
+        world.positions.insert(&world.entities, entity, 5);
+        // Not read by the systems
+        let inputs = unsafe { Inputs::new() };
+        systems.update(&mut world, &inputs);
+        assert_eq!(world.positions.get(entity), Some(&11));
+    }
+}
@@ -0,0 +1,540 @@
+//! Fixed-point numbers, computed only with integers so the simulation gives the same results
+//! in every runtime, as needed by the rollback of netplay.
+//!
+//! `Fixed` has 16 integer and 16 fractional bits, `Fixed8` has 8 and 8 and takes half the memory.
+//!
+//! Like the integers, the operators panic on overflow in debug builds and wrap around in release
+//! builds, the `checked_` and `saturating_` methods handle it instead. Products are rounded down
+//! to the nearest number, quotients toward zero.
+
+use core::fmt;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign};
+
+/// Sine of a quarter turn split into 64 steps, in 16.16
+const SINE: [i32; 65] = [
+    0, 1608, 3216, 4821, 6424, 8022, 9616, 11204, 12785, 14359, 15924, 17479, 19024, 20557, 22078, 23586,
+    25080, 26558, 28020, 29466, 30893, 32303, 33692, 35062, 36410, 37736, 39040, 40320, 41576, 42806, 44011, 45190,
+    46341, 47464, 48559, 49624, 50660, 51665, 52639, 53581, 54491, 55368, 56212, 57022, 57798, 58538, 59244, 59914,
+    60547, 61145, 61705, 62228, 62714, 63162, 63572, 63944, 64277, 64571, 64827, 65043, 65220, 65358, 65457, 65516,
+    65536,
+];
+
+/// Arctangent of values between 0 and 1 split into 64 steps, in 16.16 radians
+const ARCTANGENT: [i32; 65] = [
+    0, 1024, 2047, 3070, 4091, 5110, 6126, 7140, 8150, 9156, 10158, 11155, 12147, 13133, 14114, 15088,
+    16055, 17015, 17968, 18913, 19850, 20779, 21699, 22610, 23512, 24406, 25289, 26163, 27028, 27882, 28727, 29561,
+    30386, 31200, 32003, 32797, 33580, 34353, 35115, 35867, 36608, 37340, 38060, 38771, 39472, 40162, 40842, 41512,
+    42172, 42823, 43464, 44095, 44716, 45328, 45931, 46525, 47109, 47685, 48251, 48809, 49359, 49899, 50432, 50956,
+    51472,
+];
+
+/// Steps of the sine table in a radian, 256 / 2π in 16.16
+const STEPS_PER_RADIAN: i64 = 2670177;
+
+macro_rules! fixed {
+    ($name:ident, $bits:ty, $wide:ty, $fractional_bits:expr) => {
+        #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default)]
+        pub struct $name($bits);
+
+        impl $name {
+            pub const FRACTIONAL_BITS: u32 = $fractional_bits;
+            pub const ZERO: $name = $name(0);
+            pub const ONE: $name = $name(1 << $fractional_bits);
+            pub const HALF: $name = $name(1 << ($fractional_bits - 1));
+            pub const MIN: $name = $name(<$bits>::MIN);
+            pub const MAX: $name = $name(<$bits>::MAX);
+            /// The smallest positive value
+            pub const EPSILON: $name = $name(1);
+            pub const PI: $name = $name((205887 >> (16 - $fractional_bits)) as $bits);
+            pub const FRAC_PI_2: $name = $name((102944 >> (16 - $fractional_bits)) as $bits);
+            pub const TAU: $name = $name((411775 >> (16 - $fractional_bits)) as $bits);
+
+            /// * `bits` - Raw value, the number multiplied by 2 to the power of `FRACTIONAL_BITS`
+            pub const fn from_bits(bits: $bits) -> Self {
+                Self(bits)
+            }
+
+            pub const fn to_bits(self) -> $bits {
+                self.0
+            }
+
+            pub const fn from_int(int: $bits) -> Self {
+                debug_assert!(
+                    int as i32 >= Self::MIN.to_int() && int as i32 <= Self::MAX.to_int(),
+                    "integer out of the range of the fixed-point number"
+                );
+                Self(int << $fractional_bits)
+            }
+
+            /// Number equal to `numerator / denominator`, e.g. `from_ratio(3, 2)` for 1.5
+            pub const fn from_ratio(numerator: i32, denominator: i32) -> Self {
+                assert!(denominator != 0, "denominator must not be zero");
+                let bits = ((numerator as i64) << $fractional_bits) / denominator as i64;
+                assert!(
+                    bits >= <$bits>::MIN as i64 && bits <= <$bits>::MAX as i64,
+                    "ratio out of the range of the fixed-point number"
+                );
+                Self(bits as $bits)
+            }
+
+            /// The largest integer less than or equal to the number, e.g. for pixel coordinates
+            pub const fn to_int(self) -> i32 {
+                (self.0 >> $fractional_bits) as i32
+            }
+
+            /// The nearest integer, halves rounded up
+            pub const fn round(self) -> i32 {
+                ((self.0 as $wide + Self::HALF.0 as $wide) >> $fractional_bits) as i32
+            }
+
+            /// The smallest integer greater than or equal to the number
+            pub const fn ceil(self) -> i32 {
+                ((self.0 as $wide + Self::ONE.0 as $wide - 1) >> $fractional_bits) as i32
+            }
+
+            /// The largest integer less than or equal to the number, same as `to_int`
+            pub const fn floor(self) -> i32 {
+                self.to_int()
+            }
+
+            /// Fractional part, always positive, e.g. 0.25 for -1.75
+            pub const fn fract(self) -> Self {
+                Self(self.0 & (Self::ONE.0 - 1))
+            }
+
+            pub const fn abs(self) -> Self {
+                Self(self.0.abs())
+            }
+
+            /// -1, 0 or 1 depending on the sign
+            pub const fn signum(self) -> Self {
+                Self::from_int(self.0.signum())
+            }
+
+            pub const fn is_negative(self) -> bool {
+                self.0 < 0
+            }
+
+            pub const fn checked_add(self, other: Self) -> Option<Self> {
+                match self.0.checked_add(other.0) {
+                    Some(bits) => Some(Self(bits)),
+                    None => None,
+                }
+            }
+
+            pub const fn checked_sub(self, other: Self) -> Option<Self> {
+                match self.0.checked_sub(other.0) {
+                    Some(bits) => Some(Self(bits)),
+                    None => None,
+                }
+            }
+
+            pub const fn checked_mul(self, other: Self) -> Option<Self> {
+                Self::from_wide((self.0 as $wide * other.0 as $wide) >> $fractional_bits)
+            }
+
+            /// `None` when `other` is zero or the quotient is out of the range
+            pub const fn checked_div(self, other: Self) -> Option<Self> {
+                if other.0 == 0 {
+                    return None;
+                }
+                Self::from_wide(((self.0 as $wide) << $fractional_bits) / other.0 as $wide)
+            }
+
+            pub const fn saturating_add(self, other: Self) -> Self {
+                Self(self.0.saturating_add(other.0))
+            }
+
+            pub const fn saturating_sub(self, other: Self) -> Self {
+                Self(self.0.saturating_sub(other.0))
+            }
+
+            pub const fn saturating_mul(self, other: Self) -> Self {
+                let product = (self.0 as i64 * other.0 as i64) >> $fractional_bits;
+                if product > <$bits>::MAX as i64 {
+                    Self::MAX
+                } else if product < <$bits>::MIN as i64 {
+                    Self::MIN
+                } else {
+                    Self(product as $bits)
+                }
+            }
+
+            const fn from_wide(bits: $wide) -> Option<Self> {
+                if bits > <$bits>::MAX as $wide || bits < <$bits>::MIN as $wide {
+                    None
+                } else {
+                    Some(Self(bits as $bits))
+                }
+            }
+
+            /// Value between `self` and `to`, at `self` when `t` is 0 and at `to` when `t` is 1
+            pub fn lerp(self, to: Self, t: Self) -> Self {
+                self + (to - self) * t
+            }
+
+            /// Square root, rounded down
+            pub fn sqrt(self) -> Self {
+                assert!(self.0 >= 0, "square root of a negative number");
+                Self(isqrt((self.0 as u64) << $fractional_bits) as $bits)
+            }
+
+            /// Sine of the angle in radians, from a lookup table
+            pub fn sin(self) -> Self {
+                Self((sine(self.0 as i64 * (1 << (16 - $fractional_bits))) >> (16 - $fractional_bits)) as $bits)
+            }
+
+            /// Cosine of the angle in radians, from a lookup table
+            pub fn cos(self) -> Self {
+                (self + Self::FRAC_PI_2).sin()
+            }
+
+            /// Angle in radians between -π and π of the vector to the point `(x, self)`
+            pub fn atan2(self, x: Self) -> Self {
+                let shift = 16 - $fractional_bits;
+                Self((arctangent2((self.0 as i64) << shift, (x.0 as i64) << shift) >> shift) as $bits)
+            }
+        }
+
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: Self) -> Self {
+                Self(self.0 + other.0)
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: Self) -> Self {
+                Self(self.0 - other.0)
+            }
+        }
+
+        impl Mul for $name {
+            type Output = $name;
+
+            fn mul(self, other: Self) -> Self {
+                let product = (self.0 as $wide * other.0 as $wide) >> $fractional_bits;
+                debug_assert!(product as $bits as $wide == product, "attempt to multiply with overflow");
+                Self(product as $bits)
+            }
+        }
+
+        impl Div for $name {
+            type Output = $name;
+
+            fn div(self, other: Self) -> Self {
+                let quotient = ((self.0 as $wide) << $fractional_bits) / other.0 as $wide;
+                debug_assert!(quotient as $bits as $wide == quotient, "attempt to divide with overflow");
+                Self(quotient as $bits)
+            }
+        }
+
+        impl Rem for $name {
+            type Output = $name;
+
+            fn rem(self, other: Self) -> Self {
+                Self(self.0 % other.0)
+            }
+        }
+
+        impl Mul<$bits> for $name {
+            type Output = $name;
+
+            fn mul(self, other: $bits) -> Self {
+                Self(self.0 * other)
+            }
+        }
+
+        impl Div<$bits> for $name {
+            type Output = $name;
+
+            fn div(self, other: $bits) -> Self {
+                Self(self.0 / other)
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> Self {
+                Self(-self.0)
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: Self) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: Self) {
+                *self = *self - other;
+            }
+        }
+
+        impl MulAssign for $name {
+            fn mul_assign(&mut self, other: Self) {
+                *self = *self * other;
+            }
+        }
+
+        impl DivAssign for $name {
+            fn div_assign(&mut self, other: Self) {
+                *self = *self / other;
+            }
+        }
+
+        impl RemAssign for $name {
+            fn rem_assign(&mut self, other: Self) {
+                *self = *self % other;
+            }
+        }
+
+        impl Display for $name {
+            /// Writes the number in decimal, with 4 fractional digits unless a precision is given
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                let digits = f.precision().unwrap_or(4).min(9);
+                let magnitude = (self.0 as i64).unsigned_abs();
+                let int = magnitude >> $fractional_bits;
+                let fraction = ((magnitude & ((1 << $fractional_bits) - 1)) * 10u64.pow(digits as u32)) >> $fractional_bits;
+                let sign = if self.0 < 0 { "-" } else { "" };
+                if digits == 0 {
+                    write!(f, "{}{}", sign, int)
+                } else {
+                    write!(f, "{}{}.{:0width$}", sign, int, fraction, width = digits)
+                }
+            }
+        }
+
+        impl Debug for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                Display::fmt(self, f)
+            }
+        }
+    };
+}
+
+fixed!(Fixed, i32, i64, 16);
+fixed!(Fixed8, i16, i32, 8);
+
+impl From<Fixed8> for Fixed {
+    fn from(value: Fixed8) -> Self {
+        Fixed((value.0 as i32) << 8)
+    }
+}
+
+impl Fixed8 {
+    /// Converts the `value`, dropping the lowest fractional bits and saturating outside of the range
+    pub const fn from_fixed(value: Fixed) -> Self {
+        let bits = value.0 >> 8;
+        if bits > i16::MAX as i32 {
+            Self::MAX
+        } else if bits < i16::MIN as i32 {
+            Self::MIN
+        } else {
+            Self(bits as i16)
+        }
+    }
+}
+
+/// Integer square root, rounded down
+pub(crate) fn isqrt(value: u64) -> u64 {
+    let mut remainder = value;
+    let mut root = 0u64;
+    let mut bit = 1u64 << 62;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if remainder >= root + bit {
+            remainder -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root
+}
+
+/// Sine of the table step, the turn being split into 256 steps
+fn sine_step(step: usize) -> i64 {
+    let index = step % 64;
+    match step / 64 % 4 {
+        0 => SINE[index] as i64,
+        1 => SINE[64 - index] as i64,
+        2 => -SINE[index] as i64,
+        _ => -SINE[64 - index] as i64,
+    }
+}
+
+/// Sine of the angle in 16.16 radians, interpolated between the steps of the table
+fn sine(radians: i64) -> i64 {
+    let position = (radians * STEPS_PER_RADIAN) >> 16;
+    let step = (position >> 16).rem_euclid(256) as usize;
+    let fraction = position & 0xffff;
+    let from = sine_step(step);
+    let to = sine_step(step + 1);
+    from + (((to - from) * fraction) >> 16)
+}
+
+/// Arctangent of the ratio between 0 and 1 in 16.16
+fn arctangent(ratio: i64) -> i64 {
+    let position = ratio * 64;
+    let index = (position >> 16) as usize;
+    if index >= 64 {
+        return ARCTANGENT[64] as i64;
+    }
+    let fraction = position & 0xffff;
+    let from = ARCTANGENT[index] as i64;
+    from + (((ARCTANGENT[index + 1] as i64 - from) * fraction) >> 16)
+}
+
+/// Angle of the vector in 16.16 radians, with the coordinates in 16.16
+fn arctangent2(y: i64, x: i64) -> i64 {
+    if x == 0 && y == 0 {
+        return 0;
+    }
+    let (x_magnitude, y_magnitude) = (x.abs(), y.abs());
+    let mut angle = if y_magnitude <= x_magnitude {
+        arctangent((y_magnitude << 16) / x_magnitude)
+    } else {
+        Fixed::FRAC_PI_2.0 as i64 - arctangent((x_magnitude << 16) / y_magnitude)
+    };
+    if x < 0 {
+        angle = Fixed::PI.0 as i64 - angle;
+    }
+    if y < 0 {
+        angle = -angle;
+    }
+    angle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(value: Fixed, expected: f64, tolerance: f64) -> bool {
+        (value.to_bits() as f64 / 65536.0 - expected).abs() <= tolerance
+    }
+
+    #[test]
+    fn integers_convert_at_the_edges_of_the_range() {
+        assert_eq!(Fixed::from_int(32767).to_int(), 32767);
+        assert_eq!(Fixed::from_int(-32768), Fixed::MIN);
+        assert_eq!(Fixed::MAX.to_int(), 32767);
+        assert_eq!(Fixed8::from_int(-128), Fixed8::MIN);
+        assert_eq!(Fixed8::MAX.to_int(), 127);
+        assert_eq!(Fixed::from_ratio(-7, 4).to_int(), -2);
+        assert_eq!(Fixed::from_ratio(-7, 4).round(), -2);
+        assert_eq!(Fixed::from_ratio(-3, 2).round(), -1);
+        assert_eq!(Fixed::from_ratio(-7, 4).ceil(), -1);
+        assert_eq!(Fixed::from_ratio(-7, 4).floor(), -2);
+        assert_eq!(Fixed::from_ratio(-7, 4).fract(), Fixed::from_ratio(1, 4));
+        assert_eq!(Fixed::MAX.ceil(), 32768);
+        assert_eq!(Fixed::from(Fixed8::from_ratio(-5, 2)), Fixed::from_ratio(-5, 2));
+        assert_eq!(Fixed8::from_fixed(Fixed::from_int(300)), Fixed8::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "ratio out of the range")]
+    fn ratios_out_of_the_range_panic() {
+        Fixed8::from_ratio(255, 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "integer out of the range")]
+    fn integers_out_of_the_range_are_rejected() {
+        let _ = Fixed8::from_int(128);
+    }
+
+    #[test]
+    fn products_round_down_and_quotients_toward_zero() {
+        assert_eq!(Fixed::EPSILON * Fixed::HALF, Fixed::ZERO);
+        assert_eq!(-Fixed::EPSILON * Fixed::HALF, -Fixed::EPSILON);
+        assert_eq!(Fixed::from_int(3) * Fixed::from_ratio(3, 2), Fixed::from_ratio(9, 2));
+        assert_eq!((Fixed::ONE / Fixed::from_int(3)).to_bits(), 21845);
+        assert_eq!((-Fixed::ONE / Fixed::from_int(3)).to_bits(), -21845);
+        assert_eq!((Fixed8::ONE / Fixed8::from_int(3)).to_bits(), 85);
+        assert_eq!(Fixed::from_int(7) % Fixed::from_int(2), Fixed::ONE);
+    }
+
+    #[test]
+    fn overflow_is_checked_or_saturated() {
+        let big = Fixed::from_int(200);
+        assert_eq!(big.checked_mul(big), None);
+        assert_eq!(big.saturating_mul(big), Fixed::MAX);
+        assert_eq!((-big).saturating_mul(big), Fixed::MIN);
+        assert_eq!(Fixed::MAX.checked_add(Fixed::EPSILON), None);
+        assert_eq!(Fixed::MIN.checked_sub(Fixed::EPSILON), None);
+        assert_eq!(Fixed::ONE.checked_div(Fixed::ZERO), None);
+        assert_eq!(Fixed::from_int(200).checked_div(Fixed::from_ratio(1, 200)), None);
+        assert_eq!(Fixed8::from_int(16).checked_mul(Fixed8::from_int(8)), None);
+        assert_eq!(Fixed8::from_int(15).checked_mul(Fixed8::from_int(8)), Some(Fixed8::from_int(120)));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "attempt to multiply with overflow")]
+    fn small_multiplication_overflow_panics_in_debug() {
+        let _ = Fixed8::from_int(16) * Fixed8::from_int(8);
+    }
+
+    #[test]
+    fn square_roots_round_down() {
+        assert_eq!(Fixed::from_int(4).sqrt(), Fixed::from_int(2));
+        assert_eq!(Fixed::from_int(2).sqrt().to_bits(), 92681);
+        assert_eq!(Fixed::ZERO.sqrt(), Fixed::ZERO);
+        assert_eq!(Fixed::MAX.sqrt().to_int(), 181);
+        assert_eq!(Fixed8::from_int(100).sqrt(), Fixed8::from_int(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "square root of a negative number")]
+    fn square_root_of_a_negative_number_panics() {
+        let _ = (-Fixed::ONE).sqrt();
+    }
+
+    #[test]
+    fn sine_and_cosine_follow_the_table() {
+        assert_eq!(Fixed::ZERO.sin(), Fixed::ZERO);
+        assert!(close(Fixed::ZERO.cos(), 1.0, 0.0001));
+        for step in -400..=400 {
+            let angle = Fixed::from_ratio(step, 40);
+            let radians = angle.to_bits() as f64 / 65536.0;
+            assert!(close(angle.sin(), radians.sin(), 0.002), "sin {radians}");
+            assert!(close(angle.cos(), radians.cos(), 0.002), "cos {radians}");
+        }
+        let sine = Fixed8::FRAC_PI_2.sin().to_bits();
+        assert!((255..=256).contains(&sine));
+    }
+
+    #[test]
+    fn arctangent_covers_every_quadrant() {
+        assert_eq!(Fixed::ZERO.atan2(Fixed::ZERO), Fixed::ZERO);
+        assert_eq!(Fixed::ZERO.atan2(-Fixed::ONE), Fixed::PI);
+        for y in -6..=6 {
+            for x in -6..=6 {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                let angle = Fixed::from_int(y).atan2(Fixed::from_int(x));
+                assert!(close(angle, (y as f64).atan2(x as f64), 0.001), "atan2 {y} {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn display_writes_decimals() {
+        use alloc::format;
+        assert_eq!(format!("{}", Fixed::from_ratio(-7, 4)), "-1.7500");
+        assert_eq!(format!("{:.1}", Fixed::from_ratio(1, 3)), "0.3");
+        assert_eq!(format!("{:.0}", Fixed::from_int(12)), "12");
+    }
+}
@@ -1,4 +1,5 @@
 use crate::color::Color;
+use crate::geometry::{Point, Rect};
 use crate::sprite::Sprite;
 use crate::system;
 
@@ -42,6 +43,10 @@ impl Framebuffer {
         system::SCREEN_HEIGHT
     }
 
+    pub fn get_screen_bounds(&self) -> Rect {
+        Rect::new(0, 0, system::SCREEN_WIDTH, system::SCREEN_HEIGHT)
+    }
+
     pub fn line(&self, start_x: i32, start_y: i32, end_x: i32, end_y: i32) {
         unsafe { system::line(start_x, start_y, end_x, end_y) }
     }
@@ -72,6 +77,62 @@ impl Framebuffer {
         }
     }
 
+    /// Part of the `sprite` inside the `region`, e.g. a frame of a sprite sheet
+    pub fn sprite_region(&self, sprite: &Sprite, start_x: i32, start_y: i32, region: Rect) {
+        unsafe {
+            system::blitSub(
+                sprite.bytes.as_ptr(), start_x, start_y, region.width, region.height,
+                region.x as u32, region.y as u32, sprite.width, sprite.flags as u32,
+            );
+        }
+    }
+
+    /// Writes the pixel directly into the framebuffer, ignoring the draw colors.
+    /// Pixels outside of the screen and transparent ones are skipped.
+    pub fn set_pixel(&self, x: i32, y: i32, color: PaletteIndex) {
+        if x < 0 || y < 0 || x >= system::SCREEN_WIDTH as i32 || y >= system::SCREEN_HEIGHT as i32 {
+            return;
+        }
+        let Some(value) = (color as u8).checked_sub(1) else { return };
+        let index = (y as u32 * system::SCREEN_WIDTH + x as u32) as usize;
+        let shift = (index & 0b11) << 1;
+        unsafe {
+            let byte = &mut (*system::FRAMEBUFFER)[index >> 2];
+            *byte = (*byte & !(0b11 << shift)) | (value << shift);
+        }
+    }
+
+    /// Palette color of the pixel, `None` outside of the screen
+    pub fn get_pixel(&self, x: i32, y: i32) -> Option<PaletteIndex> {
+        if x < 0 || y < 0 || x >= system::SCREEN_WIDTH as i32 || y >= system::SCREEN_HEIGHT as i32 {
+            return None;
+        }
+        let index = (y as u32 * system::SCREEN_WIDTH + x as u32) as usize;
+        let value = unsafe { (*system::FRAMEBUFFER)[index >> 2] } >> ((index & 0b11) << 1) & 0b11;
+        PaletteIndex::try_from(value as u16 + 1).ok()
+    }
+
+    pub fn line_between(&self, start: Point, end: Point) {
+        self.line(start.x, start.y, end.x, end.y)
+    }
+
+    /// Oval inscribed in the `rect`
+    pub fn oval_in(&self, rect: Rect) {
+        self.oval(rect.x, rect.y, rect.width, rect.height)
+    }
+
+    pub fn rectangle_in(&self, rect: Rect) {
+        self.rectangle(rect.x, rect.y, rect.width, rect.height)
+    }
+
+    pub fn text_at(&self, text: &str, position: Point) {
+        self.text(text, position.x, position.y)
+    }
+
+    pub fn sprite_at(&self, sprite: &Sprite, position: Point) {
+        self.sprite(sprite, position.x, position.y)
+    }
+
     pub fn set_draw_colors(&self, palettes: [Option<PaletteIndex>; 4]) {
         let mut draw_colors = unsafe { *system::DRAW_COLORS };
         let mut set_draw_color = |draw_color: DrawColorIndex, palette| {
@@ -92,10 +153,10 @@ impl Framebuffer {
     pub fn get_draw_colors(&self) -> [PaletteIndex; 4] {
         let draw_colors = unsafe { *system::DRAW_COLORS };
         [
-            PaletteIndex::try_from(draw_colors & 0x000f >> DrawColorIndex::Index1.offset()).unwrap(),
-            PaletteIndex::try_from(draw_colors & 0x00f0 >> DrawColorIndex::Index2.offset()).unwrap(),
-            PaletteIndex::try_from(draw_colors & 0x0f00 >> DrawColorIndex::Index3.offset()).unwrap(),
-            PaletteIndex::try_from(draw_colors & 0xf000 >> DrawColorIndex::Index4.offset()).unwrap(),
+            PaletteIndex::try_from((draw_colors >> DrawColorIndex::Index1.offset()) & 0xf).unwrap(),
+            PaletteIndex::try_from((draw_colors >> DrawColorIndex::Index2.offset()) & 0xf).unwrap(),
+            PaletteIndex::try_from((draw_colors >> DrawColorIndex::Index3.offset()) & 0xf).unwrap(),
+            PaletteIndex::try_from((draw_colors >> DrawColorIndex::Index4.offset()) & 0xf).unwrap(),
         ]
     }
 
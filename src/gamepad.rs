@@ -3,13 +3,13 @@ use crate::system;
 
 #[derive(Eq, PartialEq)]
 pub struct Gamepad {
-    address: *const u8,
+    state: u8,
     last_state: u8
 }
 
 #[repr(u8)]
 #[allow(dead_code)]
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum GamepadButton {
     ButtonX = system::BUTTON_1,
     ButtonY = system::BUTTON_2,
@@ -19,32 +19,50 @@ pub enum GamepadButton {
     DPadDown = system::BUTTON_DOWN,
 }
 
+impl GamepadButton {
+    pub const ALL: [GamepadButton; 6] = [
+        GamepadButton::ButtonX,
+        GamepadButton::ButtonY,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+    ];
+}
+
 #[allow(dead_code)]
 impl Gamepad {
-    pub(crate) const unsafe fn new(address: *const u8) -> Self {
-        Gamepad { address, last_state: 0 }
+    pub(crate) const unsafe fn new() -> Self {
+        Gamepad { state: 0, last_state: 0 }
     }
 }
 
 impl Gamepad {
+    pub(crate) fn early_update(&mut self, state: u8) {
+        self.state = state;
+    }
+
     pub(crate) unsafe fn late_update(&mut self) {
-        unsafe {
-            self.last_state = *self.address;
-        }
+        self.last_state = self.state;
+    }
+
+    /// Bitmask of the buttons held down, made of `GamepadButton` values
+    pub fn buttons(&self) -> u8 {
+        self.state
     }
 
     pub fn is_pressed(&self, button: GamepadButton) -> bool {
-        let state = unsafe { *self.address };
+        let state = self.state;
         !Self::is_pressing(self.last_state, &button) && Self::is_pressing(state, &button)
     }
 
     pub fn is_held(&self, button: GamepadButton) -> bool {
-        let state = unsafe { *self.address };
+        let state = self.state;
         Self::is_pressing(state, &button)
     }
 
     pub fn is_released(&self, button: GamepadButton) -> bool {
-        let state = unsafe { *self.address };
+        let state = self.state;
         Self::is_pressing(self.last_state, &button) && !Self::is_pressing(state, &button)
     }
 
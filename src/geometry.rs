@@ -0,0 +1,447 @@
+//! Points, sizes, rectangles and vectors in screen coordinates, with the y axis pointing down.
+
+use core::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use crate::fixed::{isqrt, Fixed};
+
+/// Position of a pixel
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Integer displacement between two points
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Vector {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Displacement with fixed-point precision, e.g. for positions and velocities of moving objects
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct FixedVector {
+    pub x: Fixed,
+    pub y: Fixed,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Rectangle covering the pixels from `x` to `x + width - 1` and from `y` to `y + height - 1`
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Point {
+    pub const ZERO: Point = Point { x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// Vector from the origin to the point
+    pub const fn to_vector(self) -> Vector {
+        Vector { x: self.x, y: self.y }
+    }
+}
+
+impl Vector {
+    pub const ZERO: Vector = Vector { x: 0, y: 0 };
+
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub const fn dot(self, other: Vector) -> i32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    pub const fn length_squared(self) -> i32 {
+        self.dot(self)
+    }
+
+    pub const fn to_fixed(self) -> FixedVector {
+        FixedVector { x: Fixed::from_int(self.x), y: Fixed::from_int(self.y) }
+    }
+}
+
+impl FixedVector {
+    pub const ZERO: FixedVector = FixedVector { x: Fixed::ZERO, y: Fixed::ZERO };
+
+    pub const fn new(x: Fixed, y: Fixed) -> Self {
+        Self { x, y }
+    }
+
+    /// Vector of the `length` pointing at the `angle` in radians
+    pub fn from_angle(angle: Fixed, length: Fixed) -> Self {
+        Self { x: angle.cos() * length, y: angle.sin() * length }
+    }
+
+    /// Dot product, saturating outside of the range of `Fixed`
+    pub fn dot(self, other: FixedVector) -> Fixed {
+        let bits = self.wide_dot(other) >> Fixed::FRACTIONAL_BITS;
+        Fixed::from_bits(bits.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+
+    /// Square of the length, saturating above about 181 pixels
+    pub fn length_squared(self) -> Fixed {
+        self.dot(self)
+    }
+
+    /// Length, rounded down and saturating above the range of `Fixed`
+    pub fn length(self) -> Fixed {
+        let bits = isqrt(self.wide_dot(self) as u64);
+        Fixed::from_bits(bits.min(i32::MAX as u64) as i32)
+    }
+
+    /// Vector of length 1 in the same direction, or zero for the zero vector
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == Fixed::ZERO {
+            return Self::ZERO;
+        }
+        Self { x: self.x / length, y: self.y / length }
+    }
+
+    /// Angle in radians between -π and π
+    pub fn angle(self) -> Fixed {
+        self.y.atan2(self.x)
+    }
+
+    /// Pixel position of the vector from the origin, rounded down
+    pub const fn to_point(self) -> Point {
+        Point { x: self.x.to_int(), y: self.y.to_int() }
+    }
+
+    /// Dot product with twice the fractional bits, which can't overflow
+    fn wide_dot(self, other: FixedVector) -> i64 {
+        self.x.to_bits() as i64 * other.x.to_bits() as i64 + self.y.to_bits() as i64 * other.y.to_bits() as i64
+    }
+}
+
+impl Size {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    pub const fn area(self) -> u32 {
+        self.width * self.height
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub const fn from_origin_size(origin: Point, size: Size) -> Self {
+        Self { x: origin.x, y: origin.y, width: size.width, height: size.height }
+    }
+
+    /// Smallest rectangle containing both points
+    pub const fn from_corners(a: Point, b: Point) -> Self {
+        let (left, right) = if a.x < b.x { (a.x, b.x) } else { (b.x, a.x) };
+        let (top, bottom) = if a.y < b.y { (a.y, b.y) } else { (b.y, a.y) };
+        Self::new(left, top, (right - left + 1) as u32, (bottom - top + 1) as u32)
+    }
+
+    /// Rectangle of the `size` centered on the `center`
+    pub const fn centered(center: Point, size: Size) -> Self {
+        Self::new(center.x - (size.width / 2) as i32, center.y - (size.height / 2) as i32, size.width, size.height)
+    }
+
+    pub const fn origin(&self) -> Point {
+        Point { x: self.x, y: self.y }
+    }
+
+    pub const fn size(&self) -> Size {
+        Size { width: self.width, height: self.height }
+    }
+
+    pub const fn left(&self) -> i32 {
+        self.x
+    }
+
+    pub const fn top(&self) -> i32 {
+        self.y
+    }
+
+    /// Coordinate just past the right edge
+    pub const fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    /// Coordinate just past the bottom edge
+    pub const fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    pub const fn center(&self) -> Point {
+        Point { x: self.x + (self.width / 2) as i32, y: self.y + (self.height / 2) as i32 }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    pub const fn contains(&self, point: Point) -> bool {
+        point.x >= self.left() && point.x < self.right() && point.y >= self.top() && point.y < self.bottom()
+    }
+
+    pub const fn contains_rect(&self, other: &Rect) -> bool {
+        other.left() >= self.left() && other.right() <= self.right()
+            && other.top() >= self.top() && other.bottom() <= self.bottom()
+    }
+
+    /// Whether the rectangles share at least one pixel
+    pub const fn intersects(&self, other: &Rect) -> bool {
+        !self.is_empty() && !other.is_empty()
+            && self.left() < other.right() && other.left() < self.right()
+            && self.top() < other.bottom() && other.top() < self.bottom()
+    }
+
+    /// Pixels shared by both rectangles, `None` when there are none
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let left = self.left().max(other.left());
+        let top = self.top().max(other.top());
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        Some(Rect::new(left, top, (right - left) as u32, (bottom - top) as u32))
+    }
+
+    /// Smallest rectangle containing both rectangles, ignoring an empty one
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let left = self.left().min(other.left());
+        let top = self.top().min(other.top());
+        let right = self.right().max(other.right());
+        let bottom = self.bottom().max(other.bottom());
+        Rect::new(left, top, (right - left) as u32, (bottom - top) as u32)
+    }
+
+    pub const fn translate(&self, offset: Vector) -> Rect {
+        Rect::new(self.x + offset.x, self.y + offset.y, self.width, self.height)
+    }
+
+    /// Rectangle grown by the `amount` on every side, or shrunk for a negative amount
+    pub fn inflate(&self, amount: i32) -> Rect {
+        let width = (self.width as i32 + amount * 2).max(0);
+        let height = (self.height as i32 + amount * 2).max(0);
+        Rect::new(self.x - amount, self.y - amount, width as u32, height as u32)
+    }
+
+    /// The nearest point inside the rectangle, e.g. to keep a cursor on the screen
+    pub fn clamp_point(&self, point: Point) -> Point {
+        Point {
+            x: point.x.clamp(self.left(), (self.right() - 1).max(self.left())),
+            y: point.y.clamp(self.top(), (self.bottom() - 1).max(self.top())),
+        }
+    }
+
+    /// The `other` rectangle moved the least to fit inside, aligned to the top left when it's larger
+    pub fn clamp_rect(&self, other: &Rect) -> Rect {
+        let x = other.x.min(self.right() - other.width as i32).max(self.left());
+        let y = other.y.min(self.bottom() - other.height as i32).max(self.top());
+        Rect::new(x, y, other.width, other.height)
+    }
+}
+
+impl Add<Vector> for Point {
+    type Output = Point;
+
+    fn add(self, other: Vector) -> Point {
+        Point { x: self.x + other.x, y: self.y + other.y }
+    }
+}
+
+impl Sub<Vector> for Point {
+    type Output = Point;
+
+    fn sub(self, other: Vector) -> Point {
+        Point { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl Sub for Point {
+    type Output = Vector;
+
+    fn sub(self, other: Point) -> Vector {
+        Vector { x: self.x - other.x, y: self.y - other.y }
+    }
+}
+
+impl AddAssign<Vector> for Point {
+    fn add_assign(&mut self, other: Vector) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign<Vector> for Point {
+    fn sub_assign(&mut self, other: Vector) {
+        *self = *self - other;
+    }
+}
+
+macro_rules! vector_ops {
+    ($name:ident, $scalar:ty) => {
+        impl Add for $name {
+            type Output = $name;
+
+            fn add(self, other: $name) -> $name {
+                $name { x: self.x + other.x, y: self.y + other.y }
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+
+            fn sub(self, other: $name) -> $name {
+                $name { x: self.x - other.x, y: self.y - other.y }
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name { x: -self.x, y: -self.y }
+            }
+        }
+
+        impl Mul<$scalar> for $name {
+            type Output = $name;
+
+            fn mul(self, scalar: $scalar) -> $name {
+                $name { x: self.x * scalar, y: self.y * scalar }
+            }
+        }
+
+        impl Div<$scalar> for $name {
+            type Output = $name;
+
+            fn div(self, scalar: $scalar) -> $name {
+                $name { x: self.x / scalar, y: self.y / scalar }
+            }
+        }
+
+        impl AddAssign for $name {
+            fn add_assign(&mut self, other: $name) {
+                *self = *self + other;
+            }
+        }
+
+        impl SubAssign for $name {
+            fn sub_assign(&mut self, other: $name) {
+                *self = *self - other;
+            }
+        }
+    };
+}
+
+vector_ops!(Vector, i32);
+vector_ops!(FixedVector, Fixed);
+
+impl From<Vector> for FixedVector {
+    fn from(vector: Vector) -> Self {
+        vector.to_fixed()
+    }
+}
+
+impl From<(i32, i32)> for Point {
+    fn from((x, y): (i32, i32)) -> Self {
+        Point { x, y }
+    }
+}
+
+impl From<(u32, u32)> for Size {
+    fn from((width, height): (u32, u32)) -> Self {
+        Size { width, height }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_vector(x: i32, y: i32) -> FixedVector {
+        Vector::new(x, y).to_fixed()
+    }
+
+    #[test]
+    fn length_of_a_long_vector_does_not_overflow() {
+        let vector = fixed_vector(160, 160);
+        // √51200 = 226.274
+        assert_eq!(vector.length().to_int(), 226);
+        assert_eq!(vector.length().to_bits(), 14829104);
+        assert_eq!(vector.length_squared(), Fixed::MAX);
+        assert_eq!(vector.dot(-vector), Fixed::MIN);
+        let normalized = vector.normalize();
+        assert_eq!(normalized.x, normalized.y);
+        assert_eq!(normalized.x.to_bits(), 46340);
+    }
+
+    #[test]
+    fn fixed_vectors_measure_with_full_precision() {
+        assert_eq!(fixed_vector(3, 4).length(), Fixed::from_int(5));
+        assert_eq!(fixed_vector(3, 4).dot(fixed_vector(-2, 1)), Fixed::from_int(-2));
+        let small = FixedVector::new(Fixed::from_ratio(1, 256), Fixed::ZERO);
+        assert_eq!(small.length(), small.x);
+        assert_eq!(FixedVector::ZERO.normalize(), FixedVector::ZERO);
+        assert_eq!(fixed_vector(0, -7).normalize(), fixed_vector(0, -1));
+        assert_eq!(FixedVector::new(Fixed::from_ratio(-3, 2), Fixed::HALF).to_point(), Point::new(-2, 0));
+    }
+
+    #[test]
+    fn rect_edges_are_exclusive() {
+        let rect = Rect::new(10, 20, 5, 3);
+        assert_eq!((rect.right(), rect.bottom()), (15, 23));
+        assert!(rect.contains(Point::new(14, 22)));
+        assert!(!rect.contains(Point::new(15, 22)));
+        assert!(rect.intersects(&Rect::new(14, 22, 1, 1)));
+        assert!(!rect.intersects(&Rect::new(15, 20, 1, 1)));
+        assert!(!rect.intersects(&Rect::new(12, 21, 0, 1)));
+        assert_eq!(Rect::from_corners(Point::new(4, 9), Point::new(1, 2)), Rect::new(1, 2, 4, 8));
+        assert_eq!(Rect::centered(Point::new(10, 10), Size::new(4, 5)), Rect::new(8, 8, 4, 5));
+    }
+
+    #[test]
+    fn rects_combine() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, -5, 10, 10);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 0, 5, 5)));
+        assert_eq!(a.intersection(&Rect::new(10, 0, 5, 5)), None);
+        assert_eq!(a.union(&b), Rect::new(0, -5, 15, 15));
+        assert_eq!(a.union(&Rect::new(50, 50, 0, 0)), a);
+        assert_eq!(a.inflate(-6), Rect::new(6, 6, 0, 0));
+        assert!(a.contains_rect(&a.inflate(-1)));
+        assert!(!a.contains_rect(&b));
+    }
+
+    #[test]
+    fn clamping_keeps_inside() {
+        let screen = Rect::new(0, 0, 160, 160);
+        assert_eq!(screen.clamp_point(Point::new(-4, 200)), Point::new(0, 159));
+        assert_eq!(screen.clamp_rect(&Rect::new(155, -3, 10, 10)), Rect::new(150, 0, 10, 10));
+        assert_eq!(screen.clamp_rect(&Rect::new(20, 20, 200, 10)), Rect::new(0, 20, 200, 10));
+    }
+}
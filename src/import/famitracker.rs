@@ -0,0 +1,402 @@
+//! FamiTracker text export import
+//!
+//! The first four channels of the 2A03 chip have direct equivalents in WASM-4:
+//! two pulse channels, triangle and noise. The DPCM channel and expansion chips are skipped.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::vec::Vec;
+
+use crate::audio::{Channel, DutyCycle};
+use crate::import::{ImportError, ImportedSong, TimedNote};
+
+const CHANNELS: [Channel; 4] = [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise];
+
+/// Lowest MIDI note used for the noise channel, the 16 noise pitches are spread every 6 semitones above it
+const NOISE_BASE_NOTE: u8 = 24;
+
+#[derive(Clone, Debug)]
+pub struct FamiTrackerOptions {
+    /// Index of the track (song) in the module, counted from 0
+    pub track: usize,
+    /// Duty cycle for the pulse channels
+    pub duty_cycle: DutyCycle,
+}
+
+impl Default for FamiTrackerOptions {
+    fn default() -> Self {
+        Self { track: 0, duty_cycle: DutyCycle::OneHalf }
+    }
+}
+
+/// Converts a FamiTracker text export into a song
+pub fn import(text: &str, options: &FamiTrackerOptions) -> Result<ImportedSong, ImportError> {
+    let module = Module::parse(text, options.track)?;
+    let mut song = ImportedSong::new(0, true);
+    let mut channels: [ChannelState; 4] = Default::default();
+    let mut speed = module.speed.max(1);
+    let mut tempo = module.tempo.max(1);
+    // Position in 1/1000 of a frame, rows don't have to last a whole number of frames
+    let mut position = 0u64;
+    let mut order_index = 0;
+    let mut start_row = 0;
+    let mut visited = Vec::new();
+
+    'song: while let Some(order) = module.orders.get(order_index) {
+        if visited.contains(&(order_index, start_row)) {
+            break;
+        }
+        visited.push((order_index, start_row));
+        let mut next = (order_index + 1, 0);
+
+        for row_index in start_row..module.pattern_length {
+            let frame = (position / 1000) as u32;
+            let mut jump = None;
+            let mut halt = false;
+            for (column, pattern) in order.iter().enumerate() {
+                let Some(cell) = module.patterns.get(&(column, *pattern, row_index)) else { continue };
+                if let Some(state) = channels.get_mut(column) {
+                    state.apply(cell, frame, column);
+                } else if cell.note != Cell::EMPTY_NOTE {
+                    song.warn_unsupported("DPCM and expansion channels");
+                }
+                for (effect, value) in &cell.effects {
+                    match (effect, *value) {
+                        ('F', value) if value < module.split => speed = value.max(1) as u64,
+                        ('F', value) => tempo = value.max(1) as u64,
+                        ('B', value) => jump = Some((value as usize, 0)),
+                        ('D', value) => jump = Some((order_index + 1, value as usize)),
+                        ('C', _) => halt = true,
+                        (effect, _) => song.warn_unsupported(&format!("effect {}", effect)),
+                    }
+                }
+            }
+            position += speed * 150 * 1000 / tempo;
+            if halt {
+                song.looping = false;
+                break 'song;
+            }
+            if let Some(jump) = jump {
+                next = jump;
+                break;
+            }
+        }
+
+        (order_index, start_row) = next;
+        if order_index >= module.orders.len() {
+            (order_index, start_row) = (0, 0);
+        }
+    }
+
+    song.length = (position / 1000) as u32;
+    for (channel, state) in CHANNELS.iter().zip(channels.iter_mut()) {
+        state.release(song.length);
+        if !state.notes.is_empty() {
+            song.add_track(*channel, options.duty_cycle, core::mem::take(&mut state.notes));
+        }
+    }
+    Ok(song)
+}
+
+struct ChannelState {
+    notes: Vec<TimedNote>,
+    sounding: Option<TimedNote>,
+    volume: u8,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        Self { notes: Vec::new(), sounding: None, volume: 100 }
+    }
+}
+
+impl ChannelState {
+    fn apply(&mut self, cell: &Cell, frame: u32, column: usize) {
+        if let Some(volume) = cell.volume {
+            self.volume = (volume as u32 * 100 / 15) as u8;
+        }
+        match cell.note {
+            Cell::EMPTY_NOTE => {}
+            Cell::NOTE_CUT | Cell::NOTE_RELEASE => self.release(frame),
+            midi => {
+                self.release(frame);
+                let midi = if column == 3 { NOISE_BASE_NOTE + midi * 6 } else { midi };
+                self.sounding = Some(TimedNote { start: frame, end: frame, midi, volume: self.volume });
+            }
+        }
+    }
+
+    fn release(&mut self, frame: u32) {
+        if let Some(mut note) = self.sounding.take() {
+            note.end = frame;
+            self.notes.push(note);
+        }
+    }
+}
+
+struct Cell {
+    /// MIDI note, noise pitch for the noise channel, or one of the special values
+    note: u8,
+    /// Whether the note is a noise pitch like `A-#`
+    noise: bool,
+    volume: Option<u8>,
+    effects: Vec<(char, u8)>,
+}
+
+impl Cell {
+    const EMPTY_NOTE: u8 = 0xff;
+    const NOTE_CUT: u8 = 0xfe;
+    const NOTE_RELEASE: u8 = 0xfd;
+
+    /// Parses a column like `C#4 00 F 4A0`
+    fn parse(text: &str) -> Option<Self> {
+        let mut fields = text.split_whitespace();
+        let (note, noise) = Self::parse_note(fields.next()?)?;
+        let _instrument = fields.next()?;
+        let volume = match fields.next()? {
+            "." => None,
+            volume => Some(u8::from_str_radix(volume, 16).ok()?),
+        };
+        let mut effects = Vec::new();
+        for effect in fields {
+            if effect.chars().all(|c| c == '.') {
+                continue;
+            }
+            let mut chars = effect.chars();
+            let kind = chars.next()?;
+            let value = u8::from_str_radix(chars.as_str(), 16).ok()?;
+            effects.push((kind, value));
+        }
+        Some(Self { note, noise, volume, effects })
+    }
+
+    /// Rejects the values out of range, `channel` being the index of the column
+    fn check(&self, channel: usize) -> Result<(), ImportError> {
+        if self.volume.is_some_and(|volume| volume > 0xf) {
+            return Err(ImportError::Malformed("volume"));
+        }
+        if matches!(self.note, Self::EMPTY_NOTE | Self::NOTE_CUT | Self::NOTE_RELEASE) || self.noise {
+            return Ok(());
+        }
+        if channel == 3 {
+            return Err(ImportError::Malformed("noise pitch"));
+        }
+        if self.note > 127 {
+            return Err(ImportError::Malformed("note"));
+        }
+        Ok(())
+    }
+
+    /// Note value and whether it's a noise pitch
+    fn parse_note(text: &str) -> Option<(u8, bool)> {
+        match text {
+            "..." => return Some((Self::EMPTY_NOTE, false)),
+            "---" => return Some((Self::NOTE_CUT, false)),
+            "===" => return Some((Self::NOTE_RELEASE, false)),
+            _ => {}
+        }
+        let bytes = text.as_bytes();
+        if bytes.len() != 3 {
+            return None;
+        }
+        if bytes[2] == b'#' {
+            return (bytes[0] as char).to_digit(16).map(|pitch| (pitch as u8, true));
+        }
+        let semitone = match bytes[0] {
+            b'C' => 0,
+            b'D' => 2,
+            b'E' => 4,
+            b'F' => 5,
+            b'G' => 7,
+            b'A' => 9,
+            b'B' => 11,
+            _ => return None,
+        } + match bytes[1] {
+            b'-' => 0,
+            b'#' => 1,
+            _ => return None,
+        };
+        let octave = (bytes[2] as char).to_digit(10)? as u8;
+        // FamiTracker octave 0 starts at C0 in scientific pitch notation, MIDI note 12
+        Some(((octave + 1) * 12 + semitone, false))
+    }
+}
+
+struct Module {
+    pattern_length: usize,
+    speed: u64,
+    tempo: u64,
+    split: u8,
+    /// Pattern index of every channel for each frame of the order list
+    orders: Vec<Vec<u8>>,
+    /// Cells by channel, pattern and row
+    patterns: BTreeMap<(usize, u8, usize), Cell>,
+}
+
+impl Module {
+    fn parse(text: &str, track: usize) -> Result<Self, ImportError> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, header)) if header.starts_with("# FamiTracker text export") => {}
+            _ => return Err(ImportError::InvalidHeader),
+        }
+
+        let mut module = Module {
+            pattern_length: 0,
+            speed: 6,
+            tempo: 150,
+            split: 32,
+            orders: Vec::new(),
+            patterns: BTreeMap::new(),
+        };
+        let mut track_index = None;
+        let mut pattern = 0u8;
+
+        for (number, line) in lines {
+            let invalid = || ImportError::InvalidLine(number + 1);
+            let line = line.trim();
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match keyword {
+                "SPLIT" => module.split = rest.trim().parse().map_err(|_| invalid())?,
+                "TRACK" => {
+                    track_index = Some(track_index.map_or(0, |index| index + 1));
+                    if track_index == Some(track) {
+                        let mut values = rest.split_whitespace();
+                        let mut next = || values.next().and_then(|value| value.parse().ok()).ok_or_else(invalid);
+                        module.pattern_length = next()? as usize;
+                        module.speed = next()?;
+                        module.tempo = next()?;
+                    }
+                }
+                _ if track_index != Some(track) => {}
+                "ORDER" => {
+                    let (_, patterns) = rest.split_once(':').ok_or_else(invalid)?;
+                    let patterns = patterns.split_whitespace()
+                        .map(|value| u8::from_str_radix(value, 16).map_err(|_| invalid()))
+                        .collect::<Result<Vec<u8>, ImportError>>()?;
+                    module.orders.push(patterns);
+                }
+                "PATTERN" => pattern = u8::from_str_radix(rest.trim(), 16).map_err(|_| invalid())?,
+                "ROW" => {
+                    let mut columns = rest.split(':');
+                    let row = usize::from_str_radix(columns.next().ok_or_else(invalid)?.trim(), 16)
+                        .map_err(|_| invalid())?;
+                    for (channel, column) in columns.enumerate() {
+                        let cell = Cell::parse(column).ok_or_else(invalid)?;
+                        cell.check(channel)?;
+                        module.patterns.insert((channel, pattern, row), cell);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if track_index.is_none_or(|index| index < track) {
+            return Err(ImportError::Unsupported("track index past the last track"));
+        }
+        Ok(module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+
+    use super::*;
+    use crate::import::ImportWarning;
+    use crate::note::Note;
+    use crate::sequencer::SongNote;
+
+    /// Export with one track of four rows, `rows` being the pulse and noise columns of each
+    fn export(split: u8, rows: [(&str, &str); 4]) -> String {
+        let mut text = String::from("# FamiTracker text export 0.4.2\n\n");
+        text += &format!("SPLIT {split}\n\nTRACK   4   6 150 \"Song\"\nCOLUMNS : 1 1 1 1 1\n\n");
+        text += "ORDER 00 : 00 00 00 00 00\n\nPATTERN 00\n";
+        for (row, (pulse, noise)) in rows.iter().enumerate() {
+            text += &format!("ROW {row:02} : {pulse} : ... .. . ... : ... .. . ... : {noise} : ... .. . ...\n");
+        }
+        text
+    }
+
+    const ROWS: [(&str, &str); 4] = [
+        ("C-4 00 F ...", "1-# 00 8 ..."),
+        ("... .. . F03", "... .. . ..."),
+        ("--- .. . ...", "... .. . ..."),
+        ("D-4 00 . ...", "... .. . ..."),
+    ];
+
+    #[test]
+    fn rows_follow_the_speed_changes() {
+        let song = import(&export(32, ROWS), &FamiTrackerOptions::default()).unwrap();
+        // 6 frames for the first row, 3 for the others after F03
+        assert_eq!(song.length, 15);
+        assert!(song.looping);
+        assert_eq!(song.tracks[0].channel, Channel::Pulse1);
+        assert_eq!(song.tracks[0].notes, [
+            SongNote { delay: 0, note: Note::C4, duration: 9, volume: 100 },
+            SongNote { delay: 12, note: Note::from_midi(62), duration: 3, volume: 100 },
+        ]);
+        assert_eq!(song.tracks[1].channel, Channel::Noise);
+        assert_eq!(song.tracks[1].notes, [SongNote { delay: 0, note: Note::from_midi(30), duration: 15, volume: 53 }]);
+    }
+
+    #[test]
+    fn tempo_changes_above_the_split() {
+        let mut rows = ROWS;
+        rows[1].0 = "... .. . F4B";
+        let song = import(&export(32, rows), &FamiTrackerOptions::default()).unwrap();
+        // Rows last 6 * 150 / 75 = 12 frames after the tempo is halved
+        assert_eq!(song.length, 6 + 12 * 3);
+    }
+
+    #[test]
+    fn zero_tempo_is_clamped() {
+        let mut rows = ROWS;
+        rows[1].0 = "... .. . F00";
+        let song = import(&export(0, rows), &FamiTrackerOptions::default()).unwrap();
+        assert_eq!(song.length, 6 + 6 * 150 * 3);
+    }
+
+    #[test]
+    fn halt_ends_the_song_without_looping() {
+        let mut rows = ROWS;
+        rows[2].0 = "--- .. . C00";
+        let song = import(&export(32, rows), &FamiTrackerOptions::default()).unwrap();
+        assert!(!song.looping);
+        assert_eq!(song.length, 12);
+    }
+
+    #[test]
+    fn unsupported_effects_are_counted() {
+        let mut rows = ROWS;
+        rows[0].0 = "C-4 00 F 4A0";
+        rows[3].0 = "D-4 00 . 401";
+        let song = import(&export(32, rows), &FamiTrackerOptions::default()).unwrap();
+        assert_eq!(song.warnings, [ImportWarning::Unsupported { feature: String::from("effect 4"), occurrences: 2 }]);
+    }
+
+    #[test]
+    fn errors() {
+        let options = FamiTrackerOptions::default();
+        assert_eq!(import("TRACK 4 6 150", &options).unwrap_err(), ImportError::InvalidHeader);
+        let mut rows = ROWS;
+        rows[2].0 = "H-4 00 . ...";
+        assert_eq!(import(&export(32, rows), &options).unwrap_err(), ImportError::InvalidLine(13));
+        let options = FamiTrackerOptions { track: 1, ..Default::default() };
+        assert!(matches!(import(&export(32, ROWS), &options), Err(ImportError::Unsupported(_))));
+    }
+
+    #[test]
+    fn values_out_of_range_are_errors() {
+        let options = FamiTrackerOptions::default();
+        let mut rows = ROWS;
+        rows[3].0 = "B-9 00 . ...";
+        assert_eq!(import(&export(32, rows), &options).unwrap_err(), ImportError::Malformed("note"));
+        let mut rows = ROWS;
+        rows[3].0 = "D-4 00 FF ...";
+        assert_eq!(import(&export(32, rows), &options).unwrap_err(), ImportError::Malformed("volume"));
+        let mut rows = ROWS;
+        rows[3].1 = "C-4 00 . ...";
+        assert_eq!(import(&export(32, rows), &options).unwrap_err(), ImportError::Malformed("noise pitch"));
+    }
+}
@@ -0,0 +1,401 @@
+//! Standard MIDI File (format 0 and 1) import
+
+use alloc::vec::Vec;
+
+use crate::audio::{Channel, DutyCycle};
+use crate::import::{ImportError, ImportWarning, ImportedSong, TimedNote};
+
+/// MIDI channel reserved for percussion by General MIDI, counted from 0
+pub const PERCUSSION_CHANNEL: u8 = 9;
+
+#[derive(Clone, Debug)]
+pub struct MidiOptions {
+    /// WASM-4 channel for each of the 16 MIDI channels, `None` skips the notes of the channel.
+    /// When the whole mapping is `None`, channels are assigned automatically:
+    /// the percussion channel goes to `Noise` and the first three other channels with notes,
+    /// in order of their first note, go to `Pulse1`, `Pulse2` and `Triangle`.
+    pub mapping: [Option<Channel>; 16],
+    /// Duty cycle for the pulse channels
+    pub duty_cycle: DutyCycle,
+    pub looping: bool,
+}
+
+impl Default for MidiOptions {
+    fn default() -> Self {
+        Self {
+            mapping: [None; 16],
+            duty_cycle: DutyCycle::OneHalf,
+            looping: false,
+        }
+    }
+}
+
+/// Converts the bytes of a Standard MIDI File into a song
+pub fn import(bytes: &[u8], options: &MidiOptions) -> Result<ImportedSong, ImportError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(4)? != b"MThd" {
+        return Err(ImportError::InvalidHeader);
+    }
+    let header_length = reader.u32()? as usize;
+    let mut header = Reader { bytes: reader.take(header_length)?, position: 0 };
+    let format = header.u16()?;
+    let track_count = header.u16()?;
+    let division = header.u16()?;
+    if format > 1 {
+        return Err(ImportError::Unsupported("MIDI format 2"));
+    }
+
+    let mut events = Events::default();
+    for _ in 0..track_count {
+        let chunk_type = reader.take(4)?;
+        let chunk_length = reader.u32()? as usize;
+        let chunk = reader.take(chunk_length)?;
+        if chunk_type == b"MTrk" {
+            read_track(chunk, &mut events)?;
+        }
+    }
+
+    let timing = Timing::new(division, events.tempos)?;
+    let mut notes_by_source: [Vec<TimedNote>; 16] = Default::default();
+    let mut first_note_tick = [u32::MAX; 16];
+    for note in &events.notes {
+        let source = note.channel as usize;
+        first_note_tick[source] = first_note_tick[source].min(note.start);
+        notes_by_source[source].push(TimedNote {
+            start: timing.frame(note.start),
+            end: timing.frame(note.end),
+            midi: note.key,
+            volume: (note.velocity as u32 * 100 / 127) as u8,
+        });
+    }
+
+    let mapping = if options.mapping.iter().all(Option::is_none) {
+        automatic_mapping(&first_note_tick)
+    } else {
+        options.mapping
+    };
+
+    // The end of track keeps the rests after the last note
+    let length = events.notes.iter().map(|note| note.end).max().unwrap_or(0).max(events.end);
+    let length = timing.frame(length);
+    let mut song = ImportedSong::new(length, options.looping);
+    for channel in [Channel::Pulse1, Channel::Pulse2, Channel::Triangle, Channel::Noise] {
+        let notes: Vec<TimedNote> = mapping.iter()
+            .zip(notes_by_source.iter())
+            .filter(|(mapped, _)| **mapped == Some(channel))
+            .flat_map(|(_, notes)| notes.iter().copied())
+            .collect();
+        if !notes.is_empty() {
+            song.add_track(channel, options.duty_cycle, notes);
+        }
+    }
+    for (source, notes) in notes_by_source.iter().enumerate() {
+        if mapping[source].is_none() && !notes.is_empty() {
+            song.warnings.push(ImportWarning::UnmappedSource { source: source as u8, notes: notes.len() });
+        }
+    }
+    Ok(song)
+}
+
+fn automatic_mapping(first_note_tick: &[u32; 16]) -> [Option<Channel>; 16] {
+    let mut mapping = [None; 16];
+    if first_note_tick[PERCUSSION_CHANNEL as usize] != u32::MAX {
+        mapping[PERCUSSION_CHANNEL as usize] = Some(Channel::Noise);
+    }
+    let mut melodic: Vec<usize> = (0..16)
+        .filter(|source| *source != PERCUSSION_CHANNEL as usize && first_note_tick[*source] != u32::MAX)
+        .collect();
+    melodic.sort_by_key(|source| first_note_tick[*source]);
+    for (source, channel) in melodic.into_iter().zip([Channel::Pulse1, Channel::Pulse2, Channel::Triangle]) {
+        mapping[source] = Some(channel);
+    }
+    mapping
+}
+
+#[derive(Default)]
+struct Events {
+    notes: Vec<MidiNote>,
+    /// Tick and microseconds per quarter note of every tempo change
+    tempos: Vec<(u32, u32)>,
+    /// Tick of the latest end of track
+    end: u32,
+}
+
+struct MidiNote {
+    start: u32,
+    end: u32,
+    channel: u8,
+    key: u8,
+    velocity: u8,
+}
+
+fn read_track(chunk: &[u8], events: &mut Events) -> Result<(), ImportError> {
+    let mut reader = Reader { bytes: chunk, position: 0 };
+    let mut tick = 0u32;
+    let mut running_status = 0u8;
+    // Start tick and velocity of the sounding note for every channel and key
+    let mut sounding: Vec<(u8, u8, u32, u8)> = Vec::new();
+
+    while !reader.is_at_end() {
+        tick = tick.checked_add(reader.variable_length()?).ok_or(ImportError::Malformed("delta time"))?;
+        let mut status = reader.u8()?;
+        if status < 0x80 {
+            if running_status == 0 {
+                return Err(ImportError::Unsupported("running status without a previous status"));
+            }
+            status = running_status;
+            reader.position -= 1;
+        }
+
+        match status {
+            0xff => {
+                let meta_type = reader.u8()?;
+                let length = reader.variable_length()? as usize;
+                let data = reader.take(length)?;
+                if meta_type == 0x51 && data.len() == 3 {
+                    let tempo = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+                    events.tempos.push((tick, tempo));
+                } else if meta_type == 0x2f {
+                    events.end = events.end.max(tick);
+                    break;
+                }
+            }
+            0xf0 | 0xf7 => {
+                let length = reader.variable_length()? as usize;
+                reader.take(length)?;
+                running_status = 0;
+            }
+            0xf1..=0xfe => return Err(ImportError::Unsupported("system message in a file")),
+            _ => {
+                running_status = status;
+                let channel = status & 0x0f;
+                let data1 = reader.data_byte()?;
+                let data2 = match status & 0xf0 {
+                    0xc0 | 0xd0 => 0,
+                    _ => reader.data_byte()?,
+                };
+                let is_note_on = status & 0xf0 == 0x90 && data2 > 0;
+                let is_note_off = status & 0xf0 == 0x80 || (status & 0xf0 == 0x90 && data2 == 0);
+                if is_note_on || is_note_off {
+                    if let Some(index) = sounding.iter().position(|(c, k, _, _)| *c == channel && *k == data1) {
+                        let (_, _, start, velocity) = sounding.swap_remove(index);
+                        events.notes.push(MidiNote { start, end: tick, channel, key: data1, velocity });
+                    }
+                }
+                if is_note_on {
+                    sounding.push((channel, data1, tick, data2));
+                }
+            }
+        }
+    }
+
+    for (channel, key, start, velocity) in sounding {
+        events.notes.push(MidiNote { start, end: tick, channel, key, velocity });
+    }
+    Ok(())
+}
+
+/// Conversion of MIDI ticks into frames, following the tempo changes
+struct Timing {
+    ticks_per_quarter: u64,
+    /// Ticks per second for SMPTE timing, where tempo changes don't apply
+    ticks_per_second: Option<u64>,
+    tempos: Vec<(u32, u32)>,
+}
+
+impl Timing {
+    /// Tempo of a file without any tempo changes, 120 beats per minute
+    const DEFAULT_TEMPO: u32 = 500_000;
+    const FRAMES_PER_SECOND: u64 = 60;
+
+    fn new(division: u16, mut tempos: Vec<(u32, u32)>) -> Result<Self, ImportError> {
+        tempos.sort_by_key(|(tick, _)| *tick);
+        if division & 0x8000 == 0 {
+            return Ok(Self { ticks_per_quarter: division.max(1) as u64, ticks_per_second: None, tempos });
+        }
+        // Negative SMPTE frame rate, -24, -25, -29 or -30
+        let frames_per_second = ((division >> 8) as u8 as i8).checked_neg()
+            .filter(|frames_per_second| *frames_per_second > 0)
+            .ok_or(ImportError::Malformed("SMPTE frame rate"))? as u64;
+        let ticks_per_frame = (division & 0xff).max(1) as u64;
+        Ok(Self { ticks_per_quarter: 1, ticks_per_second: Some(frames_per_second * ticks_per_frame), tempos })
+    }
+
+    fn frame(&self, tick: u32) -> u32 {
+        if let Some(ticks_per_second) = self.ticks_per_second {
+            return ((tick as u64 * Self::FRAMES_PER_SECOND + ticks_per_second / 2) / ticks_per_second) as u32;
+        }
+        let mut microseconds = 0u64;
+        let mut last_tick = 0u32;
+        let mut tempo = Self::DEFAULT_TEMPO;
+        for (change_tick, change_tempo) in &self.tempos {
+            if *change_tick >= tick {
+                break;
+            }
+            microseconds += (*change_tick - last_tick) as u64 * tempo as u64;
+            last_tick = *change_tick;
+            tempo = *change_tempo;
+        }
+        microseconds += (tick - last_tick) as u64 * tempo as u64;
+        let divisor = self.ticks_per_quarter * 1_000_000;
+        ((microseconds * Self::FRAMES_PER_SECOND + divisor / 2) / divisor) as u32
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_at_end(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], ImportError> {
+        let end = self.position.checked_add(length).ok_or(ImportError::UnexpectedEnd)?;
+        let slice = self.bytes.get(self.position..end).ok_or(ImportError::UnexpectedEnd)?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, ImportError> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Data byte of a channel message, like a key or a velocity, between 0 and 127
+    fn data_byte(&mut self) -> Result<u8, ImportError> {
+        let byte = self.u8()?;
+        if byte >= 0x80 {
+            return Err(ImportError::Malformed("data byte"));
+        }
+        Ok(byte)
+    }
+
+    fn u16(&mut self) -> Result<u16, ImportError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, ImportError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn variable_length(&mut self) -> Result<u32, ImportError> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ImportError::Unsupported("variable-length quantity longer than 4 bytes"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+    use crate::note::Note;
+    use crate::sequencer::SongNote;
+
+    /// Standard MIDI File of format 0 with the single `track`
+    fn file(division: u16, track: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(b"MThd");
+        bytes.extend_from_slice(&6u32.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 1]);
+        bytes.extend_from_slice(&division.to_be_bytes());
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(track);
+        bytes
+    }
+
+    /// 96 ticks per quarter note, 120 then 60 beats per minute, ending with a rest
+    const TRACK: [u8; 42] = [
+        0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20,
+        0x00, 0x90, 60, 100,
+        0x60, 0x80, 60, 0,
+        0x00, 0x90, 64, 127,
+        // Note off by a note on without velocity, with running status
+        0x30, 64, 0,
+        0x00, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40,
+        0x60, 0x90, 67, 80,
+        0x60, 0x80, 67, 64,
+        0x81, 0x40, 0xff, 0x2f, 0x00,
+    ];
+
+    #[test]
+    fn notes_follow_the_tempo_changes() {
+        let song = import(&file(96, &TRACK), &MidiOptions::default()).unwrap();
+        assert_eq!(song.tracks.len(), 1);
+        assert_eq!(song.tracks[0].channel, Channel::Pulse1);
+        assert_eq!(song.tracks[0].notes, [
+            SongNote { delay: 0, note: Note::C4, duration: 30, volume: 78 },
+            SongNote { delay: 30, note: Note::from_midi(64), duration: 15, volume: 100 },
+            SongNote { delay: 75, note: Note::from_midi(67), duration: 60, volume: 62 },
+        ]);
+        assert!(song.warnings.is_empty());
+    }
+
+    #[test]
+    fn length_includes_the_rest_before_the_end_of_track() {
+        let song = import(&file(96, &TRACK), &MidiOptions::default()).unwrap();
+        assert_eq!(song.length, 285);
+    }
+
+    #[test]
+    fn smpte_timing() {
+        // 25 frames per second of 40 ticks, a note of one second
+        let track = [0x00, 0x90, 60, 100, 0x87, 0x68, 0x80, 60, 0, 0x00, 0xff, 0x2f, 0x00];
+        let song = import(&file(0xe728, &track), &MidiOptions::default()).unwrap();
+        assert_eq!(song.tracks[0].notes[0].duration, 60);
+    }
+
+    #[test]
+    fn invalid_smpte_frame_rate_is_an_error() {
+        let track = [0x00, 0xff, 0x2f, 0x00];
+        let result = import(&file(0x8028, &track), &MidiOptions::default());
+        assert_eq!(result.unwrap_err(), ImportError::Malformed("SMPTE frame rate"));
+    }
+
+    #[test]
+    fn delta_time_overflow_is_an_error() {
+        // Empty text events after the longest delta time, past u32::MAX ticks in total
+        let track: Vec<u8> = (0..17).flat_map(|_| [0xff, 0xff, 0xff, 0x7f, 0xff, 0x01, 0x00]).collect();
+        let result = import(&file(96, &track), &MidiOptions::default());
+        assert_eq!(result.unwrap_err(), ImportError::Malformed("delta time"));
+    }
+
+    #[test]
+    fn truncated_and_foreign_data_are_errors() {
+        let bytes = file(96, &TRACK);
+        let result = import(&bytes[..bytes.len() - 10], &MidiOptions::default());
+        assert_eq!(result.unwrap_err(), ImportError::UnexpectedEnd);
+        let result = import(b"RIFF\0\0\0\0", &MidiOptions::default());
+        assert_eq!(result.unwrap_err(), ImportError::InvalidHeader);
+    }
+
+    #[test]
+    fn data_bytes_with_the_high_bit_are_errors() {
+        for event in [[0x90, 0x80, 100], [0x90, 60, 0xff]] {
+            let track = [&[0x00][..], &event, &[0x00, 0xff, 0x2f, 0x00]].concat();
+            let result = import(&file(96, &track), &MidiOptions::default());
+            assert_eq!(result.unwrap_err(), ImportError::Malformed("data byte"));
+        }
+    }
+
+    #[test]
+    fn explicit_mapping_reports_unmapped_channels() {
+        let options = MidiOptions { mapping: [None, Some(Channel::Triangle), None, None, None, None, None, None,
+            None, None, None, None, None, None, None, None], ..Default::default() };
+        let song = import(&file(96, &TRACK), &options).unwrap();
+        assert!(song.tracks.is_empty());
+        assert_eq!(song.warnings, vec![ImportWarning::UnmappedSource { source: 0, notes: 3 }]);
+    }
+}
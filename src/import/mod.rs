@@ -0,0 +1,229 @@
+//! Build-time conversion of music files into `sequencer::Song` data.
+//!
+//! The converters work on bytes and produce Rust source, so they can be used
+//! from a build script of the game:
+//!
+//! ```ignore
+//! let bytes = std::fs::read("music/theme.mid").unwrap();
+//! let song = wasm4::import::midi::import(&bytes, &MidiOptions::default()).unwrap();
+//! for warning in &song.warnings {
+//!     println!("cargo:warning=theme.mid: {}", warning);
+//! }
+//! std::fs::write(out_dir.join("theme.rs"), song.to_rust("THEME")).unwrap();
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Display, Formatter, Write};
+
+use crate::audio::{Channel, DutyCycle, Pan};
+use crate::sequencer::{SongNote, MAX_TRACKS};
+
+pub mod midi;
+pub mod famitracker;
+
+/// Song converted from an external format, ready to be written as Rust source
+#[derive(Clone, Debug)]
+pub struct ImportedSong {
+    /// Total length of the song in frames
+    pub length: u32,
+    pub looping: bool,
+    pub tracks: Vec<ImportedTrack>,
+    /// Problems found during the conversion that didn't stop it
+    pub warnings: Vec<ImportWarning>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportedTrack {
+    pub channel: Channel,
+    pub duty_cycle: DutyCycle,
+    pub pan: Pan,
+    /// Path of an `Instrument` constant to play the track with, e.g. `crate::music::LEAD`
+    pub instrument: Option<String>,
+    pub notes: Vec<SongNote>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImportWarning {
+    /// Notes started while another note was sounding on the same channel.
+    /// Notes starting at the same time were dropped, keeping the highest one,
+    /// and notes still sounding were cut short by the next one.
+    Polyphony { channel: Channel, dropped: usize, truncated: usize },
+    /// Notes of a source channel or track without a WASM-4 channel assigned
+    UnmappedSource { source: u8, notes: usize },
+    /// Notes longer than 255 frames, shortened to fit `ToneDuration`
+    DurationClamped { channel: Channel, notes: usize },
+    /// Gap between notes longer than 65535 frames, shortened to fit `SongNote::delay`
+    DelayClamped { channel: Channel, notes: usize },
+    /// Effect or feature of the source format without an equivalent, ignored
+    Unsupported { feature: String, occurrences: usize },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImportError {
+    /// The data doesn't start with the expected signature
+    InvalidHeader,
+    /// The data ended in the middle of a structure
+    UnexpectedEnd,
+    /// The data is well-formed but uses a variant that isn't supported
+    Unsupported(&'static str),
+    /// A text line that couldn't be parsed, with its 1-based number
+    InvalidLine(usize),
+    /// A value that can't be right, e.g. a time past the largest one supported
+    Malformed(&'static str),
+}
+
+/// Note with absolute timing, before it's fitted onto a monophonic channel
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct TimedNote {
+    pub start: u32,
+    pub end: u32,
+    pub midi: u8,
+    pub volume: u8,
+}
+
+impl ImportedSong {
+    pub(crate) fn new(length: u32, looping: bool) -> Self {
+        Self { length, looping, tracks: Vec::new(), warnings: Vec::new() }
+    }
+
+    /// Fits the notes onto the `channel` and adds them as a new track
+    pub(crate) fn add_track(&mut self, channel: Channel, duty_cycle: DutyCycle, mut notes: Vec<TimedNote>) {
+        assert!(self.tracks.len() < MAX_TRACKS, "song can have at most 4 tracks");
+        notes.sort_by(|a, b| a.start.cmp(&b.start).then(b.midi.cmp(&a.midi)));
+
+        let mut dropped = 0;
+        let mut truncated = 0;
+        let mut monophonic: Vec<TimedNote> = Vec::with_capacity(notes.len());
+        for note in notes {
+            if let Some(previous) = monophonic.last_mut() {
+                if note.start == previous.start {
+                    dropped += 1;
+                    continue;
+                }
+                if note.start < previous.end {
+                    previous.end = note.start;
+                    truncated += 1;
+                }
+            }
+            monophonic.push(note);
+        }
+        if dropped > 0 || truncated > 0 {
+            self.warnings.push(ImportWarning::Polyphony { channel, dropped, truncated });
+        }
+
+        let mut clamped_durations = 0;
+        let mut clamped_delays = 0;
+        let mut previous_start = 0;
+        let mut song_notes = Vec::with_capacity(monophonic.len());
+        for note in monophonic {
+            let duration = note.end.saturating_sub(note.start).max(1);
+            if duration > u8::MAX as u32 {
+                clamped_durations += 1;
+            }
+            let delay = note.start - previous_start;
+            if delay > u16::MAX as u32 {
+                clamped_delays += 1;
+            }
+            previous_start = note.start;
+            song_notes.push(SongNote::new(
+                delay.min(u16::MAX as u32) as u16,
+                note.midi,
+                duration.min(u8::MAX as u32) as u8,
+                note.volume,
+            ));
+        }
+        if clamped_durations > 0 {
+            self.warnings.push(ImportWarning::DurationClamped { channel, notes: clamped_durations });
+        }
+        if clamped_delays > 0 {
+            self.warnings.push(ImportWarning::DelayClamped { channel, notes: clamped_delays });
+        }
+
+        self.tracks.push(ImportedTrack { channel, duty_cycle, pan: Pan::Center, instrument: None, notes: song_notes });
+    }
+
+    pub(crate) fn warn_unsupported(&mut self, feature: &str) {
+        for warning in self.warnings.iter_mut() {
+            if let ImportWarning::Unsupported { feature: existing, occurrences } = warning {
+                if existing == feature {
+                    *occurrences += 1;
+                    return;
+                }
+            }
+        }
+        self.warnings.push(ImportWarning::Unsupported { feature: String::from(feature), occurrences: 1 });
+    }
+
+    /// Rust source of a `const` item with the song, to be included with `include!`
+    /// * `name` - Name of the constant, e.g. `THEME`
+    pub fn to_rust(&self, name: &str) -> String {
+        let mut source = String::new();
+        self.write_rust(&mut source, name).unwrap();
+        source
+    }
+
+    fn write_rust(&self, out: &mut String, name: &str) -> fmt::Result {
+        writeln!(out, "pub const {}: wasm4::sequencer::Song = {{", name)?;
+        writeln!(out, "    use wasm4::audio::{{Channel, DutyCycle, Pan}};")?;
+        writeln!(out, "    use wasm4::sequencer::{{Song, SongNote as N, Track}};")?;
+        writeln!(out, "    Song {{")?;
+        writeln!(out, "        length: {},", self.length)?;
+        writeln!(out, "        looping: {},", self.looping)?;
+        writeln!(out, "        tracks: &[")?;
+        for track in &self.tracks {
+            writeln!(out, "            Track {{")?;
+            writeln!(out, "                channel: Channel::{:?},", track.channel)?;
+            writeln!(out, "                duty_cycle: DutyCycle::{:?},", track.duty_cycle)?;
+            writeln!(out, "                pan: Pan::{:?},", track.pan)?;
+            match &track.instrument {
+                Some(instrument) => writeln!(out, "                instrument: Some(&{}),", instrument)?,
+                None => writeln!(out, "                instrument: None,")?,
+            }
+            writeln!(out, "                notes: &[")?;
+            for chunk in track.notes.chunks(4) {
+                write!(out, "                   ")?;
+                for note in chunk {
+                    write!(out, " N::new({}, {}, {}, {}),", note.delay, note.note.midi(), note.duration, note.volume)?;
+                }
+                writeln!(out)?;
+            }
+            writeln!(out, "                ],")?;
+            writeln!(out, "            }},")?;
+        }
+        writeln!(out, "        ],")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}};")
+    }
+}
+
+impl Display for ImportWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportWarning::Polyphony { channel, dropped, truncated } =>
+                write!(f, "{:?} is monophonic, dropped {} simultaneous notes and cut short {} overlapping notes",
+                       channel, dropped, truncated),
+            ImportWarning::UnmappedSource { source, notes } =>
+                write!(f, "source channel {} has no WASM-4 channel assigned, skipped {} notes", source, notes),
+            ImportWarning::DurationClamped { channel, notes } =>
+                write!(f, "{:?} has {} notes longer than 255 frames, shortened", channel, notes),
+            ImportWarning::DelayClamped { channel, notes } =>
+                write!(f, "{:?} has {} gaps longer than 65535 frames, shortened", channel, notes),
+            ImportWarning::Unsupported { feature, occurrences } =>
+                write!(f, "{} is not supported, ignored {} times", feature, occurrences),
+        }
+    }
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::InvalidHeader => write!(f, "invalid header"),
+            ImportError::UnexpectedEnd => write!(f, "unexpected end of data"),
+            ImportError::Unsupported(feature) => write!(f, "unsupported {}", feature),
+            ImportError::InvalidLine(line) => write!(f, "invalid line {}", line),
+            ImportError::Malformed(value) => write!(f, "malformed {}", value),
+        }
+    }
+}
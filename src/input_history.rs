@@ -0,0 +1,323 @@
+use crate::gamepad::{Gamepad, GamepadButton};
+
+/// Combination of buttons held down at the same time, e.g. down and right for a diagonal
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct Buttons(u8);
+
+impl Buttons {
+    pub const NONE: Buttons = Buttons(0);
+    pub const DOWN_LEFT: Buttons = Buttons::of(GamepadButton::DPadDown).with(GamepadButton::DPadLeft);
+    pub const DOWN_RIGHT: Buttons = Buttons::of(GamepadButton::DPadDown).with(GamepadButton::DPadRight);
+    pub const UP_LEFT: Buttons = Buttons::of(GamepadButton::DPadUp).with(GamepadButton::DPadLeft);
+    pub const UP_RIGHT: Buttons = Buttons::of(GamepadButton::DPadUp).with(GamepadButton::DPadRight);
+
+    pub const fn of(button: GamepadButton) -> Self {
+        Self(button as u8)
+    }
+
+    /// * `bits` - Bitmask made of `GamepadButton` values
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn with(self, button: GamepadButton) -> Self {
+        Self(self.0 | button as u8)
+    }
+
+    pub const fn contains(&self, button: GamepadButton) -> bool {
+        self.0 & button as u8 != 0
+    }
+
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<GamepadButton> for Buttons {
+    fn from(button: GamepadButton) -> Self {
+        Self::of(button)
+    }
+}
+
+/// Sequence of button combinations entered one after another, e.g. a special move or a cheat code
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Combo {
+    pub steps: &'static [Buttons],
+    /// The most frames allowed between the first and the last step
+    pub window: u32,
+    /// Whether no other buttons may be pressed between the steps
+    pub strict: bool,
+}
+
+impl Combo {
+    /// Lenient sequence, like motions of fighting games, e.g. down, down-right, right + X
+    pub const fn motion(steps: &'static [Buttons], window: u32) -> Self {
+        Self { steps, window, strict: false }
+    }
+
+    /// Exact sequence of presses, like cheat codes, where a wrong button breaks the sequence
+    pub const fn sequence(steps: &'static [Buttons], window: u32) -> Self {
+        Self { steps, window, strict: true }
+    }
+}
+
+/// Change of the held buttons
+#[derive(Copy, Clone, Default, Debug)]
+struct Change {
+    frame: u32,
+    buttons: u8,
+    /// Buttons pressed by this change
+    pressed: u8,
+}
+
+/// Recent inputs of a gamepad, for hold timing, repeating, buffering and combos.
+/// Keeps the last `N` changes of the held buttons, the `update` must be called every frame.
+pub struct GamepadHistory<const N: usize> {
+    changes: [Change; N],
+    /// Index of the newest change
+    head: usize,
+    count: usize,
+    frame: u32,
+    buttons: u8,
+    /// Frame of the last press for every button bit
+    pressed_at: [u32; 8],
+    /// Frame of the last `consume` for every button bit
+    consumed_at: [u32; 8],
+}
+
+impl<const N: usize> GamepadHistory<N> {
+    pub const fn new() -> Self {
+        Self {
+            changes: [Change { frame: 0, buttons: 0, pressed: 0 }; N],
+            head: 0,
+            count: 0,
+            frame: 0,
+            buttons: 0,
+            pressed_at: [0; 8],
+            consumed_at: [0; 8],
+        }
+    }
+
+    /// Records the buttons of the `gamepad` for this frame
+    pub fn update(&mut self, gamepad: &Gamepad) {
+        self.record(gamepad.buttons());
+    }
+
+    /// Records the `buttons` bitmask for this frame
+    pub fn record(&mut self, buttons: u8) {
+        self.frame += 1;
+        if buttons == self.buttons {
+            return;
+        }
+        let pressed = buttons & !self.buttons;
+        for (bit, pressed_at) in self.pressed_at.iter_mut().enumerate() {
+            if pressed & 1 << bit != 0 {
+                *pressed_at = self.frame;
+            }
+        }
+        self.buttons = buttons;
+        if N > 0 {
+            self.head = (self.head + 1) % N;
+            self.changes[self.head] = Change { frame: self.frame, buttons, pressed };
+            self.count = (self.count + 1).min(N);
+        }
+    }
+
+    /// Number of frames the `button` has been held down, 1 in the frame it was pressed
+    pub fn hold_duration(&self, button: GamepadButton) -> u32 {
+        if self.buttons & button as u8 == 0 {
+            return 0;
+        }
+        self.frame - self.pressed_at[Self::bit(button)] + 1
+    }
+
+    /// Button has been held down for exactly the `frames` in this frame, e.g. for charged attacks
+    pub fn is_held_for(&self, button: GamepadButton, frames: u32) -> bool {
+        self.hold_duration(button) == frames
+    }
+
+    /// Button has been pressed in this frame for the second time within the `window` frames
+    pub fn is_double_tapped(&self, button: GamepadButton, window: u32) -> bool {
+        let mut presses = self.changes()
+            .filter(|change| change.pressed & button as u8 != 0);
+        match (presses.next(), presses.next()) {
+            (Some(last), Some(previous)) => last.frame == self.frame && last.frame - previous.frame <= window,
+            _ => false,
+        }
+    }
+
+    /// Button has been pressed in this frame, or has been held for longer than the `delay`
+    /// and the `rate` frames have passed since the last repeat, like a key held in a menu
+    pub fn is_repeated(&self, button: GamepadButton, delay: u32, rate: u32) -> bool {
+        let duration = self.hold_duration(button);
+        duration == 1 || (duration > delay && (duration - delay - 1).is_multiple_of(rate.max(1)))
+    }
+
+    /// Button has been pressed within the last `frames` and the press hasn't been consumed,
+    /// e.g. to accept a jump pressed just before landing
+    pub fn is_buffered(&self, button: GamepadButton, frames: u32) -> bool {
+        let pressed_at = self.pressed_at[Self::bit(button)];
+        pressed_at > 0
+            && pressed_at > self.consumed_at[Self::bit(button)]
+            && self.frame - pressed_at < frames
+    }
+
+    /// Marks the last press of the `button` as used, so it's no longer buffered
+    pub fn consume(&mut self, button: GamepadButton) {
+        self.consumed_at[Self::bit(button)] = self.frame;
+    }
+
+    /// The last step of the `combo` has been entered in this frame, after the previous steps
+    pub fn matches(&self, combo: &Combo) -> bool {
+        let Some((last_step, steps)) = combo.steps.split_last() else { return false };
+        let mut changes = self.changes().filter(|change| change.buttons != 0);
+        let Some(last) = changes.next() else { return false };
+        if last.frame != self.frame || last.buttons != last_step.bits() {
+            return false;
+        }
+        let mut steps = steps.iter().rev().peekable();
+        for change in changes {
+            let Some(step) = steps.peek() else { break };
+            if self.frame - change.frame > combo.window {
+                return false;
+            }
+            if change.buttons == step.bits() {
+                steps.next();
+            } else if combo.strict {
+                return false;
+            }
+        }
+        steps.peek().is_none()
+    }
+
+    /// Buttons held down in the last recorded frame
+    pub fn buttons(&self) -> Buttons {
+        Buttons(self.buttons)
+    }
+
+    /// Number of frames recorded so far
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Changes from the newest to the oldest
+    fn changes(&self) -> impl Iterator<Item=&Change> {
+        (0..self.count).map(move |offset| &self.changes[(self.head + N - offset) % N])
+    }
+
+    fn bit(button: GamepadButton) -> usize {
+        (button as u8).trailing_zeros() as usize
+    }
+}
+
+impl<const N: usize> Default for GamepadHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const X: u8 = GamepadButton::ButtonX as u8;
+    const DOWN: u8 = GamepadButton::DPadDown as u8;
+    const RIGHT: u8 = GamepadButton::DPadRight as u8;
+
+    fn history(frames: &[u8]) -> GamepadHistory<8> {
+        let mut history = GamepadHistory::new();
+        for buttons in frames {
+            history.record(*buttons);
+        }
+        history
+    }
+
+    #[test]
+    fn buttons_combine() {
+        let buttons = Buttons::of(GamepadButton::DPadDown).with(GamepadButton::DPadRight);
+        assert_eq!(buttons, Buttons::DOWN_RIGHT);
+        assert_eq!(buttons.bits(), DOWN | RIGHT);
+        assert!(buttons.contains(GamepadButton::DPadRight));
+        assert!(!buttons.contains(GamepadButton::ButtonX));
+        assert!(Buttons::NONE.is_empty());
+        assert_eq!(Buttons::from(GamepadButton::ButtonX), Buttons::from_bits(X));
+    }
+
+    #[test]
+    fn hold_duration_counts_from_the_press() {
+        let mut history = history(&[0, X, X, X]);
+        assert_eq!(history.hold_duration(GamepadButton::ButtonX), 3);
+        assert!(history.is_held_for(GamepadButton::ButtonX, 3));
+        assert_eq!(history.hold_duration(GamepadButton::ButtonY), 0);
+        history.record(0);
+        assert_eq!(history.hold_duration(GamepadButton::ButtonX), 0);
+        assert_eq!(history.frame(), 5);
+    }
+
+    #[test]
+    fn double_tap_within_the_window() {
+        assert!(history(&[X, 0, 0, X]).is_double_tapped(GamepadButton::ButtonX, 3));
+        assert!(!history(&[X, 0, 0, 0, X]).is_double_tapped(GamepadButton::ButtonX, 3));
+        assert!(!history(&[X, 0, X, X]).is_double_tapped(GamepadButton::ButtonX, 3));
+        assert!(!history(&[X]).is_double_tapped(GamepadButton::ButtonX, 3));
+    }
+
+    #[test]
+    fn repeats_after_the_delay_at_the_rate() {
+        let mut history = GamepadHistory::<8>::new();
+        let repeated: [bool; 9] = core::array::from_fn(|_| {
+            history.record(X);
+            history.is_repeated(GamepadButton::ButtonX, 2, 3)
+        });
+        assert_eq!(repeated, [true, false, true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn buffered_press_expires_and_is_consumed() {
+        let mut history = history(&[X, 0, 0]);
+        assert!(history.is_buffered(GamepadButton::ButtonX, 3));
+        history.record(0);
+        assert!(!history.is_buffered(GamepadButton::ButtonX, 3));
+
+        let mut history = self::history(&[X, 0]);
+        history.consume(GamepadButton::ButtonX);
+        assert!(!history.is_buffered(GamepadButton::ButtonX, 3));
+        history.record(X);
+        assert!(history.is_buffered(GamepadButton::ButtonX, 3));
+    }
+
+    #[test]
+    fn motion_allows_other_inputs_between_the_steps() {
+        const STEPS: [Buttons; 3] = [Buttons::of(GamepadButton::DPadDown), Buttons::DOWN_RIGHT, Buttons::from_bits(RIGHT | X)];
+        let motion = Combo::motion(&STEPS, 10);
+        assert!(history(&[DOWN, DOWN | RIGHT, RIGHT, RIGHT | X]).matches(&motion));
+        assert!(history(&[DOWN, 0, DOWN | RIGHT, RIGHT | X]).matches(&motion));
+        assert!(!history(&[DOWN, DOWN | RIGHT, RIGHT | X, RIGHT | X]).matches(&motion));
+        assert!(!history(&[DOWN, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, DOWN | RIGHT, RIGHT | X]).matches(&motion));
+        assert!(!history(&[DOWN | RIGHT, RIGHT | X]).matches(&motion));
+    }
+
+    #[test]
+    fn sequence_breaks_on_another_input() {
+        const STEPS: [Buttons; 3] = [Buttons::of(GamepadButton::DPadDown), Buttons::of(GamepadButton::DPadRight), Buttons::of(GamepadButton::ButtonX)];
+        let sequence = Combo::sequence(&STEPS, 10);
+        assert!(history(&[DOWN, 0, RIGHT, 0, X]).matches(&sequence));
+        assert!(history(&[DOWN, 0, DOWN, RIGHT, 0, X]).matches(&sequence));
+        assert!(!history(&[DOWN, 0, GamepadButton::ButtonY as u8, RIGHT, 0, X]).matches(&sequence));
+        assert!(!history(&[DOWN, 0, RIGHT, RIGHT | X]).matches(&sequence));
+    }
+
+    #[test]
+    fn combos_longer_than_the_history_never_match() {
+        const STEPS: [Buttons; 3] = [Buttons::of(GamepadButton::DPadDown), Buttons::of(GamepadButton::DPadRight), Buttons::of(GamepadButton::ButtonX)];
+        let mut history = GamepadHistory::<2>::new();
+        for buttons in [DOWN, 0, RIGHT, 0, X] {
+            history.record(buttons);
+        }
+        assert!(!history.matches(&Combo::motion(&STEPS, 10)));
+    }
+}
@@ -1,21 +1,123 @@
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
 use crate::gamepad::Gamepad;
-use crate::system;
+use crate::mouse::Mouse;
+use crate::player::Player;
+use crate::replay::{InputFrame, InputPlayer, InputRecorder};
 
 pub struct Inputs {
     pub gamepad1: Gamepad,
     pub gamepad2: Gamepad,
     pub gamepad3: Gamepad,
     pub gamepad4: Gamepad,
+    pub mouse: Mouse,
+    frame: InputFrame,
+    /// Bitmask of the players who pressed any button
+    active: u8,
+    recorder: RefCell<Option<InputRecorder>>,
+    player: RefCell<Option<InputPlayer>>,
 }
 
 impl Inputs {
     pub const unsafe fn new() -> Self {
         Self {
-            gamepad1: unsafe { Gamepad::new(system::GAMEPAD1) },
-            gamepad2: unsafe { Gamepad::new(system::GAMEPAD2) },
-            gamepad3: unsafe { Gamepad::new(system::GAMEPAD3) },
-            gamepad4: unsafe { Gamepad::new(system::GAMEPAD4) },
+            gamepad1: unsafe { Gamepad::new() },
+            gamepad2: unsafe { Gamepad::new() },
+            gamepad3: unsafe { Gamepad::new() },
+            gamepad4: unsafe { Gamepad::new() },
+            mouse: unsafe { Mouse::new() },
+            frame: InputFrame { gamepads: [0; 4], mouse_x: 0, mouse_y: 0, mouse_buttons: 0 },
+            active: 0,
+            recorder: RefCell::new(None),
+            player: RefCell::new(None),
+        }
+    }
+
+    pub fn gamepad(&self, player: Player) -> &Gamepad {
+        match player {
+            Player::One => &self.gamepad1,
+            Player::Two => &self.gamepad2,
+            Player::Three => &self.gamepad3,
+            Player::Four => &self.gamepad4,
+        }
+    }
+
+    /// Gamepads of all four players
+    pub fn gamepads(&self) -> impl Iterator<Item=(Player, &Gamepad)> {
+        Player::ALL.into_iter().map(|player| (player, self.gamepad(player)))
+    }
+
+    /// Whether the `player` has pressed any button so far, so there is someone holding the gamepad
+    pub fn is_active(&self, player: Player) -> bool {
+        self.active & 1 << player.index() != 0
+    }
+
+    /// Players who have pressed any button so far
+    pub fn active_players(&self) -> impl Iterator<Item=Player> + '_ {
+        Player::ALL.into_iter().filter(|player| self.is_active(*player))
+    }
+
+    /// Values of the input registers seen in this frame, recorded or played back
+    pub fn frame(&self) -> InputFrame {
+        self.frame
+    }
+
+    /// Starts capturing the inputs from the next frame, replacing the previous recording
+    pub fn start_recording(&self) {
+        *self.recorder.borrow_mut() = Some(InputRecorder::new());
+    }
+
+    /// Stops capturing the inputs, returns the encoded recording
+    pub fn stop_recording(&self) -> Option<Vec<u8>> {
+        self.recorder.borrow_mut().take().map(|recorder| recorder.to_bytes())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.borrow().is_some()
+    }
+
+    /// Replaces the inputs with the recorded ones from the next frame,
+    /// until the recording ends or `stop_playback` is called.
+    /// The real inputs are still available with `InputFrame::live`, e.g. to leave a demo.
+    pub fn start_playback(&self, player: InputPlayer) {
+        *self.player.borrow_mut() = Some(player);
+    }
+
+    pub fn stop_playback(&self) {
+        *self.player.borrow_mut() = None;
+    }
+
+    /// Whether the inputs of this frame come from a recording
+    pub fn is_playing_back(&self) -> bool {
+        self.player.borrow().is_some()
+    }
+
+    /// Reads the inputs of this frame, must be called before the update
+    ///
+    /// # Safety
+    /// Reads the memory-mapped registers, so it must run inside the WASM-4 runtime
+    pub unsafe fn early_update(&mut self) {
+        let player = self.player.get_mut();
+        let played = player.as_mut().and_then(|player| player.next());
+        if played.is_none() {
+            *player = None;
         }
+        let frame = played.unwrap_or_else(InputFrame::live);
+        if let Some(recorder) = self.recorder.get_mut() {
+            recorder.record(&frame);
+        }
+        self.frame = frame;
+        for (index, gamepad) in frame.gamepads.iter().enumerate() {
+            if *gamepad != 0 {
+                self.active |= 1 << index;
+            }
+        }
+        self.gamepad1.early_update(frame.gamepads[0]);
+        self.gamepad2.early_update(frame.gamepads[1]);
+        self.gamepad3.early_update(frame.gamepads[2]);
+        self.gamepad4.early_update(frame.gamepads[3]);
+        self.mouse.early_update(frame.mouse_x, frame.mouse_y, frame.mouse_buttons);
     }
 
     pub unsafe fn late_update(&mut self) {
@@ -23,5 +125,6 @@ impl Inputs {
         self.gamepad2.late_update();
         self.gamepad3.late_update();
         self.gamepad4.late_update();
+        self.mouse.late_update();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,297 @@
+use crate::audio::{ADSRDuration, Channel, DutyCycle, Flags, Frequency, Pan, ToneOutput, Volume};
+use crate::note::Note;
+
+/// Values applied one per frame, like the macros of FamiTracker
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Sequence<T: 'static> {
+    pub values: &'static [T],
+    /// Index the sequence jumps back to after the last value, otherwise the last value is held
+    pub loop_point: Option<u8>,
+    /// Index where the sequence waits, or loops back to the loop point, until the note is released
+    pub release_point: Option<u8>,
+}
+
+impl<T: Copy> Sequence<T> {
+    /// Sequence without any values, leaving the parameter unchanged
+    pub const EMPTY: Sequence<T> = Sequence { values: &[], loop_point: None, release_point: None };
+
+    /// Sequence playing the `values` once and holding the last one
+    pub const fn once(values: &'static [T]) -> Self {
+        Self { values, loop_point: None, release_point: None }
+    }
+
+    /// Sequence repeating the `values` from the start
+    pub const fn repeat(values: &'static [T]) -> Self {
+        Self { values, loop_point: Some(0), release_point: None }
+    }
+
+    pub const fn with_loop_point(self, index: u8) -> Self {
+        Self { loop_point: Some(index), ..self }
+    }
+
+    pub const fn with_release_point(self, index: u8) -> Self {
+        Self { release_point: Some(index), ..self }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    fn get(&self, index: usize) -> Option<T> {
+        self.values.get(index).copied()
+    }
+
+    /// Whether the sequence reached the last value and won't change anymore
+    fn is_finished(&self, index: usize, released: bool) -> bool {
+        self.advance(index, released).is_none() && index + 1 >= self.values.len()
+    }
+
+    /// Index of the value for the next frame, or `None` when the current value is held
+    fn advance(&self, index: usize, released: bool) -> Option<usize> {
+        let loop_point = self.loop_point.map(|point| point as usize);
+        let release_point = self.release_point.map(|point| point as usize);
+        if let Some(release_point) = release_point.filter(|point| !released && index >= *point) {
+            return loop_point.filter(|loop_point| *loop_point <= release_point);
+        }
+        if index + 1 < self.values.len() {
+            return Some(index + 1);
+        }
+        loop_point.filter(|loop_point| release_point.is_none_or(|release_point| *loop_point > release_point))
+    }
+}
+
+/// Sound defined by per-frame sequences, played by `InstrumentVoice`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Instrument {
+    /// Volume between 0 and 100, scaling the volume of the note
+    pub volume: Sequence<u8>,
+    /// Pitch change in 1/16 of a semitone, accumulated every frame for slides and vibratos
+    pub pitch: Sequence<i8>,
+    /// Semitones added to the note, for arpeggios
+    pub arpeggio: Sequence<i8>,
+    /// Duty cycle of the pulse channels
+    pub duty_cycle: Sequence<DutyCycle>,
+}
+
+impl Instrument {
+    /// Instrument playing the notes with constant volume and pitch
+    pub const PLAIN: Instrument = Instrument {
+        volume: Sequence::EMPTY,
+        pitch: Sequence::EMPTY,
+        arpeggio: Sequence::EMPTY,
+        duty_cycle: Sequence::EMPTY,
+    };
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Self::PLAIN
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct SequenceCursors {
+    volume: usize,
+    pitch: usize,
+    arpeggio: usize,
+    duty_cycle: usize,
+}
+
+/// Plays notes of an `Instrument` on a channel,
+/// realised by starting a tone one frame long every `update`
+#[derive(Copy, Clone)]
+pub struct InstrumentVoice {
+    instrument: &'static Instrument,
+    channel: Channel,
+    duty_cycle: DutyCycle,
+    pan: Pan,
+    note: Option<Note>,
+    volume: u8,
+    released: bool,
+    cursors: SequenceCursors,
+    /// Accumulated pitch sequence in 1/16 of a semitone
+    pitch_offset: i32,
+    /// Whether the pitch sequence holds its value, which then is no longer accumulated
+    pitch_held: bool,
+}
+
+impl InstrumentVoice {
+    pub const fn new(instrument: &'static Instrument, channel: Channel) -> Self {
+        Self {
+            instrument,
+            channel,
+            duty_cycle: DutyCycle::OneHalf,
+            pan: Pan::Center,
+            note: None,
+            volume: 100,
+            released: false,
+            cursors: SequenceCursors { volume: 0, pitch: 0, arpeggio: 0, duty_cycle: 0 },
+            pitch_offset: 0,
+            pitch_held: false,
+        }
+    }
+
+    pub fn instrument(&self) -> &'static Instrument {
+        self.instrument
+    }
+
+    /// Changes the instrument, applied from the next note
+    pub fn set_instrument(&mut self, instrument: &'static Instrument) {
+        self.instrument = instrument;
+    }
+
+    /// Duty cycle used when the instrument has no duty cycle sequence
+    pub fn set_duty_cycle(&mut self, duty_cycle: DutyCycle) {
+        self.duty_cycle = duty_cycle;
+    }
+
+    pub fn set_pan(&mut self, pan: Pan) {
+        self.pan = pan;
+    }
+
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    /// Whether a note is sounding, including its release
+    pub fn is_active(&self) -> bool {
+        self.note.is_some()
+    }
+
+    /// Starts the `note` from the beginning of every sequence
+    /// * `volume` - Volume of the note between 0 and 100
+    pub fn note_on(&mut self, note: Note, volume: u8) {
+        assert!(volume <= 100, "volume must be between 0 and 100");
+        self.note = Some(note);
+        self.volume = volume;
+        self.released = false;
+        self.cursors = SequenceCursors::default();
+        self.pitch_offset = 0;
+        self.pitch_held = false;
+    }
+
+    /// Moves the sequences past their release points.
+    /// Without a release point in the volume sequence the note stops immediately.
+    pub fn note_off(&mut self) {
+        if self.instrument.volume.release_point.is_none() {
+            self.note = None;
+        }
+        self.released = true;
+    }
+
+    /// Stops the note immediately
+    pub fn stop(&mut self) {
+        self.note = None;
+    }
+
+    /// Starts the tone for the current frame and advances the sequences
+    pub fn update(&mut self, audio: &impl ToneOutput) {
+        let Some(note) = self.note else { return };
+        let instrument = self.instrument;
+        let cursors = self.cursors;
+
+        let volume = instrument.volume.get(cursors.volume)
+            .map_or(self.volume, |volume| (volume.min(100) as u32 * self.volume as u32 / 100) as u8);
+        if let Some(pitch) = instrument.pitch.get(cursors.pitch).filter(|_| !self.pitch_held) {
+            self.pitch_offset += pitch as i32;
+        }
+        let arpeggio = instrument.arpeggio.get(cursors.arpeggio).unwrap_or(0);
+        let duty_cycle = instrument.duty_cycle.get(cursors.duty_cycle).unwrap_or(self.duty_cycle);
+        let hertz = Self::hertz(note, arpeggio, self.pitch_offset);
+
+        audio.tone(
+            Frequency::constant(hertz),
+            ADSRDuration::constant(1),
+            Volume::constant(volume),
+            Flags::new(self.channel, duty_cycle, self.pan),
+        );
+
+        if self.released && instrument.volume.is_finished(cursors.volume, true) {
+            self.note = None;
+        }
+        let released = self.released;
+        let pitch = instrument.pitch.advance(cursors.pitch, released);
+        self.pitch_held = pitch.is_none();
+        self.cursors = SequenceCursors {
+            volume: instrument.volume.advance(cursors.volume, released).unwrap_or(cursors.volume),
+            pitch: pitch.unwrap_or(cursors.pitch),
+            arpeggio: instrument.arpeggio.advance(cursors.arpeggio, released).unwrap_or(cursors.arpeggio),
+            duty_cycle: instrument.duty_cycle.advance(cursors.duty_cycle, released).unwrap_or(cursors.duty_cycle),
+        };
+    }
+
+    /// Frequency of the note shifted by whole semitones and 1/16 of a semitone,
+    /// interpolating linearly between the neighbour notes
+    fn hertz(note: Note, semitones: i8, sixteenths: i32) -> u16 {
+        let position = (note.midi() as i32 * 16 + semitones as i32 * 16 + sixteenths)
+            .clamp(0, Note::MAX.midi() as i32 * 16);
+        let lower = Note::from_midi((position / 16) as u8);
+        let upper = lower.transpose(1).unwrap_or(lower);
+        let fraction = position % 16;
+        (lower.hertz() as i32 + (upper.hertz() as i32 - lower.hertz() as i32) * fraction / 16) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use crate::audio::Tones;
+
+    /// Frequencies and volumes of the tones played in the `frames`
+    fn play(voice: &mut InstrumentVoice, frames: usize) -> Vec<(u32, u32)> {
+        let tones = Tones::default();
+        for _ in 0..frames {
+            voice.update(&tones);
+        }
+        tones.take().into_iter().map(|(frequency, _, volume, _)| (frequency, volume)).collect()
+    }
+
+    #[test]
+    fn plain_instrument_holds_the_note_until_released() {
+        let mut voice = InstrumentVoice::new(&Instrument::PLAIN, Channel::Pulse1);
+        voice.note_on(Note::A4, 60);
+        assert_eq!(play(&mut voice, 2), [(440, 60), (440, 60)]);
+        voice.note_off();
+        assert!(!voice.is_active());
+        assert!(play(&mut voice, 1).is_empty());
+    }
+
+    #[test]
+    fn volume_sequence_holds_at_release_point_then_finishes() {
+        static ENVELOPE: Instrument = Instrument {
+            volume: Sequence::once(&[100, 50, 0]).with_release_point(1),
+            ..Instrument::PLAIN
+        };
+        let mut voice = InstrumentVoice::new(&ENVELOPE, Channel::Triangle);
+        voice.note_on(Note::A4, 80);
+        assert_eq!(play(&mut voice, 3), [(440, 80), (440, 40), (440, 40)]);
+        voice.note_off();
+        assert_eq!(play(&mut voice, 3), [(440, 40), (440, 0)]);
+        assert!(!voice.is_active());
+    }
+
+    #[test]
+    fn arpeggio_loops_over_the_offsets() {
+        static OCTAVES: Instrument = Instrument { arpeggio: Sequence::repeat(&[0, 12]), ..Instrument::PLAIN };
+        let mut voice = InstrumentVoice::new(&OCTAVES, Channel::Pulse2);
+        voice.note_on(Note::A4, 100);
+        assert_eq!(play(&mut voice, 3), [(440, 100), (880, 100), (440, 100)]);
+    }
+
+    #[test]
+    fn pitch_sequence_accumulates_and_holds_at_the_end() {
+        static BEND: Instrument = Instrument { pitch: Sequence::once(&[8, 8]), ..Instrument::PLAIN };
+        let mut voice = InstrumentVoice::new(&BEND, Channel::Pulse1);
+        voice.note_on(Note::A4, 100);
+        let half = (440 + Note::A4.transpose(1).unwrap().hertz() as u32) / 2;
+        let sharp = Note::A4.transpose(1).unwrap().hertz() as u32;
+        assert_eq!(play(&mut voice, 3), [(half, 100), (sharp, 100), (sharp, 100)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "volume must be between 0 and 100")]
+    fn loud_note_panics() {
+        InstrumentVoice::new(&Instrument::PLAIN, Channel::Noise).note_on(Note::C4, 101);
+    }
+}
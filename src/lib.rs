@@ -1,23 +1,50 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 extern crate alloc;
 
-use alloc::string::{String, ToString};
-use alloc::vec;
 use core::fmt;
 
 use crate::color::Color;
 use crate::framebuffer::Palette;
+use crate::stack_string::StackString;
 
 mod system;
 pub mod application;
 pub mod framebuffer;
 pub mod gamepad;
 pub mod inputs;
+pub mod player;
+pub mod mouse;
+pub mod pointer;
 pub mod sprite;
 pub mod audio;
 pub mod color;
 pub mod hsl_color;
+pub mod note;
+pub mod sequencer;
+pub mod import;
+pub mod sfx;
+pub mod instrument;
+pub mod synthesizer;
+pub mod disk;
+pub mod actions;
+pub mod input_history;
+pub mod replay;
+pub mod scene;
+pub mod fixed;
+pub mod geometry;
+pub mod collision;
+pub mod random;
+pub mod tween;
+pub mod clock;
+pub mod ecs;
+pub mod particles;
+pub mod platformer;
+pub mod ui;
+pub mod debug;
+pub mod logger;
+pub mod stack_string;
+pub mod tracef;
 
 pub fn get_char_width() -> u32 {
     system::CHAR_WIDTH
@@ -27,28 +54,37 @@ pub fn get_char_height() -> u32 {
     system::CHAR_HEIGHT
 }
 
-pub fn char_x_button() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_X_BUTTON]) }
+/// Special glyphs of the built-in font, e.g. `text_fmt!(framebuffer, 0, 0, "{CHAR_X_BUTTON} jump")`.
+/// `Framebuffer::text` decodes UTF-8, so the code point of the glyph draws it.
+pub const CHAR_X_BUTTON: &str = "\u{80}";
+pub const CHAR_Y_BUTTON: &str = "\u{81}";
+pub const CHAR_LEFT_ARROW: &str = "\u{84}";
+pub const CHAR_RIGHT_ARROW: &str = "\u{85}";
+pub const CHAR_UP_ARROW: &str = "\u{86}";
+pub const CHAR_DOWN_ARROW: &str = "\u{87}";
+
+pub fn char_x_button() -> &'static str {
+    CHAR_X_BUTTON
 }
 
-pub fn char_y_button() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_Y_BUTTON]) }
+pub fn char_y_button() -> &'static str {
+    CHAR_Y_BUTTON
 }
 
-pub fn char_left_arrow() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_LEFT_ARROW]) }
+pub fn char_left_arrow() -> &'static str {
+    CHAR_LEFT_ARROW
 }
 
-pub fn char_right_arrow() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_RIGHT_ARROW]) }
+pub fn char_right_arrow() -> &'static str {
+    CHAR_RIGHT_ARROW
 }
 
-pub fn char_up_arrow() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_UP_ARROW]) }
+pub fn char_up_arrow() -> &'static str {
+    CHAR_UP_ARROW
 }
 
-pub fn char_down_arrow() -> String {
-    unsafe { String::from_utf8_unchecked(vec![system::CHAR_DOWN_ARROW]) }
+pub fn char_down_arrow() -> &'static str {
+    CHAR_DOWN_ARROW
 }
 
 #[allow(dead_code)]
@@ -58,9 +94,13 @@ pub fn trace(msg: &str) {
     }
 }
 
+/// Longest message of `println!`, longer ones are cut off
+pub const TRACE_BUFFER_SIZE: usize = 256;
+/// Longest text of `text_fmt!`, longer ones are cut off
+pub const TEXT_BUFFER_SIZE: usize = 128;
+
 pub fn _trace_args(args: fmt::Arguments) {
-    let string = args.to_string();
-    trace(&*string);
+    trace(&StackString::<TRACE_BUFFER_SIZE>::format(args));
 }
 
 #[macro_export]
@@ -73,6 +113,14 @@ macro_rules! println {
     );
 }
 
+/// Draws formatted text without allocating, e.g. `text_fmt!(framebuffer, 0, 0, "Score {}", score)`
+#[macro_export]
+macro_rules! text_fmt {
+    ($framebuffer:expr, $x:expr, $y:expr, $($arg:tt)*) => (
+        $framebuffer.text(&$crate::stack_string::StackString::<{ $crate::TEXT_BUFFER_SIZE }>::format(format_args!($($arg)*)), $x, $y)
+    );
+}
+
 /// From https://wasm4.org/docs/guides/basic-drawing
 pub const PALETTE_DEFAULT: Palette = [
     Color::from(0xe0f8cf),
@@ -80,3 +128,19 @@ pub const PALETTE_DEFAULT: Palette = [
     Color::from(0x306850),
     Color::from(0x071821)
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glyphs_are_the_code_points_of_the_font() {
+        let glyphs = [CHAR_X_BUTTON, CHAR_Y_BUTTON, CHAR_LEFT_ARROW, CHAR_RIGHT_ARROW, CHAR_UP_ARROW, CHAR_DOWN_ARROW];
+        let bytes = [system::CHAR_X_BUTTON, system::CHAR_Y_BUTTON, system::CHAR_LEFT_ARROW,
+            system::CHAR_RIGHT_ARROW, system::CHAR_UP_ARROW, system::CHAR_DOWN_ARROW];
+        for (glyph, byte) in glyphs.iter().zip(bytes) {
+            assert_eq!(glyph.chars().collect::<alloc::vec::Vec<_>>(), [byte as char]);
+        }
+        assert_eq!(char_x_button(), CHAR_X_BUTTON);
+    }
+}
@@ -0,0 +1,127 @@
+//! Backend of the `log` crate writing to the console of WASM-4, so the messages of the game
+//! and of the crates it uses show up next to those of `println!`.
+//!
+//! ```ignore
+//! static LOGGER: Logger = Logger::new(LevelFilter::Debug).with_frame(true);
+//!
+//! fn start() -> Self {
+//!     wasm4::logger::init(&LOGGER).unwrap();
+//!     log::info!("started");
+//! }
+//! ```
+
+use core::fmt::Write;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::stack_string::StackString;
+use crate::{clock, trace, TRACE_BUFFER_SIZE};
+
+/// Writes records like `[120] WARN game::player: fell off` with `trace`
+pub struct Logger {
+    level: LevelFilter,
+    module_path: bool,
+    frame: bool,
+}
+
+impl Logger {
+    /// Logger of the records up to the `level`, with the module path and without the frame
+    pub const fn new(level: LevelFilter) -> Self {
+        Self { level, module_path: true, frame: false }
+    }
+
+    /// Whether to prefix the records with the module they come from
+    pub const fn with_module_path(self, module_path: bool) -> Self {
+        Self { module_path, ..self }
+    }
+
+    /// Whether to prefix the records with the number of the frame of the shared clock
+    pub const fn with_frame(self, frame: bool) -> Self {
+        Self { frame, ..self }
+    }
+
+    pub const fn level(&self) -> LevelFilter {
+        self.level
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        trace(&self.format(record, clock::frame()));
+    }
+
+    fn flush(&self) {}
+}
+
+impl Logger {
+    /// * `frame` - Frame of the shared clock, shown when enabled
+    fn format(&self, record: &Record, frame: u32) -> StackString<TRACE_BUFFER_SIZE> {
+        // Errors only mean the line was cut off
+        let mut line = StackString::new();
+        if self.frame {
+            let _ = write!(line, "[{frame}] ");
+        }
+        let _ = write!(line, "{}", record.level());
+        if let Some(module_path) = record.module_path().filter(|_| self.module_path) {
+            let _ = write!(line, " {module_path}");
+        }
+        let _ = write!(line, ": {}", record.args());
+        line
+    }
+}
+
+/// Makes the `logger` the one of the `log` crate, keeping only the records up to its level.
+/// Fails when a logger was already set.
+pub fn init(logger: &'static Logger) -> Result<(), SetLoggerError> {
+    log::set_logger(logger)?;
+    log::set_max_level(logger.level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    fn format(logger: &Logger, level: Level, message: &str) -> alloc::string::String {
+        logger.format(&Record::builder()
+            .args(format_args!("{message}"))
+            .level(level)
+            .module_path(Some("game::player"))
+            .build(), 120)
+            .as_str()
+            .into()
+    }
+
+    #[test]
+    fn records_are_prefixed_with_the_level_and_module() {
+        let logger = Logger::new(LevelFilter::Debug);
+        assert_eq!(format(&logger, Level::Warn, "fell off"), "WARN game::player: fell off");
+        let logger = logger.with_module_path(false);
+        assert_eq!(format(&logger, Level::Info, "started"), "INFO: started");
+        let line = format(&logger.with_frame(true), Level::Debug, "jumped");
+        assert_eq!(line, "[120] DEBUG: jumped");
+    }
+
+    #[test]
+    fn records_above_the_level_are_disabled() {
+        let logger = Logger::new(LevelFilter::Info);
+        assert!(logger.enabled(&Metadata::builder().level(Level::Warn).build()));
+        assert!(!logger.enabled(&Metadata::builder().level(Level::Debug).build()));
+        assert!(!Logger::new(LevelFilter::Off).enabled(&Metadata::builder().level(Level::Error).build()));
+    }
+
+    #[test]
+    fn long_records_are_cut_off() {
+        let message = "x".repeat(TRACE_BUFFER_SIZE);
+        let line = format(&Logger::new(LevelFilter::Trace), Level::Error, &message);
+        assert_eq!(line.len(), TRACE_BUFFER_SIZE);
+        assert!(line.starts_with("ERROR game::player: xxx"));
+    }
+}
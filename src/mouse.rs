@@ -0,0 +1,76 @@
+use core::ops::BitAnd;
+use crate::geometry::Point;
+use crate::system;
+
+pub struct Mouse {
+    x: i16,
+    y: i16,
+    buttons: u8,
+    last_buttons: u8,
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MouseButton {
+    Left = system::MOUSE_LEFT,
+    Right = system::MOUSE_RIGHT,
+    Middle = system::MOUSE_MIDDLE,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+}
+
+impl Mouse {
+    pub(crate) const unsafe fn new() -> Self {
+        Mouse { x: 0, y: 0, buttons: 0, last_buttons: 0 }
+    }
+
+    pub(crate) fn early_update(&mut self, x: i16, y: i16, buttons: u8) {
+        self.x = x;
+        self.y = y;
+        self.buttons = buttons;
+    }
+
+    pub(crate) unsafe fn late_update(&mut self) {
+        self.last_buttons = self.buttons;
+    }
+
+    /// Horizontal position in pixels, it can be outside of the screen
+    pub fn x(&self) -> i32 {
+        self.x as i32
+    }
+
+    /// Vertical position in pixels, it can be outside of the screen
+    pub fn y(&self) -> i32 {
+        self.y as i32
+    }
+
+    pub fn position(&self) -> Point {
+        Point::new(self.x(), self.y())
+    }
+
+    /// Held buttons as a mask of `MouseButton`
+    pub fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        let buttons = self.buttons;
+        !Self::is_pressing(self.last_buttons, button) && Self::is_pressing(buttons, button)
+    }
+
+    pub fn is_held(&self, button: MouseButton) -> bool {
+        let buttons = self.buttons;
+        Self::is_pressing(buttons, button)
+    }
+
+    pub fn is_released(&self, button: MouseButton) -> bool {
+        let buttons = self.buttons;
+        Self::is_pressing(self.last_buttons, button) && !Self::is_pressing(buttons, button)
+    }
+
+    fn is_pressing(buttons: u8, button: MouseButton) -> bool {
+        buttons.bitand(button as u8) != 0
+    }
+}
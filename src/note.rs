@@ -0,0 +1,347 @@
+use core::fmt::{Debug, Formatter};
+
+use crate::audio::Frequency;
+
+/// One of the twelve pitch classes of the chromatic scale
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PitchClass {
+    C = 0,
+    CSharp = 1,
+    D = 2,
+    DSharp = 3,
+    E = 4,
+    F = 5,
+    FSharp = 6,
+    G = 7,
+    GSharp = 8,
+    A = 9,
+    ASharp = 10,
+    B = 11,
+}
+
+impl PitchClass {
+    const ALL: [PitchClass; 12] = [
+        PitchClass::C,
+        PitchClass::CSharp,
+        PitchClass::D,
+        PitchClass::DSharp,
+        PitchClass::E,
+        PitchClass::F,
+        PitchClass::FSharp,
+        PitchClass::G,
+        PitchClass::GSharp,
+        PitchClass::A,
+        PitchClass::ASharp,
+        PitchClass::B,
+    ];
+
+    /// * `semitone` - Number of semitones above C, wrapped to a single octave
+    pub const fn from_semitone(semitone: u8) -> Self {
+        Self::ALL[(semitone % 12) as usize]
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            PitchClass::C => "C",
+            PitchClass::CSharp => "C#",
+            PitchClass::D => "D",
+            PitchClass::DSharp => "D#",
+            PitchClass::E => "E",
+            PitchClass::F => "F",
+            PitchClass::FSharp => "F#",
+            PitchClass::G => "G",
+            PitchClass::GSharp => "G#",
+            PitchClass::A => "A",
+            PitchClass::ASharp => "A#",
+            PitchClass::B => "B",
+        }
+    }
+}
+
+/// Musical note in twelve-tone equal temperament, tuned to A4 = 440 Hz.
+/// Internally stored as MIDI note number, where middle C (C4) is 60.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Note(u8);
+
+/// Frequencies of the lowest octave (MIDI notes 0-11) in 1/65536 Hz,
+/// higher octaves are derived by shifting, so no rounding error accumulates.
+const LOWEST_OCTAVE_FREQUENCIES: [u32; 12] = [
+    535809, 567670, 601425, 637188, 675077, 715219,
+    757749, 802807, 850544, 901120, 954703, 1011473,
+];
+
+impl Note {
+    pub const MIN: Note = Note(0);
+    pub const MAX: Note = Note(127);
+    /// Middle C
+    pub const C4: Note = Note(60);
+    /// Concert pitch, 440 Hz
+    pub const A4: Note = Note(69);
+
+    /// * `pitch_class` - Pitch class of the note
+    /// * `octave` - Octave in scientific pitch notation, between -1 and 9
+    pub const fn new(pitch_class: PitchClass, octave: i8) -> Self {
+        let midi = (octave as i16 + 1) * 12 + pitch_class as i16;
+        assert!(midi >= 0 && midi <= 127, "note must be between C-1 and G9");
+        Self(midi as u8)
+    }
+
+    /// * `midi` - MIDI note number between 0 and 127
+    pub const fn from_midi(midi: u8) -> Self {
+        assert!(midi <= 127, "MIDI note must be between 0 and 127");
+        Self(midi)
+    }
+
+    pub const fn midi(&self) -> u8 {
+        self.0
+    }
+
+    pub const fn pitch_class(&self) -> PitchClass {
+        PitchClass::from_semitone(self.0)
+    }
+
+    /// Octave in scientific pitch notation, middle C is in octave 4
+    pub const fn octave(&self) -> i8 {
+        (self.0 / 12) as i8 - 1
+    }
+
+    /// Frequency of the note in hertz, rounded to the nearest integer
+    pub const fn hertz(&self) -> u16 {
+        let base = LOWEST_OCTAVE_FREQUENCIES[(self.0 % 12) as usize];
+        let scaled = base << (self.0 / 12);
+        ((scaled + (1 << 15)) >> 16) as u16
+    }
+
+    /// Returns the note moved by `semitones`, or `None` if it falls outside the MIDI range
+    pub const fn transpose(&self, semitones: i16) -> Option<Self> {
+        let midi = self.0 as i16 + semitones;
+        if midi >= 0 && midi <= 127 {
+            Some(Self(midi as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the note moved by whole `octaves`, or `None` if it falls outside the MIDI range
+    pub const fn transpose_octaves(&self, octaves: i8) -> Option<Self> {
+        self.transpose(octaves as i16 * 12)
+    }
+
+    /// Number of semitones from `self` to `other`
+    pub const fn interval_to(&self, other: Note) -> i16 {
+        other.0 as i16 - self.0 as i16
+    }
+}
+
+impl From<Note> for Frequency {
+    fn from(note: Note) -> Self {
+        Frequency::constant(note.hertz())
+    }
+}
+
+impl Debug for Note {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}{}", self.pitch_class().name(), self.octave())
+    }
+}
+
+impl Frequency {
+    /// Constant wave frequency of the `note`
+    pub const fn note(note: Note) -> Self {
+        Self::constant(note.hertz())
+    }
+
+    /// Wave frequency sliding linearly from one note to another over the tone duration
+    /// * `start` - Note at the start of the tone
+    /// * `end` - Note at the end of the tone
+    pub const fn glide(start: Note, end: Note) -> Self {
+        Self::linear(start.hertz(), end.hertz())
+    }
+}
+
+/// Set of intervals above a root note, repeating every octave
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Scale {
+    /// Semitones above the root for each degree in a single octave, starting with 0
+    intervals: &'static [u8],
+}
+
+impl Scale {
+    pub const MAJOR: Scale = Scale::new(&[0, 2, 4, 5, 7, 9, 11]);
+    pub const NATURAL_MINOR: Scale = Scale::new(&[0, 2, 3, 5, 7, 8, 10]);
+    pub const HARMONIC_MINOR: Scale = Scale::new(&[0, 2, 3, 5, 7, 8, 11]);
+    pub const MELODIC_MINOR: Scale = Scale::new(&[0, 2, 3, 5, 7, 9, 11]);
+    pub const DORIAN: Scale = Scale::new(&[0, 2, 3, 5, 7, 9, 10]);
+    pub const PHRYGIAN: Scale = Scale::new(&[0, 1, 3, 5, 7, 8, 10]);
+    pub const LYDIAN: Scale = Scale::new(&[0, 2, 4, 6, 7, 9, 11]);
+    pub const MIXOLYDIAN: Scale = Scale::new(&[0, 2, 4, 5, 7, 9, 10]);
+    pub const MAJOR_PENTATONIC: Scale = Scale::new(&[0, 2, 4, 7, 9]);
+    pub const MINOR_PENTATONIC: Scale = Scale::new(&[0, 3, 5, 7, 10]);
+    pub const BLUES: Scale = Scale::new(&[0, 3, 5, 6, 7, 10]);
+    pub const WHOLE_TONE: Scale = Scale::new(&[0, 2, 4, 6, 8, 10]);
+    pub const CHROMATIC: Scale = Scale::new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+
+    /// * `intervals` - Semitones above the root for each degree in a single octave, below 12
+    pub const fn new(intervals: &'static [u8]) -> Self {
+        assert!(!intervals.is_empty(), "scale must have a degree");
+        let mut index = 0;
+        while index < intervals.len() {
+            assert!(intervals[index] < 12, "scale intervals must be within a single octave");
+            index += 1;
+        }
+        Self { intervals }
+    }
+
+    pub const fn intervals(&self) -> &'static [u8] {
+        self.intervals
+    }
+
+    /// Number of degrees in a single octave
+    pub const fn degree_count(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Returns the note of the scale `degree`, counted from 0 at the `root`.
+    /// Degrees outside of a single octave continue into the neighbour octaves,
+    /// so for the major scale degree 7 is the root one octave higher and -1 is the leading tone below.
+    pub const fn degree(&self, root: Note, degree: i16) -> Option<Note> {
+        let length = self.intervals.len() as i16;
+        let octave = degree.div_euclid(length);
+        let index = degree.rem_euclid(length) as usize;
+        root.transpose(octave * 12 + self.intervals[index] as i16)
+    }
+
+    /// Notes of the scale in a single octave starting at the `root`
+    pub fn notes(&self, root: Note) -> impl Iterator<Item=Note> + '_ {
+        self.intervals.iter()
+            .filter_map(move |interval| root.transpose(*interval as i16))
+    }
+
+    /// Whether the `note` belongs to the scale in any octave
+    pub fn contains(&self, root: Note, note: Note) -> bool {
+        let semitone = root.interval_to(note).rem_euclid(12) as u8;
+        self.intervals.contains(&semitone)
+    }
+}
+
+/// Set of intervals above a root note played together
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Chord {
+    /// Semitones above the root for each note of the chord, starting with 0
+    intervals: &'static [u8],
+}
+
+impl Chord {
+    pub const MAJOR: Chord = Chord::new(&[0, 4, 7]);
+    pub const MINOR: Chord = Chord::new(&[0, 3, 7]);
+    pub const DIMINISHED: Chord = Chord::new(&[0, 3, 6]);
+    pub const AUGMENTED: Chord = Chord::new(&[0, 4, 8]);
+    pub const SUSPENDED_SECOND: Chord = Chord::new(&[0, 2, 7]);
+    pub const SUSPENDED_FOURTH: Chord = Chord::new(&[0, 5, 7]);
+    pub const MAJOR_SEVENTH: Chord = Chord::new(&[0, 4, 7, 11]);
+    pub const MINOR_SEVENTH: Chord = Chord::new(&[0, 3, 7, 10]);
+    pub const DOMINANT_SEVENTH: Chord = Chord::new(&[0, 4, 7, 10]);
+    pub const POWER: Chord = Chord::new(&[0, 7, 12]);
+
+    /// * `intervals` - Semitones above the root for each note of the chord
+    pub const fn new(intervals: &'static [u8]) -> Self {
+        assert!(!intervals.is_empty(), "chord must have a note");
+        Self { intervals }
+    }
+
+    pub const fn intervals(&self) -> &'static [u8] {
+        self.intervals
+    }
+
+    /// Number of notes in the chord
+    pub const fn note_count(&self) -> usize {
+        self.intervals.len()
+    }
+
+    /// Notes of the chord built on the `root`, skipping those outside the MIDI range.
+    /// WASM-4 channels are monophonic, so chords are usually played as arpeggios.
+    pub fn notes(&self, root: Note) -> impl Iterator<Item=Note> + '_ {
+        self.intervals.iter()
+            .filter_map(move |interval| root.transpose(*interval as i16))
+    }
+
+    /// Returns the `index` note of the chord, wrapping into higher octaves for indices past the last note
+    pub const fn arpeggio(&self, root: Note, index: usize) -> Option<Note> {
+        let length = self.intervals.len();
+        let octave = (index / length) as i16;
+        root.transpose(octave * 12 + self.intervals[index % length] as i16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concert_pitch_and_middle_c() {
+        assert_eq!(Note::A4.hertz(), 440);
+        assert_eq!(Note::C4.hertz(), 262);
+        assert_eq!(Note::new(PitchClass::A, 3).hertz(), 220);
+        assert_eq!(Note::MIN.hertz(), 8);
+        assert_eq!(Note::MAX.hertz(), 12544);
+    }
+
+    #[test]
+    fn octaves_and_pitch_classes() {
+        let note = Note::new(PitchClass::FSharp, 2);
+        assert_eq!(note.midi(), 42);
+        assert_eq!(note.octave(), 2);
+        assert_eq!(note.pitch_class(), PitchClass::FSharp);
+        assert_eq!(Note::new(PitchClass::C, -1), Note::MIN);
+        assert_eq!(Note::new(PitchClass::G, 9), Note::MAX);
+        assert_eq!(alloc::format!("{:?}", Note::from_midi(61)), "C#4");
+    }
+
+    #[test]
+    fn transpose_stays_in_range() {
+        assert_eq!(Note::C4.transpose(9), Some(Note::A4));
+        assert_eq!(Note::C4.transpose_octaves(-1), Some(Note::new(PitchClass::C, 3)));
+        assert_eq!(Note::MAX.transpose(1), None);
+        assert_eq!(Note::MIN.transpose(-1), None);
+        assert_eq!(Note::C4.interval_to(Note::A4), 9);
+    }
+
+    #[test]
+    fn scale_degrees_continue_into_neighbour_octaves() {
+        let root = Note::C4;
+        assert_eq!(Scale::MAJOR.degree(root, 2), Some(Note::new(PitchClass::E, 4)));
+        assert_eq!(Scale::MAJOR.degree(root, 7), Some(Note::new(PitchClass::C, 5)));
+        assert_eq!(Scale::MAJOR.degree(root, -1), Some(Note::new(PitchClass::B, 3)));
+        assert!(Scale::MAJOR.contains(root, Note::new(PitchClass::F, 7)));
+        assert!(!Scale::MAJOR.contains(root, Note::new(PitchClass::FSharp, 4)));
+        assert_eq!(Scale::MINOR_PENTATONIC.notes(root).count(), 5);
+    }
+
+    #[test]
+    fn chord_arpeggio_wraps_into_higher_octaves() {
+        let root = Note::new(PitchClass::A, 3);
+        let notes: alloc::vec::Vec<Note> = Chord::MINOR.notes(root).collect();
+        assert_eq!(notes, [root, Note::new(PitchClass::C, 4), Note::new(PitchClass::E, 4)]);
+        assert_eq!(Chord::MINOR.arpeggio(root, 3), Some(Note::A4));
+        assert_eq!(Chord::MAJOR.arpeggio(Note::MAX, 1), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "scale must have a degree")]
+    fn empty_scale_panics() {
+        Scale::new(&[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "chord must have a note")]
+    fn empty_chord_panics() {
+        Chord::new(&[]);
+    }
+
+    #[test]
+    fn custom_scale_repeats_every_octave() {
+        let scale = Scale::new(&[0, 7]);
+        assert_eq!(scale.intervals(), [0, 7]);
+        assert_eq!(scale.degree(Note::C4, 3), Note::C4.transpose(19));
+    }
+}
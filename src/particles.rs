@@ -0,0 +1,307 @@
+//! Particles for explosions, dust and sparks, moved with fixed-point physics
+//! and drawn straight into the framebuffer.
+//!
+//! ```ignore
+//! const SPARKS: ParticleConfig = ParticleConfig::new(ParticleShape::Pixel, &[Palette4, Palette3, Palette2])
+//!     .with_lifetime(10, 20)
+//!     .with_speed(Fixed::from_ratio(1, 2), Fixed::from_int(2))
+//!     .with_gravity(FixedVector::new(Fixed::ZERO, Fixed::from_ratio(1, 10)));
+//!
+//! particles.burst(&SPARKS, position, 30, &mut random);
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::fixed::Fixed;
+use crate::framebuffer::{Framebuffer, PaletteIndex};
+use crate::geometry::{FixedVector, Rect, Size};
+use crate::random::Random;
+use crate::sprite::{Flags, Sprite};
+
+#[derive(Copy, Clone)]
+pub enum ParticleShape {
+    /// Single pixel
+    Pixel,
+    /// Filled rectangle with its top left corner at the position
+    Rect(Size),
+    /// Frames of a sprite sheet laid out horizontally, played over the lifetime of the particle.
+    /// A 1BPP sprite is drawn with the color of the particle, a 2BPP one with the current draw colors.
+    Sprite { sprite: &'static Sprite, frame_width: u32 },
+}
+
+/// Look and motion of the particles of an effect
+#[derive(Copy, Clone)]
+pub struct ParticleConfig {
+    pub shape: ParticleShape,
+    /// Colors from the birth to the death of the particle, evenly split over its lifetime
+    pub colors: &'static [PaletteIndex],
+    /// Shortest and longest lifetime in frames, set with `with_lifetime` so it can't be 0
+    lifetime: (u16, u16),
+    /// Slowest and fastest initial speed in pixels per frame
+    pub speed: (Fixed, Fixed),
+    /// Angle in radians the particles are emitted towards, 0 pointing right
+    pub direction: Fixed,
+    /// Angle in radians around the direction the particles are spread over, `TAU` for every direction
+    pub spread: Fixed,
+    /// Acceleration added to the velocity every frame
+    pub gravity: FixedVector,
+    /// Factor the velocity is multiplied by every frame, `ONE` to keep it
+    pub drag: Fixed,
+}
+
+impl ParticleConfig {
+    pub const fn new(shape: ParticleShape, colors: &'static [PaletteIndex]) -> Self {
+        Self {
+            shape,
+            colors,
+            lifetime: (30, 30),
+            speed: (Fixed::ONE, Fixed::ONE),
+            direction: Fixed::ZERO,
+            spread: Fixed::TAU,
+            gravity: FixedVector::ZERO,
+            drag: Fixed::ONE,
+        }
+    }
+
+    pub const fn with_lifetime(self, min: u16, max: u16) -> Self {
+        assert!(min > 0 && min <= max, "lifetime must be positive and min must not exceed max");
+        Self { lifetime: (min, max), ..self }
+    }
+
+    /// Shortest and longest lifetime in frames
+    pub const fn lifetime(&self) -> (u16, u16) {
+        self.lifetime
+    }
+
+    pub const fn with_speed(self, min: Fixed, max: Fixed) -> Self {
+        Self { speed: (min, max), ..self }
+    }
+
+    pub const fn with_direction(self, direction: Fixed, spread: Fixed) -> Self {
+        Self { direction, spread, ..self }
+    }
+
+    pub const fn with_gravity(self, gravity: FixedVector) -> Self {
+        Self { gravity, ..self }
+    }
+
+    pub const fn with_drag(self, drag: Fixed) -> Self {
+        Self { drag, ..self }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Particle {
+    config: &'static ParticleConfig,
+    position: FixedVector,
+    velocity: FixedVector,
+    age: u16,
+    lifetime: u16,
+}
+
+/// Pool of up to `capacity` particles of any effect
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    capacity: usize,
+}
+
+impl ParticleSystem {
+    pub fn new(capacity: usize) -> Self {
+        Self { particles: Vec::with_capacity(capacity), capacity }
+    }
+
+    /// Emits `count` particles at once at the `position`, skipping those that don't fit
+    pub fn burst(&mut self, config: &'static ParticleConfig, position: FixedVector, count: usize, random: &mut Random) {
+        for _ in 0..count.min(self.capacity - self.particles.len()) {
+            self.emit(config, position, random);
+        }
+    }
+
+    fn emit(&mut self, config: &'static ParticleConfig, position: FixedVector, random: &mut Random) {
+        if self.particles.len() >= self.capacity {
+            return;
+        }
+        let (min_lifetime, max_lifetime) = config.lifetime;
+        let lifetime = random.range_inclusive(min_lifetime as i32..=max_lifetime as i32) as u16;
+        let (min_speed, max_speed) = config.speed;
+        let speed = min_speed + (max_speed - min_speed) * random.fixed();
+        let angle = config.direction + config.spread * (random.fixed() - Fixed::HALF);
+        self.particles.push(Particle {
+            config,
+            position,
+            velocity: FixedVector::from_angle(angle, speed),
+            age: 0,
+            lifetime,
+        });
+    }
+
+    /// Moves and ages every particle by one frame, removing the expired ones
+    pub fn update(&mut self) {
+        let mut index = 0;
+        while index < self.particles.len() {
+            let particle = &mut self.particles[index];
+            particle.age += 1;
+            if particle.age >= particle.lifetime {
+                self.particles.swap_remove(index);
+                continue;
+            }
+            particle.velocity = (particle.velocity + particle.config.gravity) * particle.config.drag;
+            particle.position += particle.velocity;
+            index += 1;
+        }
+    }
+
+    pub fn render(&self, framebuffer: &Framebuffer) {
+        for particle in &self.particles {
+            let config = particle.config;
+            let Some(color) = Self::color(particle) else { continue };
+            let position = particle.position.to_point();
+            match config.shape {
+                ParticleShape::Pixel => framebuffer.set_pixel(position.x, position.y, color),
+                ParticleShape::Rect(size) => {
+                    for y in 0..size.height as i32 {
+                        for x in 0..size.width as i32 {
+                            framebuffer.set_pixel(position.x + x, position.y + y, color);
+                        }
+                    }
+                }
+                ParticleShape::Sprite { sprite, frame_width } => {
+                    let frames = (sprite.width / frame_width.max(1)).max(1);
+                    let frame = particle.age as u32 * frames / particle.lifetime as u32;
+                    let region = Rect::new((frame * frame_width) as i32, 0, frame_width, sprite.height);
+                    if let Flags::BLIT_2BPP = sprite.flags {
+                        framebuffer.sprite_region(sprite, position.x, position.y, region);
+                    } else {
+                        let draw_colors = framebuffer.get_draw_colors();
+                        framebuffer.set_draw_colors([Some(PaletteIndex::Transparent), Some(color), None, None]);
+                        framebuffer.sprite_region(sprite, position.x, position.y, region);
+                        framebuffer.set_draw_colors(draw_colors.map(Some));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Number of living particles
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    fn color(particle: &Particle) -> Option<PaletteIndex> {
+        let colors = particle.config.colors;
+        if colors.is_empty() {
+            return None;
+        }
+        let index = particle.age as usize * colors.len() / particle.lifetime as usize;
+        colors.get(index).copied()
+    }
+}
+
+/// Source of a continuous stream of particles, e.g. smoke or a thruster
+#[derive(Copy, Clone)]
+pub struct Emitter {
+    pub config: &'static ParticleConfig,
+    pub position: FixedVector,
+    /// Particles emitted per frame, e.g. `from_ratio(1, 4)` for one every fourth frame
+    pub rate: Fixed,
+    pub active: bool,
+    accumulated: Fixed,
+}
+
+impl Emitter {
+    pub const fn new(config: &'static ParticleConfig, position: FixedVector, rate: Fixed) -> Self {
+        Self { config, position, rate, active: true, accumulated: Fixed::ZERO }
+    }
+
+    /// Emits the particles due in this frame into the `system`
+    pub fn update(&mut self, system: &mut ParticleSystem, random: &mut Random) {
+        if !self.active {
+            self.accumulated = Fixed::ZERO;
+            return;
+        }
+        self.accumulated += self.rate;
+        while self.accumulated >= Fixed::ONE {
+            self.accumulated -= Fixed::ONE;
+            system.emit(self.config, self.position, random);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+
+    const COLORS: [PaletteIndex; 2] = [PaletteIndex::Palette4, PaletteIndex::Palette2];
+    const DUST: ParticleConfig = ParticleConfig::new(ParticleShape::Pixel, &COLORS)
+        .with_lifetime(4, 4)
+        .with_speed(Fixed::ONE, Fixed::ONE)
+        .with_direction(Fixed::ZERO, Fixed::ZERO)
+        .with_gravity(FixedVector::new(Fixed::ZERO, Fixed::ONE));
+
+    #[test]
+    fn particles_move_change_color_and_expire() {
+        let mut system = ParticleSystem::new(8);
+        system.burst(&DUST, FixedVector::ZERO, 3, &mut Random::new(1));
+        assert_eq!(system.len(), 3);
+        assert!(matches!(ParticleSystem::color(&system.particles[0]), Some(PaletteIndex::Palette4)));
+        system.update();
+        system.update();
+        let particle = system.particles[0];
+        assert_eq!(particle.position.to_point(), Point::new(1, 3));
+        assert!(matches!(ParticleSystem::color(&particle), Some(PaletteIndex::Palette2)));
+        system.update();
+        system.update();
+        assert!(system.is_empty());
+    }
+
+    #[test]
+    fn bursts_stop_at_the_capacity() {
+        let mut system = ParticleSystem::new(5);
+        let mut random = Random::new(2);
+        system.burst(&DUST, FixedVector::ZERO, 3, &mut random);
+        system.burst(&DUST, FixedVector::ZERO, 3, &mut random);
+        assert_eq!(system.len(), 5);
+        system.clear();
+        assert!(system.is_empty());
+    }
+
+    #[test]
+    fn lifetimes_are_within_the_range() {
+        const SPARKS: ParticleConfig = DUST.with_lifetime(2, 6);
+        assert_eq!(SPARKS.lifetime(), (2, 6));
+        let mut system = ParticleSystem::new(64);
+        system.burst(&SPARKS, FixedVector::ZERO, 64, &mut Random::new(3));
+        assert!(system.particles.iter().all(|particle| (2..=6).contains(&particle.lifetime)));
+    }
+
+    #[test]
+    #[should_panic(expected = "lifetime must be positive")]
+    fn zero_lifetime_is_rejected() {
+        let _ = DUST.with_lifetime(0, 0);
+    }
+
+    #[test]
+    fn emitters_accumulate_their_rate() {
+        let mut emitter = Emitter::new(&DUST, FixedVector::ZERO, Fixed::from_ratio(1, 4));
+        let mut system = ParticleSystem::new(8);
+        let mut random = Random::new(4);
+        for _ in 0..4 {
+            emitter.update(&mut system, &mut random);
+        }
+        assert_eq!(system.len(), 1);
+        emitter.active = false;
+        for _ in 0..3 {
+            emitter.update(&mut system, &mut random);
+        }
+        assert_eq!(system.len(), 1);
+    }
+}
@@ -0,0 +1,459 @@
+//! Character controller of a platformer, moving a box through a grid of tiles.
+//!
+//! The box moves one pixel at a time, so it never passes through thin walls, while its position
+//! keeps the fractions of a pixel for smooth acceleration. It climbs and descends 45 degree slopes,
+//! lands on one-way platforms from above and can drop through them.
+
+use crate::fixed::Fixed;
+use crate::gamepad::{Gamepad, GamepadButton};
+use crate::geometry::{FixedVector, Point, Rect, Size, Vector};
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum Tile {
+    #[default]
+    Empty,
+    Solid,
+    /// Platform solid only from above
+    OneWay,
+    /// Slope rising to the right, like `/`
+    SlopeUp,
+    /// Slope falling to the right, like `\`
+    SlopeDown,
+}
+
+/// Grid of square tiles the character collides with
+pub trait TileMap {
+    /// Width and height of a tile in pixels
+    fn tile_size(&self) -> u32;
+
+    fn tile(&self, column: i32, row: i32) -> Tile;
+}
+
+/// Tiles stored row by row, empty outside of the grid
+#[derive(Copy, Clone, Debug)]
+pub struct TileGrid<'a> {
+    pub tiles: &'a [Tile],
+    pub columns: u32,
+    pub tile_size: u32,
+}
+
+impl TileMap for TileGrid<'_> {
+    fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    fn tile(&self, column: i32, row: i32) -> Tile {
+        if column < 0 || row < 0 || column >= self.columns as i32 {
+            return Tile::Empty;
+        }
+        let index = row as usize * self.columns as usize + column as usize;
+        self.tiles.get(index).copied().unwrap_or(Tile::Empty)
+    }
+}
+
+/// Sides of the box touching the tiles after the last move
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Contacts {
+    pub ground: bool,
+    pub ceiling: bool,
+    pub left_wall: bool,
+    pub right_wall: bool,
+    /// Standing on a slope
+    pub slope: bool,
+    /// Standing on a one-way platform
+    pub one_way: bool,
+}
+
+/// Box moving through the tiles with fixed-point position and velocity
+#[derive(Copy, Clone, Debug)]
+pub struct Body {
+    /// Top left corner
+    pub position: FixedVector,
+    /// Pixels per frame
+    pub velocity: FixedVector,
+    pub size: Size,
+    pub contacts: Contacts,
+}
+
+impl Body {
+    pub fn new(position: Point, size: Size) -> Self {
+        Self { position: position.to_vector().to_fixed(), velocity: FixedVector::ZERO, size, contacts: Contacts::default() }
+    }
+
+    /// Pixels covered by the box
+    pub fn bounds(&self) -> Rect {
+        Rect::from_origin_size(self.position.to_point(), self.size)
+    }
+
+    /// Moves the box by its velocity, stopping at the tiles
+    /// * `drop_through` - Whether to fall through one-way platforms
+    pub fn move_and_collide(&mut self, map: &impl TileMap, drop_through: bool) {
+        let was_grounded = self.contacts.ground;
+        self.move_x(map, was_grounded);
+        self.move_y(map, drop_through);
+        self.update_contacts(map, drop_through);
+    }
+
+    fn move_x(&mut self, map: &impl TileMap, grounded: bool) {
+        let target = self.position.x + self.velocity.x;
+        let steps = target.to_int() - self.position.x.to_int();
+        let direction = steps.signum();
+        let mut bounds = self.bounds();
+        for _ in 0..steps.abs() {
+            let next = bounds.translate(Vector::new(direction, 0));
+            if !collides(map, &next, None) {
+                bounds = next;
+                // Follows a slope going down instead of walking off it
+                let below = bounds.translate(Vector::new(0, 1));
+                let two_below = bounds.translate(Vector::new(0, 2));
+                if grounded && !collides(map, &below, None) && collides(map, &two_below, None) {
+                    bounds = below;
+                }
+                continue;
+            }
+            let climbed = next.translate(Vector::new(0, -1));
+            if grounded && !collides(map, &climbed, None) {
+                bounds = climbed;
+                continue;
+            }
+            self.position.x = Fixed::from_int(bounds.x);
+            self.position.y = Fixed::from_int(bounds.y) + self.position.y.fract();
+            self.velocity.x = Fixed::ZERO;
+            return;
+        }
+        self.position.x = target;
+        self.position.y = Fixed::from_int(bounds.y) + self.position.y.fract();
+    }
+
+    fn move_y(&mut self, map: &impl TileMap, drop_through: bool) {
+        let target = self.position.y + self.velocity.y;
+        let steps = target.to_int() - self.position.y.to_int();
+        let direction = steps.signum();
+        let mut bounds = self.bounds();
+        for _ in 0..steps.abs() {
+            let next = bounds.translate(Vector::new(0, direction));
+            let one_way = if direction > 0 && !drop_through { Some(bounds.bottom()) } else { None };
+            if collides(map, &next, one_way) {
+                self.position.y = Fixed::from_int(bounds.y);
+                self.velocity.y = Fixed::ZERO;
+                return;
+            }
+            bounds = next;
+        }
+        self.position.y = target;
+    }
+
+    fn update_contacts(&mut self, map: &impl TileMap, drop_through: bool) {
+        let bounds = self.bounds();
+        let below = bounds.translate(Vector::new(0, 1));
+        let one_way = if drop_through { None } else { Some(bounds.bottom()) };
+        let touches = |x: i32, y: i32| collides(map, &bounds.translate(Vector::new(x, y)), None);
+        self.contacts.ground = self.velocity.y >= Fixed::ZERO && collides(map, &below, one_way);
+        self.contacts.ceiling = touches(0, -1);
+        // A slope the box could climb isn't a wall
+        self.contacts.left_wall = touches(-1, 0) && touches(-1, -1);
+        self.contacts.right_wall = touches(1, 0) && touches(1, -1);
+        self.contacts.slope = false;
+        self.contacts.one_way = false;
+        if self.contacts.ground {
+            for_each_tile(map, &below, |tile, _| match tile {
+                Tile::SlopeUp | Tile::SlopeDown => self.contacts.slope = true,
+                Tile::OneWay => self.contacts.one_way = true,
+                _ => {}
+            });
+        }
+    }
+}
+
+/// Calls the `visit` with every tile under the `rect` and the pixels of the tile
+fn for_each_tile(map: &impl TileMap, rect: &Rect, mut visit: impl FnMut(Tile, Rect)) {
+    let size = map.tile_size() as i32;
+    for row in rect.top().div_euclid(size)..=(rect.bottom() - 1).div_euclid(size) {
+        for column in rect.left().div_euclid(size)..=(rect.right() - 1).div_euclid(size) {
+            let tile_rect = Rect::new(column * size, row * size, size as u32, size as u32);
+            visit(map.tile(column, row), tile_rect);
+        }
+    }
+}
+
+/// Whether the `rect` overlaps a solid part of the tiles.
+/// * `one_way_bottom` - Bottom of the box before moving down, the one-way platforms
+///   are solid only for a box which was above them
+fn collides(map: &impl TileMap, rect: &Rect, one_way_bottom: Option<i32>) -> bool {
+    let mut collides = false;
+    for_each_tile(map, rect, |tile, tile_rect| {
+        let Some(area) = rect.intersection(&tile_rect) else { return };
+        collides |= match tile {
+            Tile::Empty => false,
+            Tile::Solid => true,
+            Tile::OneWay => one_way_bottom.is_some_and(|bottom| bottom <= tile_rect.top() && area.top() == tile_rect.top()),
+            // The lowest pixel of the area towards the high side of the slope is the most likely to be solid
+            Tile::SlopeUp => {
+                let x = area.right() - 1 - tile_rect.left();
+                area.bottom() - 1 - tile_rect.top() >= tile_rect.height as i32 - 1 - x
+            }
+            Tile::SlopeDown => {
+                let x = area.left() - tile_rect.left();
+                area.bottom() - 1 - tile_rect.top() >= x
+            }
+        };
+    });
+    collides
+}
+
+/// Tuning of the movement, in pixels and frames
+#[derive(Copy, Clone, Debug)]
+pub struct ControllerConfig {
+    /// Speed gained every frame while walking
+    pub acceleration: Fixed,
+    /// Speed lost every frame without any direction held
+    pub deceleration: Fixed,
+    pub max_speed: Fixed,
+    /// Speed gained downwards every frame while falling
+    pub gravity: Fixed,
+    pub max_fall_speed: Fixed,
+    /// Upwards speed at the start of a jump
+    pub jump_speed: Fixed,
+    /// Factor of the upwards speed kept when the jump button is released early, for shorter jumps
+    pub jump_cut: Fixed,
+    /// Frames after walking off a ledge during which a jump is still allowed
+    pub coyote_frames: u32,
+    /// Frames before landing during which a jump press is remembered
+    pub jump_buffer_frames: u32,
+}
+
+impl ControllerConfig {
+    pub const DEFAULT: ControllerConfig = ControllerConfig {
+        acceleration: Fixed::from_ratio(1, 4),
+        deceleration: Fixed::from_ratio(1, 3),
+        max_speed: Fixed::from_ratio(3, 2),
+        gravity: Fixed::from_ratio(1, 5),
+        max_fall_speed: Fixed::from_int(4),
+        jump_speed: Fixed::from_ratio(7, 2),
+        jump_cut: Fixed::HALF,
+        coyote_frames: 6,
+        jump_buffer_frames: 6,
+    };
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// State of the buttons controlling the character in a frame
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct ControllerInput {
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub jump_pressed: bool,
+    pub jump_held: bool,
+}
+
+impl ControllerInput {
+    /// D-pad to walk, `ButtonX` to jump, down and `ButtonX` to drop through one-way platforms
+    pub fn from_gamepad(gamepad: &Gamepad) -> Self {
+        Self {
+            left: gamepad.is_held(GamepadButton::DPadLeft),
+            right: gamepad.is_held(GamepadButton::DPadRight),
+            down: gamepad.is_held(GamepadButton::DPadDown),
+            jump_pressed: gamepad.is_pressed(GamepadButton::ButtonX),
+            jump_held: gamepad.is_held(GamepadButton::ButtonX),
+        }
+    }
+}
+
+/// Walking and jumping character with coyote time and jump buffering
+#[derive(Copy, Clone, Debug)]
+pub struct Controller {
+    pub body: Body,
+    pub config: ControllerConfig,
+    /// Frames since the character was last on the ground
+    airborne_frames: u32,
+    /// Frames since the jump was pressed, `None` when there is no pending jump
+    jump_buffer: Option<u32>,
+    jumping: bool,
+    /// Bottom of the box when it started dropping through a one-way platform
+    dropping_from: Option<i32>,
+}
+
+impl Controller {
+    pub fn new(body: Body, config: ControllerConfig) -> Self {
+        Self { body, config, airborne_frames: 0, jump_buffer: None, jumping: false, dropping_from: None }
+    }
+
+    /// Moves the character with the gamepad, see `ControllerInput::from_gamepad`
+    pub fn update(&mut self, gamepad: &Gamepad, map: &impl TileMap) {
+        self.update_with(ControllerInput::from_gamepad(gamepad), map);
+    }
+
+    /// Moves the character with the `input`, e.g. read from an `ActionMap`
+    pub fn update_with(&mut self, input: ControllerInput, map: &impl TileMap) {
+        let config = self.config;
+        let body = &mut self.body;
+
+        let direction = input.right as i32 - input.left as i32;
+        body.velocity.x = if direction != 0 {
+            (body.velocity.x + config.acceleration * direction).clamp(-config.max_speed, config.max_speed)
+        } else if body.velocity.x > Fixed::ZERO {
+            (body.velocity.x - config.deceleration).max(Fixed::ZERO)
+        } else {
+            (body.velocity.x + config.deceleration).min(Fixed::ZERO)
+        };
+        body.velocity.y = (body.velocity.y + config.gravity).min(config.max_fall_speed);
+
+        if input.jump_pressed {
+            self.jump_buffer = Some(0);
+        }
+        let drop_through = input.down && input.jump_pressed && body.contacts.one_way;
+        if drop_through {
+            self.jump_buffer = None;
+            self.dropping_from = Some(body.bounds().bottom());
+        }
+        let can_jump = body.contacts.ground || self.airborne_frames < config.coyote_frames;
+        if can_jump && !self.jumping && self.jump_buffer.is_some() {
+            body.velocity.y = -config.jump_speed;
+            self.jump_buffer = None;
+            self.jumping = true;
+            self.airborne_frames = config.coyote_frames;
+        }
+        if self.jumping && !input.jump_held && body.velocity.y < Fixed::ZERO {
+            body.velocity.y *= config.jump_cut;
+            self.jumping = false;
+        }
+
+        body.move_and_collide(map, self.dropping_from.is_some());
+        // The platform stops being solid once the box is below its top
+        self.dropping_from = self.dropping_from.filter(|bottom| body.bounds().bottom() <= *bottom);
+
+        if body.contacts.ground {
+            self.airborne_frames = 0;
+            self.jumping = false;
+        } else {
+            self.airborne_frames = self.airborne_frames.saturating_add(1);
+            if body.velocity.y >= Fixed::ZERO {
+                self.jumping = false;
+            }
+        }
+        self.jump_buffer = self.jump_buffer
+            .map(|frames| frames + 1)
+            .filter(|frames| *frames <= config.jump_buffer_frames);
+    }
+
+    pub fn contacts(&self) -> Contacts {
+        self.body.contacts
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.body.contacts.ground
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E: Tile = Tile::Empty;
+    const S: Tile = Tile::Solid;
+    const O: Tile = Tile::OneWay;
+    const U: Tile = Tile::SlopeUp;
+
+    fn grid(tiles: &[Tile]) -> TileGrid<'_> {
+        TileGrid { tiles, columns: 4, tile_size: 8 }
+    }
+
+    fn body(x: Fixed, y: Fixed, velocity: FixedVector) -> Body {
+        Body { position: FixedVector::new(x, y), velocity, size: Size::new(4, 4), contacts: Contacts::default() }
+    }
+
+    #[test]
+    fn grid_is_empty_outside() {
+        let map = grid(&[S, S, S, S]);
+        assert_eq!(map.tile(3, 0), S);
+        assert_eq!(map.tile(4, 0), E);
+        assert_eq!(map.tile(0, 1), E);
+        assert_eq!(map.tile(-1, 0), E);
+    }
+
+    #[test]
+    fn falling_lands_on_the_ground() {
+        let map = grid(&[E, E, E, E, S, S, S, S]);
+        let mut body = body(Fixed::from_int(2), Fixed::ONE, FixedVector::new(Fixed::ZERO, Fixed::from_int(5)));
+        body.move_and_collide(&map, false);
+        assert_eq!(body.position.y, Fixed::from_int(4));
+        assert_eq!(body.velocity.y, Fixed::ZERO);
+        assert!(body.contacts.ground && !body.contacts.slope);
+    }
+
+    #[test]
+    fn walls_stop_only_the_horizontal_move() {
+        let map = grid(&[E, E, S, E]);
+        let y = Fixed::from_ratio(5, 2);
+        let mut body = body(Fixed::from_int(2), y, FixedVector::new(Fixed::from_int(12), Fixed::ZERO));
+        body.move_and_collide(&map, false);
+        assert_eq!(body.position, FixedVector::new(Fixed::from_int(12), y));
+        assert_eq!(body.velocity.x, Fixed::ZERO);
+        assert!(body.contacts.right_wall && !body.contacts.left_wall);
+    }
+
+    #[test]
+    fn grounded_bodies_climb_slopes() {
+        let map = grid(&[E, E, E, E, E, U, S, S, S, S, S, S]);
+        let mut body = body(Fixed::ZERO, Fixed::from_int(12), FixedVector::new(Fixed::from_int(2), Fixed::ZERO));
+        body.contacts.ground = true;
+        for _ in 0..6 {
+            body.move_and_collide(&map, false);
+        }
+        assert_eq!(body.bounds(), Rect::new(12, 4, 4, 4));
+        assert!(body.contacts.ground);
+    }
+
+    #[test]
+    fn one_way_platforms_are_solid_only_from_above() {
+        let map = grid(&[E, E, E, E, O, O, O, O, E, E, E, E]);
+        let mut falling = body(Fixed::ZERO, Fixed::ZERO, FixedVector::new(Fixed::ZERO, Fixed::from_int(6)));
+        falling.move_and_collide(&map, false);
+        assert_eq!(falling.position.y, Fixed::from_int(4));
+        assert!(falling.contacts.one_way);
+
+        let mut dropping = falling;
+        dropping.velocity.y = Fixed::from_int(2);
+        dropping.move_and_collide(&map, true);
+        assert_eq!(dropping.position.y, Fixed::from_int(6));
+
+        let mut rising = body(Fixed::ZERO, Fixed::from_int(18), FixedVector::new(Fixed::ZERO, Fixed::from_int(-12)));
+        rising.move_and_collide(&map, false);
+        assert_eq!(rising.position.y, Fixed::from_int(6));
+    }
+
+    #[test]
+    fn jumps_are_buffered_and_allowed_after_leaving_a_ledge() {
+        let map = grid(&[E, E, E, E, E, E, E, E, S, E, E, E]);
+        let jump = ControllerInput { jump_pressed: true, jump_held: true, ..ControllerInput::default() };
+        let mut controller = Controller::new(Body::new(Point::new(2, 12), Size::new(4, 4)), ControllerConfig::DEFAULT);
+        controller.update_with(ControllerInput::default(), &map);
+        assert!(controller.is_grounded());
+
+        // Walks off the ledge and jumps within the coyote frames
+        let right = ControllerInput { right: true, ..ControllerInput::default() };
+        while controller.is_grounded() {
+            controller.update_with(right, &map);
+        }
+        controller.update_with(jump, &map);
+        assert!(controller.body.velocity.y < Fixed::ZERO);
+
+        // Pressed just before landing, the jump starts on the ground
+        let mut controller = Controller::new(Body::new(Point::new(2, 10), Size::new(4, 4)), ControllerConfig::DEFAULT);
+        controller.airborne_frames = ControllerConfig::DEFAULT.coyote_frames;
+        controller.update_with(jump, &map);
+        let held = ControllerInput { jump_held: true, ..ControllerInput::default() };
+        let mut jumped = false;
+        for _ in 0..ControllerConfig::DEFAULT.jump_buffer_frames {
+            controller.update_with(held, &map);
+            jumped |= controller.body.velocity.y < Fixed::ZERO;
+        }
+        assert!(jumped);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::gamepad::GamepadButton;
+use crate::inputs::Inputs;
+use crate::system;
+
+/// One of the four players, each using its own gamepad
+#[repr(u8)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Player {
+    One = 0,
+    Two = 1,
+    Three = 2,
+    Four = 3,
+}
+
+impl Player {
+    pub const ALL: [Player; 4] = [Player::One, Player::Two, Player::Three, Player::Four];
+
+    /// * `index` - Index of the player between 0 and 3
+    pub const fn from_index(index: usize) -> Option<Self> {
+        match index {
+            0 => Some(Player::One),
+            1 => Some(Player::Two),
+            2 => Some(Player::Three),
+            3 => Some(Player::Four),
+            _ => None,
+        }
+    }
+
+    /// Index of the player between 0 and 3
+    pub const fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Number of the player between 1 and 4, as shown to the players
+    pub const fn number(self) -> u8 {
+        self as u8 + 1
+    }
+}
+
+/// Player controlling this instance of the game when netplay is active, otherwise `None`
+pub fn netplay_local_player() -> Option<Player> {
+    let netplay = unsafe { *system::NETPLAY };
+    if netplay & system::NETPLAY_ACTIVE == 0 {
+        return None;
+    }
+    Player::from_index((netplay & system::NETPLAY_PLAYER_MASK) as usize)
+}
+
+pub fn is_netplay_active() -> bool {
+    netplay_local_player().is_some()
+}
+
+/// "Press X to join" flow of a party game: players join by pressing the join button
+/// and leave by pressing the leave button, before the game starts
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct Lobby {
+    /// Bitmask of the joined players
+    joined: u8,
+}
+
+impl Lobby {
+    pub const fn new() -> Self {
+        Self { joined: 0 }
+    }
+
+    /// Lets the players join with the `join` button and leave with the `leave` button,
+    /// returns the player who joined in this frame
+    pub fn update(&mut self, inputs: &Inputs, join: GamepadButton, leave: GamepadButton) -> Option<Player> {
+        let mut joined = None;
+        for (player, gamepad) in inputs.gamepads() {
+            if !self.is_joined(player) && gamepad.is_pressed(join) {
+                self.join(player);
+                joined = joined.or(Some(player));
+            } else if self.is_joined(player) && gamepad.is_pressed(leave) {
+                self.leave(player);
+            }
+        }
+        joined
+    }
+
+    pub fn join(&mut self, player: Player) {
+        self.joined |= 1 << player.index();
+    }
+
+    pub fn leave(&mut self, player: Player) {
+        self.joined &= !(1 << player.index());
+    }
+
+    pub fn is_joined(&self, player: Player) -> bool {
+        self.joined & 1 << player.index() != 0
+    }
+
+    /// Joined players, ordered by their number
+    pub fn players(&self) -> impl Iterator<Item=Player> + '_ {
+        Player::ALL.into_iter().filter(|player| self.is_joined(*player))
+    }
+
+    pub fn count(&self) -> usize {
+        self.joined.count_ones() as usize
+    }
+
+    /// Whether at least `minimum` players joined, so the game can start
+    pub fn is_ready(&self, minimum: usize) -> bool {
+        self.count() >= minimum
+    }
+
+    pub fn clear(&mut self) {
+        self.joined = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn players_are_numbered_from_one() {
+        for (index, player) in Player::ALL.into_iter().enumerate() {
+            assert_eq!(Player::from_index(index), Some(player));
+            assert_eq!(player.index(), index);
+            assert_eq!(player.number() as usize, index + 1);
+        }
+        assert_eq!(Player::from_index(4), None);
+    }
+
+    #[test]
+    fn players_join_and_leave_with_their_buttons() {
+        let mut inputs = unsafe { Inputs::new() };
+        let mut lobby = Lobby::new();
+        inputs.gamepad3.early_update(GamepadButton::ButtonX as u8);
+        inputs.gamepad2.early_update(GamepadButton::ButtonX as u8);
+        assert_eq!(lobby.update(&inputs, GamepadButton::ButtonX, GamepadButton::ButtonY), Some(Player::Two));
+        assert_eq!(lobby.players().collect::<Vec<_>>(), [Player::Two, Player::Three]);
+        assert!(lobby.is_ready(2) && !lobby.is_ready(3));
+
+        unsafe { inputs.gamepad2.late_update() };
+        unsafe { inputs.gamepad3.late_update() };
+        assert_eq!(lobby.update(&inputs, GamepadButton::ButtonX, GamepadButton::ButtonY), None);
+        assert_eq!(lobby.count(), 2);
+
+        inputs.gamepad3.early_update(GamepadButton::ButtonY as u8);
+        assert_eq!(lobby.update(&inputs, GamepadButton::ButtonX, GamepadButton::ButtonY), None);
+        assert!(!lobby.is_joined(Player::Three));
+        assert_eq!(lobby.count(), 1);
+    }
+
+    #[test]
+    fn clearing_the_lobby_removes_every_player() {
+        let mut lobby = Lobby::new();
+        lobby.join(Player::One);
+        lobby.join(Player::Four);
+        lobby.join(Player::Four);
+        assert_eq!(lobby.count(), 2);
+        lobby.clear();
+        assert_eq!(lobby.players().next(), None);
+    }
+}
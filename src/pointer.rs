@@ -0,0 +1,376 @@
+//! Hit testing of the mouse against rectangles registered by the game, turned into
+//! hover, click, double-click and drag-and-drop events.
+//!
+//! The regions are registered again every frame as they are drawn, so the mouse is tested
+//! against what the player saw:
+//!
+//! ```ignore
+//! // update
+//! pointer.update(&inputs.mouse);
+//! for event in pointer.events() {
+//!     if let PointerEvent::Dropped { payload, target: Some(Slot(slot)), .. } = event {
+//!         board.place(*payload, *slot);
+//!     }
+//! }
+//!
+//! // render
+//! pointer.clear_regions();
+//! for (index, card) in hand.iter().enumerate() {
+//!     framebuffer.sprite_at(card.sprite, card.position);
+//!     pointer.add(Region::new(Card(index), card.bounds()).with_payload(*card));
+//! }
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::clock;
+use crate::geometry::{Point, Rect, Vector};
+use crate::mouse::{Mouse, MouseButton};
+
+/// Frames between two clicks for them to make a double click
+pub const DOUBLE_CLICK_FRAMES: u32 = 20;
+/// Pixels the mouse has to move with the left button held to start dragging
+pub const DRAG_THRESHOLD: i32 = 3;
+
+/// Rectangle reacting to the mouse
+/// * `T` - Identifier of the region, e.g. an enum of the game
+/// * `P` - Value carried when the region is dragged
+#[derive(Copy, Clone, Debug)]
+pub struct Region<T, P = ()> {
+    pub id: T,
+    pub rect: Rect,
+    /// Regions with a higher z are above the others, the last added first for the same z
+    pub z: i32,
+    /// Makes the region draggable, carrying the payload until it's dropped
+    pub payload: Option<P>,
+    pub drop_target: bool,
+}
+
+impl<T, P> Region<T, P> {
+    pub const fn new(id: T, rect: Rect) -> Self {
+        Self { id, rect, z: 0, payload: None, drop_target: false }
+    }
+
+    pub fn with_z(self, z: i32) -> Self {
+        Self { z, ..self }
+    }
+
+    pub fn with_payload(self, payload: P) -> Self {
+        Self { payload: Some(payload), ..self }
+    }
+
+    /// Makes it receive the `Dropped` events of the payloads released above it
+    pub fn with_drop_target(self) -> Self {
+        Self { drop_target: true, ..self }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PointerEvent<T, P = ()> {
+    /// The mouse moved over the region
+    Entered(T),
+    /// The mouse moved out of the region
+    Left(T),
+    Pressed(T, MouseButton),
+    /// The button was pressed and released over the region without dragging
+    Clicked(T, MouseButton),
+    /// Second left click on the same region within `DOUBLE_CLICK_FRAMES`, after its `Clicked`
+    DoubleClicked(T),
+    DragStarted(T),
+    /// The left button was released while dragging the `source`,
+    /// above the topmost drop target or `None` outside of all of them
+    Dropped { source: T, target: Option<T>, payload: P },
+}
+
+/// Region being dragged
+#[derive(Copy, Clone, Debug)]
+pub struct Drag<T, P> {
+    pub source: T,
+    pub payload: P,
+    /// Position of the mouse when the left button was pressed
+    pub start: Point,
+    pub position: Point,
+    /// Position of the mouse relative to the top left corner of the region when it was grabbed
+    pub grab: Vector,
+}
+
+impl<T, P> Drag<T, P> {
+    /// Top left corner to draw the dragged item at, so it follows the mouse where it was grabbed
+    pub fn origin(&self) -> Point {
+        self.position - self.grab
+    }
+}
+
+/// Routes the mouse to the topmost region under it
+pub struct Pointer<T, P = ()> {
+    regions: Vec<Region<T, P>>,
+    events: Vec<PointerEvent<T, P>>,
+    position: Point,
+    hovered: Option<T>,
+    /// Region and position of the mouse when each `MouseButton` was pressed
+    pressed: [Option<(T, Point)>; 3],
+    last_buttons: u8,
+    last_click: Option<(T, u32)>,
+    drag: Option<Drag<T, P>>,
+}
+
+impl<T: Copy + Eq, P: Clone> Pointer<T, P> {
+    pub const fn new() -> Self {
+        Self {
+            regions: Vec::new(),
+            events: Vec::new(),
+            position: Point::new(0, 0),
+            hovered: None,
+            pressed: [None; 3],
+            last_buttons: 0,
+            last_click: None,
+            drag: None,
+        }
+    }
+
+    /// Removes every region, before registering those of the frame
+    pub fn clear_regions(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn add(&mut self, region: Region<T, P>) {
+        self.regions.push(region);
+    }
+
+    /// Tests the mouse against the regions and replaces the events with those of this frame
+    pub fn update(&mut self, mouse: &Mouse) {
+        self.update_with(mouse.position(), mouse.buttons(), clock::frame());
+    }
+
+    /// Like `update` with the state of the mouse given, e.g. from a replay
+    /// * `buttons` - Held buttons as a mask of `MouseButton`
+    /// * `frame` - Current frame, to detect double clicks
+    pub fn update_with(&mut self, position: Point, buttons: u8, frame: u32) {
+        self.events.clear();
+        self.position = position;
+        let hit = self.region_at(position, |_| true).map(|region| region.id);
+
+        if hit != self.hovered {
+            if let Some(hovered) = self.hovered {
+                self.events.push(PointerEvent::Left(hovered));
+            }
+            if let Some(hit) = hit {
+                self.events.push(PointerEvent::Entered(hit));
+            }
+            self.hovered = hit;
+        }
+
+        for (index, button) in MouseButton::ALL.into_iter().enumerate() {
+            let held = buttons & button as u8 != 0;
+            let was_held = self.last_buttons & button as u8 != 0;
+            if held && !was_held {
+                self.pressed[index] = hit.map(|hit| (hit, position));
+                if let Some(hit) = hit {
+                    self.events.push(PointerEvent::Pressed(hit, button));
+                }
+            } else if !held && was_held {
+                if button == MouseButton::Left {
+                    self.drop();
+                }
+                if let Some((pressed, _)) = self.pressed[index].take().filter(|(pressed, _)| Some(*pressed) == hit) {
+                    self.click(pressed, button, frame);
+                }
+            } else if held && button == MouseButton::Left {
+                self.drag_to(index, position);
+            }
+        }
+        self.last_buttons = buttons;
+    }
+
+    /// Events of the last update, in the order they happened
+    pub fn events(&self) -> &[PointerEvent<T, P>] {
+        &self.events
+    }
+
+    /// Region under the mouse
+    pub fn hovered(&self) -> Option<T> {
+        self.hovered
+    }
+
+    pub fn is_hovered(&self, id: T) -> bool {
+        self.hovered == Some(id)
+    }
+
+    /// Whether the region was pressed with the button, which is still held
+    pub fn is_pressed(&self, id: T, button: MouseButton) -> bool {
+        let index = MouseButton::ALL.iter().position(|other| *other == button).unwrap();
+        self.pressed[index].is_some_and(|(pressed, _)| pressed == id)
+    }
+
+    pub fn drag(&self) -> Option<&Drag<T, P>> {
+        self.drag.as_ref()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Drop target the payload would be dropped on if released now
+    pub fn drop_target(&self) -> Option<T> {
+        let source = self.drag.as_ref()?.source;
+        self.region_at(self.position, |region| region.drop_target && region.id != source).map(|region| region.id)
+    }
+
+    /// Stops dragging without any `Dropped` event, e.g. when the game is paused
+    pub fn cancel_drag(&mut self) {
+        self.drag = None;
+    }
+
+    /// Topmost region containing the point and matching the `filter`
+    pub fn region_at(&self, point: Point, filter: impl Fn(&Region<T, P>) -> bool) -> Option<&Region<T, P>> {
+        // max_by_key keeps the last of the equal ones, the last added for the same z
+        self.regions.iter()
+            .filter(|region| region.rect.contains(point) && filter(region))
+            .max_by_key(|region| region.z)
+    }
+
+    fn click(&mut self, id: T, button: MouseButton, frame: u32) {
+        self.events.push(PointerEvent::Clicked(id, button));
+        if button != MouseButton::Left {
+            return;
+        }
+        match self.last_click {
+            Some((last, at)) if last == id && frame.wrapping_sub(at) <= DOUBLE_CLICK_FRAMES => {
+                self.events.push(PointerEvent::DoubleClicked(id));
+                self.last_click = None;
+            }
+            _ => self.last_click = Some((id, frame)),
+        }
+    }
+
+    fn drag_to(&mut self, index: usize, position: Point) {
+        if let Some(drag) = &mut self.drag {
+            drag.position = position;
+            return;
+        }
+        let Some((pressed, start)) = self.pressed[index] else { return };
+        let moved = position - start;
+        if moved.x.abs().max(moved.y.abs()) < DRAG_THRESHOLD {
+            return;
+        }
+        let Some(region) = self.regions.iter().rev().find(|region| region.id == pressed) else { return };
+        let Some(payload) = region.payload.clone() else { return };
+        self.drag = Some(Drag { source: pressed, payload, start, position, grab: start - region.rect.origin() });
+        // Dragging isn't clicking
+        self.pressed[index] = None;
+        self.events.push(PointerEvent::DragStarted(pressed));
+    }
+
+    fn drop(&mut self) {
+        let target = self.drop_target();
+        let Some(drag) = self.drag.take() else { return };
+        self.events.push(PointerEvent::Dropped { source: drag.source, target, payload: drag.payload });
+    }
+}
+
+impl<T: Copy + Eq, P: Clone> Default for Pointer<T, P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEFT: u8 = MouseButton::Left as u8;
+    const RIGHT: u8 = MouseButton::Right as u8;
+
+    #[derive(Copy, Clone, Eq, PartialEq, Debug)]
+    enum Id {
+        Card,
+        Slot,
+        Panel,
+    }
+
+    fn pointer() -> Pointer<Id, u8> {
+        let mut pointer = Pointer::new();
+        pointer.add(Region::new(Id::Panel, Rect::new(0, 0, 100, 100)));
+        pointer.add(Region::new(Id::Card, Rect::new(10, 10, 10, 10)).with_z(1).with_payload(7));
+        pointer.add(Region::new(Id::Slot, Rect::new(50, 50, 10, 10)).with_drop_target());
+        pointer
+    }
+
+    #[test]
+    fn hover_follows_the_topmost_region() {
+        let mut pointer = pointer();
+        pointer.update_with(Point::new(12, 12), 0, 0);
+        assert_eq!(pointer.events(), [PointerEvent::Entered(Id::Card)]);
+        pointer.update_with(Point::new(13, 12), 0, 1);
+        assert!(pointer.events().is_empty());
+        pointer.update_with(Point::new(55, 55), 0, 2);
+        assert_eq!(pointer.events(), [PointerEvent::Left(Id::Card), PointerEvent::Entered(Id::Slot)]);
+        assert!(pointer.is_hovered(Id::Slot));
+        pointer.update_with(Point::new(200, 0), 0, 3);
+        assert_eq!(pointer.events(), [PointerEvent::Left(Id::Slot)]);
+        assert_eq!(pointer.hovered(), None);
+    }
+
+    #[test]
+    fn click_needs_the_release_over_the_same_region() {
+        let mut pointer = pointer();
+        pointer.update_with(Point::new(12, 12), RIGHT, 0);
+        assert_eq!(pointer.events(), [PointerEvent::Entered(Id::Card), PointerEvent::Pressed(Id::Card, MouseButton::Right)]);
+        assert!(pointer.is_pressed(Id::Card, MouseButton::Right));
+        pointer.update_with(Point::new(12, 12), 0, 1);
+        assert_eq!(pointer.events(), [PointerEvent::Clicked(Id::Card, MouseButton::Right)]);
+
+        pointer.update_with(Point::new(55, 55), RIGHT, 2);
+        pointer.update_with(Point::new(80, 80), 0, 3);
+        assert_eq!(pointer.events(), [PointerEvent::Left(Id::Slot), PointerEvent::Entered(Id::Panel)]);
+    }
+
+    #[test]
+    fn double_click_within_the_frames() {
+        let mut pointer = pointer();
+        let click = |pointer: &mut Pointer<Id, u8>, frame| {
+            pointer.update_with(Point::new(80, 80), LEFT, frame);
+            pointer.update_with(Point::new(80, 80), 0, frame + 1);
+        };
+        click(&mut pointer, 0);
+        click(&mut pointer, 10);
+        assert_eq!(pointer.events(), [PointerEvent::Clicked(Id::Panel, MouseButton::Left), PointerEvent::DoubleClicked(Id::Panel)]);
+        click(&mut pointer, 20);
+        click(&mut pointer, 21 + DOUBLE_CLICK_FRAMES);
+        assert_eq!(pointer.events(), [PointerEvent::Clicked(Id::Panel, MouseButton::Left)]);
+    }
+
+    #[test]
+    fn drag_carries_the_payload_to_the_drop_target() {
+        let mut pointer = pointer();
+        pointer.update_with(Point::new(12, 14), LEFT, 0);
+        pointer.update_with(Point::new(13, 15), LEFT, 1);
+        assert!(!pointer.is_dragging());
+        pointer.update_with(Point::new(15, 14), LEFT, 2);
+        assert_eq!(pointer.events(), [PointerEvent::DragStarted(Id::Card)]);
+        pointer.update_with(Point::new(52, 58), LEFT, 3);
+        assert_eq!(pointer.events(), [PointerEvent::Left(Id::Card), PointerEvent::Entered(Id::Slot)]);
+        let drag = pointer.drag().unwrap();
+        assert_eq!((drag.start, drag.origin()), (Point::new(12, 14), Point::new(50, 54)));
+        assert_eq!(pointer.drop_target(), Some(Id::Slot));
+        pointer.update_with(Point::new(52, 58), 0, 4);
+        assert_eq!(pointer.events(), [PointerEvent::Dropped { source: Id::Card, target: Some(Id::Slot), payload: 7 }]);
+        assert!(!pointer.is_dragging());
+    }
+
+    #[test]
+    fn regions_without_payload_are_not_dragged() {
+        let mut pointer = pointer();
+        pointer.update_with(Point::new(80, 80), LEFT, 0);
+        pointer.update_with(Point::new(90, 90), LEFT, 1);
+        assert!(!pointer.is_dragging());
+        pointer.update_with(Point::new(90, 90), 0, 2);
+        assert_eq!(pointer.events(), [PointerEvent::Clicked(Id::Panel, MouseButton::Left)]);
+
+        pointer.update_with(Point::new(12, 12), LEFT, 3);
+        pointer.update_with(Point::new(30, 30), LEFT, 4);
+        pointer.cancel_drag();
+        pointer.update_with(Point::new(55, 55), 0, 5);
+        assert!(!pointer.events().iter().any(|event| matches!(event, PointerEvent::Dropped { .. })));
+    }
+}
@@ -0,0 +1,325 @@
+//! Deterministic pseudo-random numbers, the same sequence for the same seed in every runtime.
+
+use core::ops::{Range, RangeInclusive};
+
+use crate::fixed::Fixed;
+use crate::inputs::Inputs;
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const DEFAULT_STREAM: u64 = 1442695040888963407;
+
+/// Generator of the PCG family (PCG-XSH-RR 64/32), small enough to be stored
+/// in the save data or copied into a netplay rollback snapshot
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Random {
+    state: u64,
+    /// Odd increment selecting one of the independent sequences
+    increment: u64,
+}
+
+impl Random {
+    /// Number of bytes of `to_bytes`
+    pub const SIZE: usize = 16;
+
+    pub const fn new(seed: u64) -> Self {
+        Self::with_stream(seed, DEFAULT_STREAM >> 1)
+    }
+
+    /// Generator of one of the independent sequences, e.g. one for the level layout
+    /// and one for the enemies, so one doesn't change the other
+    pub const fn with_stream(seed: u64, stream: u64) -> Self {
+        let increment = (stream << 1) | 1;
+        let mut random = Self { state: 0, increment };
+        random.step();
+        random.state = random.state.wrapping_add(seed);
+        random.step();
+        random
+    }
+
+    const fn step(&mut self) {
+        self.state = self.state.wrapping_mul(MULTIPLIER).wrapping_add(self.increment);
+    }
+
+    pub const fn next_u32(&mut self) -> u32 {
+        let state = self.state;
+        self.step();
+        let xorshifted = (((state >> 18) ^ state) >> 27) as u32;
+        xorshifted.rotate_right((state >> 59) as u32)
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    /// Number between 0 and `bound - 1`, every one equally likely
+    pub fn below(&mut self, bound: u32) -> u32 {
+        assert!(bound > 0, "bound must be greater than 0");
+        // Rejects the lowest values, which would make some numbers more likely than others
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let value = self.next_u32();
+            if value >= threshold {
+                return value % bound;
+            }
+        }
+    }
+
+    /// Number in the `range`, e.g. `random.range(-4..4)`
+    pub fn range(&mut self, range: Range<i32>) -> i32 {
+        assert!(range.start < range.end, "range must not be empty");
+        let span = range.end.wrapping_sub(range.start) as u32;
+        range.start.wrapping_add(self.below(span) as i32)
+    }
+
+    /// Number in the `range` including its end, e.g. `random.range_inclusive(1..=6)`
+    pub fn range_inclusive(&mut self, range: RangeInclusive<i32>) -> i32 {
+        let (start, end) = range.into_inner();
+        assert!(start <= end, "range must not be empty");
+        let span = end.wrapping_sub(start) as u32;
+        match span.checked_add(1) {
+            Some(count) => start.wrapping_add(self.below(count) as i32),
+            None => self.next_u32() as i32,
+        }
+    }
+
+    /// Number between 0 included and 1 excluded
+    pub fn fixed(&mut self) -> Fixed {
+        Fixed::from_bits((self.next_u32() >> 16) as i32)
+    }
+
+    /// Number in the `range` with fixed-point precision, the end excluded
+    pub fn fixed_range(&mut self, range: Range<Fixed>) -> Fixed {
+        range.start + (range.end - range.start) * self.fixed()
+    }
+
+    pub fn bool(&mut self) -> bool {
+        self.next_u32() & 1 != 0
+    }
+
+    /// `true` with the probability `numerator / denominator`, e.g. `chance(1, 4)` a quarter of the time.
+    /// Panics when the `denominator` is 0.
+    pub fn chance(&mut self, numerator: u32, denominator: u32) -> bool {
+        assert!(denominator > 0, "denominator must be greater than 0");
+        self.below(denominator) < numerator
+    }
+
+    /// Sum of `count` dice with the `sides`, e.g. `roll(2, 6)` for 2d6
+    pub fn roll(&mut self, count: u32, sides: u32) -> u32 {
+        (0..count).map(|_| self.below(sides) + 1).sum()
+    }
+
+    /// Random item of the `items`, `None` when there are none
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        items.get(self.below(items.len() as u32) as usize)
+    }
+
+    /// Index picked with the probability of its weight among the `weights`,
+    /// `None` when every weight is 0
+    pub fn weighted_index(&mut self, weights: &[u32]) -> Option<usize> {
+        self.pick(weights.iter().copied())
+    }
+
+    /// Item picked with the probability of its weight, e.g. for loot tables:
+    /// `random.choose_weighted(&[(Item::Coin, 90), (Item::Gem, 10)])`
+    pub fn choose_weighted<'a, T>(&mut self, items: &'a [(T, u32)]) -> Option<&'a T> {
+        self.pick(items.iter().map(|(_, weight)| *weight)).map(|index| &items[index].0)
+    }
+
+    /// Puts the `items` in a random order
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for index in (1..items.len()).rev() {
+            let other = self.below(index as u32 + 1) as usize;
+            items.swap(index, other);
+        }
+    }
+
+    /// State of the generator, e.g. to be saved on the disk
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0u8; Self::SIZE];
+        bytes[..8].copy_from_slice(&self.state.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.increment.to_le_bytes());
+        bytes
+    }
+
+    /// Restores the generator saved by `to_bytes`, continuing the same sequence
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        let mut state = [0u8; 8];
+        let mut increment = [0u8; 8];
+        state.copy_from_slice(&bytes[..8]);
+        increment.copy_from_slice(&bytes[8..]);
+        Self { state: u64::from_le_bytes(state), increment: u64::from_le_bytes(increment) | 1 }
+    }
+
+    /// Index of the weight picked with its probability, summed in 64 bits so they can't overflow
+    fn pick(&mut self, weights: impl Iterator<Item=u32> + Clone) -> Option<usize> {
+        let total = weights.clone().map(|weight| weight as u64).sum::<u64>();
+        if total == 0 {
+            return None;
+        }
+        let mut target = if total > u32::MAX as u64 {
+            self.next_u64() % total
+        } else {
+            self.below(total as u32) as u64
+        };
+        for (index, weight) in weights.enumerate() {
+            if target < weight as u64 {
+                return Some(index);
+            }
+            target -= weight as u64;
+        }
+        None
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Collects randomness from the timing of the inputs, as WASM-4 has no clock.
+/// Updated every frame from the title screen, it gives a different seed
+/// whenever the player presses start a frame earlier or later.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Entropy {
+    hash: u64,
+    frames: u32,
+}
+
+impl Entropy {
+    pub const fn new() -> Self {
+        Self { hash: DEFAULT_STREAM, frames: 0 }
+    }
+
+    /// Mixes in the inputs of this frame and the number of frames so far
+    pub fn update(&mut self, inputs: &Inputs) {
+        let frame = inputs.frame();
+        self.frames = self.frames.wrapping_add(1);
+        self.mix(u32::from_le_bytes(frame.gamepads) as u64 | (self.frames as u64) << 32);
+        self.mix((frame.mouse_x as u16 as u64) | (frame.mouse_y as u16 as u64) << 16 | (frame.mouse_buttons as u64) << 32);
+    }
+
+    /// Mixes in any other value that differs between plays
+    pub fn mix(&mut self, value: u64) {
+        self.hash = mix64(self.hash ^ value);
+    }
+
+    pub fn seed(&self) -> u64 {
+        mix64(self.hash)
+    }
+
+    /// Generator seeded with the collected randomness
+    pub fn random(&self) -> Random {
+        Random::new(self.seed())
+    }
+}
+
+impl Default for Entropy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Finalizer of SplitMix64, every input bit affects every output bit
+const fn mix64(value: u64) -> u64 {
+    let mut value = value.wrapping_add(0x9e3779b97f4a7c15);
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_sequence() {
+        // First numbers of pcg32-demo seeded with 42 on the stream 54
+        let mut random = Random::with_stream(42, 54);
+        let numbers: [u32; 6] = core::array::from_fn(|_| random.next_u32());
+        assert_eq!(numbers, [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]);
+    }
+
+    #[test]
+    fn streams_and_seeds_differ() {
+        assert_ne!(Random::new(1).next_u32(), Random::new(2).next_u32());
+        assert_ne!(Random::with_stream(1, 1).next_u32(), Random::with_stream(1, 2).next_u32());
+        assert_eq!(Random::default(), Random::new(0));
+    }
+
+    #[test]
+    fn saved_state_continues_the_sequence() {
+        let mut random = Random::new(7);
+        random.next_u32();
+        let mut restored = Random::from_bytes(random.to_bytes());
+        assert_eq!(restored, random);
+        assert_eq!(restored.next_u64(), random.next_u64());
+    }
+
+    #[test]
+    fn ranges_stay_within_their_bounds() {
+        let mut random = Random::new(3);
+        let mut seen = [false; 8];
+        for _ in 0..1000 {
+            seen[(random.range(-4..4) + 4) as usize] = true;
+            assert!((1..=6).contains(&random.range_inclusive(1..=6)));
+            assert!(random.below(1) == 0);
+            let fixed = random.fixed();
+            assert!(fixed >= Fixed::ZERO && fixed < Fixed::ONE);
+            assert!((2..=12).contains(&random.roll(2, 6)));
+        }
+        assert!(seen.iter().all(|seen| *seen));
+        random.range_inclusive(i32::MIN..=i32::MAX);
+        assert_eq!(random.choose::<u8>(&[]), None);
+        assert!(!random.chance(0, 4) && random.chance(4, 4));
+    }
+
+    #[test]
+    #[should_panic(expected = "bound must be greater than 0")]
+    fn below_zero_panics() {
+        Random::new(0).below(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "denominator must be greater than 0")]
+    fn chance_without_denominator_panics() {
+        Random::new(0).chance(1, 0);
+    }
+
+    #[test]
+    fn weights_are_summed_without_overflow() {
+        let mut random = Random::new(11);
+        let items = [('a', u32::MAX), ('b', u32::MAX), ('c', 0)];
+        let mut counts = [0; 3];
+        for _ in 0..200 {
+            counts[(*random.choose_weighted(&items).unwrap() as u8 - b'a') as usize] += 1;
+            assert_ne!(random.weighted_index(&[u32::MAX, 0, u32::MAX]), Some(1));
+        }
+        assert!(counts[0] > 50 && counts[1] > 50 && counts[2] == 0);
+        assert_eq!(random.choose_weighted(&[('a', 0)]), None);
+        assert_eq!(random.weighted_index(&[]), None);
+        assert_eq!(random.weighted_index(&[0, 0, 5]), Some(2));
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut items = [1, 2, 3, 4, 5, 6, 7, 8];
+        Random::new(5).shuffle(&mut items);
+        assert_ne!(items, [1, 2, 3, 4, 5, 6, 7, 8]);
+        items.sort();
+        assert_eq!(items, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn entropy_depends_on_every_value() {
+        let mut a = Entropy::new();
+        let mut b = Entropy::new();
+        a.mix(1);
+        b.mix(2);
+        assert_ne!(a.seed(), b.seed());
+        assert_eq!(a.random(), Random::new(a.seed()));
+    }
+}
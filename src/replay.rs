@@ -0,0 +1,327 @@
+//! Recording of the inputs into a compact stream and deterministic playback of it,
+//! for attract-mode demos, bug reports and ghosts.
+//!
+//! The stream starts with a header, followed by runs of identical frames.
+//! Every run holds a bitmask of the registers changed since the previous run,
+//! the new values of those registers and the number of frames of the run.
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+
+use crate::system;
+
+/// Marks the start of a recording, followed by the format version
+const MAGIC: &[u8; 4] = b"W4IR";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = MAGIC.len() + 1;
+
+const CHANGED_MOUSE_X: u8 = 1 << 4;
+const CHANGED_MOUSE_Y: u8 = 1 << 5;
+const CHANGED_MOUSE_BUTTONS: u8 = 1 << 6;
+
+/// Values of the input registers in a single frame
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct InputFrame {
+    pub gamepads: [u8; 4],
+    pub mouse_x: i16,
+    pub mouse_y: i16,
+    pub mouse_buttons: u8,
+}
+
+impl InputFrame {
+    /// Reads the memory-mapped registers of the current frame
+    pub fn live() -> Self {
+        unsafe {
+            Self {
+                gamepads: [*system::GAMEPAD1, *system::GAMEPAD2, *system::GAMEPAD3, *system::GAMEPAD4],
+                mouse_x: *system::MOUSE_X,
+                mouse_y: *system::MOUSE_Y,
+                mouse_buttons: *system::MOUSE_BUTTONS,
+            }
+        }
+    }
+
+    fn changes(&self, previous: &InputFrame) -> u8 {
+        let mut changes = 0;
+        for (index, (gamepad, previous)) in self.gamepads.iter().zip(previous.gamepads).enumerate() {
+            if *gamepad != previous {
+                changes |= 1 << index;
+            }
+        }
+        if self.mouse_x != previous.mouse_x {
+            changes |= CHANGED_MOUSE_X;
+        }
+        if self.mouse_y != previous.mouse_y {
+            changes |= CHANGED_MOUSE_Y;
+        }
+        if self.mouse_buttons != previous.mouse_buttons {
+            changes |= CHANGED_MOUSE_BUTTONS;
+        }
+        changes
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ReplayError {
+    /// The data doesn't start with a recording header
+    InvalidHeader,
+    /// The recording was made with a different version of the format
+    UnsupportedVersion(u8),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::InvalidHeader => write!(f, "invalid recording header"),
+            ReplayError::UnsupportedVersion(version) => write!(f, "unsupported recording version {}", version),
+        }
+    }
+}
+
+/// Captures the inputs frame by frame into a run-length encoded stream
+#[derive(Clone, Debug)]
+pub struct InputRecorder {
+    bytes: Vec<u8>,
+    /// Frame of the pending run, written once a different frame is recorded
+    last: InputFrame,
+    /// Values of the last run written into the bytes
+    written: InputFrame,
+    run: u32,
+    frames: u32,
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        let mut bytes = Vec::with_capacity(64);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        Self {
+            bytes,
+            last: InputFrame::default(),
+            written: InputFrame::default(),
+            run: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn record(&mut self, frame: &InputFrame) {
+        if self.run > 0 && *frame != self.last {
+            Self::write_run(&mut self.bytes, &self.written, &self.last, self.run);
+            self.written = self.last;
+            self.run = 0;
+        }
+        self.last = *frame;
+        self.run += 1;
+        self.frames += 1;
+    }
+
+    /// Number of frames recorded so far
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Encoded recording of the frames so far, e.g. to be saved on the disk
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.bytes.clone();
+        if self.run > 0 {
+            Self::write_run(&mut bytes, &self.written, &self.last, self.run);
+        }
+        bytes
+    }
+
+    fn write_run(bytes: &mut Vec<u8>, previous: &InputFrame, frame: &InputFrame, run: u32) {
+        let changes = frame.changes(previous);
+        bytes.push(changes);
+        for (index, gamepad) in frame.gamepads.iter().enumerate() {
+            if changes & 1 << index != 0 {
+                bytes.push(*gamepad);
+            }
+        }
+        if changes & CHANGED_MOUSE_X != 0 {
+            bytes.extend_from_slice(&frame.mouse_x.to_le_bytes());
+        }
+        if changes & CHANGED_MOUSE_Y != 0 {
+            bytes.extend_from_slice(&frame.mouse_y.to_le_bytes());
+        }
+        if changes & CHANGED_MOUSE_BUTTONS != 0 {
+            bytes.push(frame.mouse_buttons);
+        }
+        // Variable length quantity, 7 bits per byte with the highest bit marking a continuation
+        let mut run = run;
+        while run >= 0x80 {
+            bytes.push((run & 0x7f) as u8 | 0x80);
+            run >>= 7;
+        }
+        bytes.push(run as u8);
+    }
+}
+
+impl Default for InputRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Plays back a recording made by `InputRecorder`, one frame at a time
+#[derive(Clone, Debug)]
+pub struct InputPlayer {
+    bytes: Cow<'static, [u8]>,
+    position: usize,
+    frame: InputFrame,
+    /// Frames left of the current run
+    remaining: u32,
+}
+
+impl InputPlayer {
+    /// * `bytes` - Recording, e.g. `include_bytes!` of a demo or data loaded from the disk
+    pub fn new(bytes: impl Into<Cow<'static, [u8]>>) -> Result<Self, ReplayError> {
+        let bytes = bytes.into();
+        if bytes.len() < HEADER_SIZE || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(ReplayError::InvalidHeader);
+        }
+        if bytes[MAGIC.len()] != VERSION {
+            return Err(ReplayError::UnsupportedVersion(bytes[MAGIC.len()]));
+        }
+        Ok(Self { bytes, position: HEADER_SIZE, frame: InputFrame::default(), remaining: 0 })
+    }
+
+    /// Whether every recorded frame has been played
+    pub fn is_finished(&self) -> bool {
+        self.remaining == 0 && self.position >= self.bytes.len()
+    }
+
+    /// Starts the playback again from the first frame
+    pub fn rewind(&mut self) {
+        self.position = HEADER_SIZE;
+        self.frame = InputFrame::default();
+        self.remaining = 0;
+    }
+
+    /// Reads the next run, returns `None` when the data ends, also in the middle of a run
+    fn read_run(&mut self) -> Option<()> {
+        let bytes = &self.bytes[..];
+        let mut position = self.position;
+        let mut next = || {
+            let byte = bytes.get(position).copied();
+            position += 1;
+            byte
+        };
+        let changes = next()?;
+        let mut frame = self.frame;
+        for (index, gamepad) in frame.gamepads.iter_mut().enumerate() {
+            if changes & 1 << index != 0 {
+                *gamepad = next()?;
+            }
+        }
+        if changes & CHANGED_MOUSE_X != 0 {
+            frame.mouse_x = i16::from_le_bytes([next()?, next()?]);
+        }
+        if changes & CHANGED_MOUSE_Y != 0 {
+            frame.mouse_y = i16::from_le_bytes([next()?, next()?]);
+        }
+        if changes & CHANGED_MOUSE_BUTTONS != 0 {
+            frame.mouse_buttons = next()?;
+        }
+        let mut run = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = next()?;
+            run |= ((byte & 0x7f) as u32) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        self.position = position;
+        self.frame = frame;
+        self.remaining = run;
+        Some(())
+    }
+}
+
+impl Iterator for InputPlayer {
+    type Item = InputFrame;
+
+    fn next(&mut self) -> Option<InputFrame> {
+        while self.remaining == 0 {
+            if self.read_run().is_none() {
+                self.position = self.bytes.len();
+                return None;
+            }
+        }
+        self.remaining -= 1;
+        Some(self.frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(gamepad: u8, mouse_x: i16, mouse_buttons: u8) -> InputFrame {
+        InputFrame { gamepads: [gamepad, 0, 0, 0], mouse_x, mouse_y: -3, mouse_buttons }
+    }
+
+    #[test]
+    fn recording_plays_back_the_same_frames() {
+        let mut frames = Vec::new();
+        frames.extend([frame(0, 0, 0); 3]);
+        frames.extend([frame(0x10, 80, 0); 200]);
+        frames.push(frame(0x11, -400, 1));
+        frames.extend([InputFrame::default(); 2]);
+        let mut recorder = InputRecorder::new();
+        for frame in &frames {
+            recorder.record(frame);
+        }
+        assert_eq!(recorder.frames(), frames.len() as u32);
+
+        let mut player = InputPlayer::new(recorder.to_bytes()).unwrap();
+        assert_eq!(player.by_ref().collect::<Vec<_>>(), frames);
+        assert!(player.is_finished());
+        player.rewind();
+        assert_eq!(player.next(), Some(frames[0]));
+    }
+
+    #[test]
+    fn identical_frames_take_a_run() {
+        let mut recorder = InputRecorder::new();
+        for _ in 0..300 {
+            recorder.record(&frame(0x40, 0, 0));
+        }
+        // Changes, gamepad, mouse y and a run of 2 bytes
+        assert_eq!(recorder.to_bytes().len(), HEADER_SIZE + 1 + 1 + 2 + 2);
+    }
+
+    #[test]
+    fn recording_can_go_on_after_to_bytes() {
+        let mut recorder = InputRecorder::new();
+        recorder.record(&frame(1, 0, 0));
+        let _ = recorder.to_bytes();
+        recorder.record(&frame(2, 0, 0));
+        let player = InputPlayer::new(recorder.to_bytes()).unwrap();
+        assert_eq!(player.collect::<Vec<_>>(), [frame(1, 0, 0), frame(2, 0, 0)]);
+    }
+
+    #[test]
+    fn truncated_recording_stops_at_the_last_whole_run() {
+        let mut recorder = InputRecorder::new();
+        recorder.record(&frame(1, 0, 0));
+        recorder.record(&frame(2, 5, 0));
+        let mut bytes = recorder.to_bytes();
+        bytes.pop();
+        let mut player = InputPlayer::new(bytes).unwrap();
+        assert_eq!(player.next(), Some(frame(1, 0, 0)));
+        assert_eq!(player.next(), None);
+        assert!(player.is_finished());
+    }
+
+    #[test]
+    fn header_is_checked() {
+        assert_eq!(InputPlayer::new(&b"W4I"[..]).unwrap_err(), ReplayError::InvalidHeader);
+        assert_eq!(InputPlayer::new(&b"W4XR\x01"[..]).unwrap_err(), ReplayError::InvalidHeader);
+        assert_eq!(InputPlayer::new(&b"W4IR\x02"[..]).unwrap_err(), ReplayError::UnsupportedVersion(2));
+        let mut empty = InputPlayer::new(&b"W4IR\x01"[..]).unwrap();
+        assert!(empty.is_finished());
+        assert_eq!(empty.next(), None);
+    }
+}
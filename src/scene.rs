@@ -0,0 +1,276 @@
+//! Stack of scenes, like the title screen, the gameplay and the pause menu.
+//!
+//! Only the scene on top of the stack is updated, and it's rendered over the scenes below
+//! as long as it's an overlay. The application forwards its calls to the stack:
+//!
+//! ```ignore
+//! struct Game { scenes: SceneStack<Progress> }
+//!
+//! impl Application for Game {
+//!     fn start() -> Self {
+//!         Self { scenes: SceneStack::new(Progress::default(), Box::new(Title::new())) }
+//!     }
+//!
+//!     fn update(&mut self, inputs: &Inputs) {
+//!         self.scenes.update(inputs);
+//!     }
+//!
+//!     fn render(&self, framebuffer: &Framebuffer) {
+//!         self.scenes.render(framebuffer);
+//!     }
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::framebuffer::Framebuffer;
+use crate::inputs::Inputs;
+
+/// State of the game with its own update and render, sharing the `context` with the other scenes
+pub trait Scene<C = ()> {
+    /// Called every frame while the scene is on top of the stack
+    fn update(&mut self, context: &mut C, inputs: &Inputs) -> Transition<C>;
+
+    /// Called after update while the scene is visible
+    fn render(&self, context: &C, framebuffer: &Framebuffer);
+
+    /// Whether the scenes below stay visible, e.g. for a pause menu drawn over the gameplay
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Called when the scene is added to the stack
+    fn on_enter(&mut self, _context: &mut C) {}
+
+    /// Called when the scene is removed from the stack
+    fn on_exit(&mut self, _context: &mut C) {}
+
+    /// Called when another scene is pushed on top of this one
+    fn on_pause(&mut self, _context: &mut C) {}
+
+    /// Called when this scene is on top of the stack again
+    fn on_resume(&mut self, _context: &mut C) {}
+}
+
+/// Change of the stack requested by the scene on top
+pub enum Transition<C = ()> {
+    None,
+    /// Adds the scene on top, pausing the current one
+    Push(Box<dyn Scene<C>>),
+    /// Removes the current scene, resuming the one below
+    Pop,
+    /// Replaces the current scene
+    Replace(Box<dyn Scene<C>>),
+    /// Removes every scene and starts over with the scene, e.g. back to the title screen
+    Reset(Box<dyn Scene<C>>),
+}
+
+pub struct SceneStack<C = ()> {
+    context: C,
+    scenes: Vec<Box<dyn Scene<C>>>,
+}
+
+impl<C> SceneStack<C> {
+    /// * `context` - Data shared by every scene, e.g. the progress of the player
+    /// * `scene` - The first scene
+    pub fn new(context: C, scene: Box<dyn Scene<C>>) -> Self {
+        let mut stack = Self { context, scenes: Vec::new() };
+        stack.push(scene);
+        stack
+    }
+
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    /// Number of scenes on the stack
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// Updates the scene on top and applies the transition it requested
+    pub fn update(&mut self, inputs: &Inputs) {
+        let Some(scene) = self.scenes.last_mut() else { return };
+        let transition = scene.update(&mut self.context, inputs);
+        self.apply(transition);
+    }
+
+    /// Renders the scene on top, over the scenes below it as long as it's an overlay
+    pub fn render(&self, framebuffer: &Framebuffer) {
+        let bottom = self.scenes.iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        for scene in &self.scenes[bottom..] {
+            scene.render(&self.context, framebuffer);
+        }
+    }
+
+    pub fn apply(&mut self, transition: Transition<C>) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => self.push(scene),
+            Transition::Pop => self.pop(),
+            Transition::Replace(scene) => self.replace(scene),
+            Transition::Reset(scene) => self.reset(scene),
+        }
+    }
+
+    pub fn push(&mut self, mut scene: Box<dyn Scene<C>>) {
+        if let Some(current) = self.scenes.last_mut() {
+            current.on_pause(&mut self.context);
+        }
+        scene.on_enter(&mut self.context);
+        self.scenes.push(scene);
+    }
+
+    /// Removes the scene on top, the stack can become empty
+    pub fn pop(&mut self) {
+        if let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit(&mut self.context);
+        }
+        if let Some(current) = self.scenes.last_mut() {
+            current.on_resume(&mut self.context);
+        }
+    }
+
+    pub fn replace(&mut self, mut scene: Box<dyn Scene<C>>) {
+        if let Some(mut current) = self.scenes.pop() {
+            current.on_exit(&mut self.context);
+        }
+        scene.on_enter(&mut self.context);
+        self.scenes.push(scene);
+    }
+
+    /// Removes every scene, from the top, and adds the `scene`
+    pub fn reset(&mut self, mut scene: Box<dyn Scene<C>>) {
+        while let Some(mut current) = self.scenes.pop() {
+            current.on_exit(&mut self.context);
+        }
+        scene.on_enter(&mut self.context);
+        self.scenes.push(scene);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+
+    /// Scene names with the calls they received
+    type Log = RefCell<Vec<(&'static str, &'static str)>>;
+
+    struct Named {
+        name: &'static str,
+        overlay: bool,
+        /// Transition returned by the next update
+        transition: Option<Transition<Log>>,
+    }
+
+    impl Named {
+        fn new(name: &'static str) -> Box<Self> {
+            Box::new(Self { name, overlay: false, transition: None })
+        }
+
+        fn overlay(name: &'static str) -> Box<Self> {
+            Box::new(Self { name, overlay: true, transition: None })
+        }
+
+        fn then(mut self: Box<Self>, transition: Transition<Log>) -> Box<Self> {
+            self.transition = Some(transition);
+            self
+        }
+    }
+
+    impl Scene<Log> for Named {
+        fn update(&mut self, context: &mut Log, _inputs: &Inputs) -> Transition<Log> {
+            context.get_mut().push((self.name, "update"));
+            self.transition.take().unwrap_or(Transition::None)
+        }
+
+        fn render(&self, context: &Log, _framebuffer: &Framebuffer) {
+            context.borrow_mut().push((self.name, "render"));
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+
+        fn on_enter(&mut self, context: &mut Log) {
+            context.get_mut().push((self.name, "enter"));
+        }
+
+        fn on_exit(&mut self, context: &mut Log) {
+            context.get_mut().push((self.name, "exit"));
+        }
+
+        fn on_pause(&mut self, context: &mut Log) {
+            context.get_mut().push((self.name, "pause"));
+        }
+
+        fn on_resume(&mut self, context: &mut Log) {
+            context.get_mut().push((self.name, "resume"));
+        }
+    }
+
+    fn take(stack: &mut SceneStack<Log>) -> Vec<(&'static str, &'static str)> {
+        core::mem::take(stack.context_mut().get_mut())
+    }
+
+    #[test]
+    fn only_the_top_scene_is_updated_and_transitions_call_the_hooks() {
+        let inputs = unsafe { Inputs::new() };
+        let mut stack = SceneStack::new(Log::default(), Named::new("game").then(Transition::Push(Named::overlay("pause"))));
+        assert_eq!(take(&mut stack), [("game", "enter")]);
+
+        stack.update(&inputs);
+        assert_eq!(take(&mut stack), [("game", "update"), ("game", "pause"), ("pause", "enter")]);
+        assert_eq!(stack.len(), 2);
+
+        stack.apply(Transition::Pop);
+        stack.update(&inputs);
+        assert_eq!(take(&mut stack), [("pause", "exit"), ("game", "resume"), ("game", "update")]);
+
+        stack.apply(Transition::Replace(Named::new("title")));
+        assert_eq!(take(&mut stack), [("game", "exit"), ("title", "enter")]);
+    }
+
+    #[test]
+    fn reset_exits_every_scene_from_the_top() {
+        let mut stack = SceneStack::new(Log::default(), Named::new("game"));
+        stack.push(Named::new("inventory"));
+        take(&mut stack);
+        stack.apply(Transition::Reset(Named::new("title")));
+        assert_eq!(take(&mut stack), [("inventory", "exit"), ("game", "exit"), ("title", "enter")]);
+        assert_eq!(stack.len(), 1);
+
+        stack.pop();
+        assert!(stack.is_empty());
+        stack.update(&unsafe { Inputs::new() });
+    }
+
+    #[test]
+    fn overlays_are_rendered_over_the_scenes_below() {
+        let framebuffer = unsafe { Framebuffer::new() };
+        let mut stack = SceneStack::new(Log::default(), Named::new("title"));
+        stack.push(Named::new("game"));
+        stack.push(Named::overlay("pause"));
+        stack.push(Named::overlay("confirm"));
+        take(&mut stack);
+        stack.render(&framebuffer);
+        assert_eq!(take(&mut stack), [("game", "render"), ("pause", "render"), ("confirm", "render")]);
+
+        stack.push(Named::new("options"));
+        take(&mut stack);
+        stack.render(&framebuffer);
+        assert_eq!(take(&mut stack), [("options", "render")]);
+    }
+}
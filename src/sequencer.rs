@@ -0,0 +1,301 @@
+use crate::audio::{ADSRDuration, Channel, ChannelSet, DutyCycle, Flags, Frequency, Pan, ToneDuration, ToneOutput, Volume};
+use crate::instrument::{Instrument, InstrumentVoice};
+use crate::note::Note;
+
+/// The maximum number of tracks in a song, one for every channel
+pub const MAX_TRACKS: usize = 4;
+
+/// Single note of a track
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SongNote {
+    /// Frames to wait since the start of the previous note, or since the start of the song for the first one
+    pub delay: u16,
+    pub note: Note,
+    /// Duration of the note in frames
+    pub duration: ToneDuration,
+    /// Volume between 0 and 100
+    pub volume: u8,
+}
+
+impl SongNote {
+    /// * `delay` - Frames to wait since the start of the previous note
+    /// * `midi` - MIDI note number between 0 and 127
+    /// * `duration` - Duration of the note in frames
+    /// * `volume` - Volume between 0 and 100
+    pub const fn new(delay: u16, midi: u8, duration: ToneDuration, volume: u8) -> Self {
+        assert!(volume <= 100, "volume must be between 0 and 100");
+        Self { delay, note: Note::from_midi(midi), duration, volume }
+    }
+}
+
+/// Sequence of notes played one after another on a single channel
+#[derive(Copy, Clone, Debug)]
+pub struct Track {
+    pub channel: Channel,
+    pub duty_cycle: DutyCycle,
+    pub pan: Pan,
+    /// Instrument realising the notes frame by frame, otherwise notes are plain tones
+    pub instrument: Option<&'static Instrument>,
+    pub notes: &'static [SongNote],
+}
+
+/// Song data, usually generated at build-time by the `import` module
+#[derive(Copy, Clone, Debug)]
+pub struct Song {
+    /// Total length of the song in frames
+    pub length: u32,
+    /// Whether the song starts again after reaching the end
+    pub looping: bool,
+    pub tracks: &'static [Track],
+}
+
+#[derive(Copy, Clone, Default)]
+struct TrackCursor {
+    index: usize,
+    next_frame: u32,
+    /// Frame when the last started note ends
+    sounding_until: u32,
+}
+
+/// Plays a `Song` through `Audio`, the `update` must be called every frame
+pub struct SongPlayer {
+    song: &'static Song,
+    frame: u32,
+    playing: bool,
+    cursors: [TrackCursor; MAX_TRACKS],
+    voices: [Option<InstrumentVoice>; MAX_TRACKS],
+    ducked: ChannelSet,
+    was_ducked: ChannelSet,
+}
+
+impl SongPlayer {
+    pub fn new(song: &'static Song) -> Self {
+        assert!(song.tracks.len() <= MAX_TRACKS, "song can have at most 4 tracks");
+        let mut player = Self {
+            song,
+            frame: 0,
+            playing: false,
+            cursors: [TrackCursor::default(); MAX_TRACKS],
+            voices: [None; MAX_TRACKS],
+            ducked: ChannelSet::EMPTY,
+            was_ducked: ChannelSet::EMPTY,
+        };
+        for (voice, track) in player.voices.iter_mut().zip(song.tracks) {
+            *voice = track.instrument.map(|instrument| {
+                let mut voice = InstrumentVoice::new(instrument, track.channel);
+                voice.set_duty_cycle(track.duty_cycle);
+                voice.set_pan(track.pan);
+                voice
+            });
+        }
+        player.rewind();
+        player
+    }
+
+    pub fn song(&self) -> &'static Song {
+        self.song
+    }
+
+    /// Current position in the song in frames
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Starts or resumes the playback from the current position
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Pauses the playback at the current position,
+    /// the notes already started will sound until their end
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Stops the playback and moves back to the start of the song
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.rewind();
+    }
+
+    /// Moves back to the start of the song
+    pub fn rewind(&mut self) {
+        self.frame = 0;
+        for (cursor, track) in self.cursors.iter_mut().zip(self.song.tracks) {
+            cursor.index = 0;
+            cursor.next_frame = track.notes.first().map_or(0, |note| note.delay as u32);
+            cursor.sounding_until = 0;
+        }
+        for voice in self.voices.iter_mut().flatten() {
+            voice.stop();
+        }
+    }
+
+    /// Silences the tracks on the `channels`, e.g. while sound effects play on them.
+    /// The song keeps its position and a note still sounding when the channel
+    /// is no longer ducked is resumed for its remaining duration.
+    pub fn set_ducked_channels(&mut self, channels: ChannelSet) {
+        self.ducked = channels;
+    }
+
+    pub fn ducked_channels(&self) -> ChannelSet {
+        self.ducked
+    }
+
+    /// Advances the song by one frame and starts notes scheduled on it
+    pub fn update(&mut self, audio: &impl ToneOutput) {
+        if !self.playing {
+            return;
+        }
+        let tracks = self.cursors.iter_mut().zip(self.voices.iter_mut()).zip(self.song.tracks);
+        for ((cursor, voice), track) in tracks {
+            let is_ducked = self.ducked.contains(track.channel);
+            let was_ducked = self.was_ducked.contains(track.channel);
+            if let Some(voice) = voice {
+                Self::update_voice(audio, voice, cursor, track, self.frame, is_ducked);
+                continue;
+            }
+            if !is_ducked && was_ducked && cursor.sounding_until > self.frame {
+                if let Some(note) = cursor.index.checked_sub(1).and_then(|index| track.notes.get(index)) {
+                    let remaining = (cursor.sounding_until - self.frame).min(ToneDuration::MAX as u32);
+                    Self::play_note(audio, track, &SongNote { duration: remaining as ToneDuration, ..*note });
+                }
+            }
+            while let Some(note) = Self::next_note(cursor, track, self.frame) {
+                if !is_ducked {
+                    Self::play_note(audio, track, note);
+                }
+            }
+        }
+        self.was_ducked = self.ducked;
+        self.frame += 1;
+        if self.frame >= self.song.length {
+            if self.song.looping {
+                self.rewind();
+            } else {
+                self.stop();
+            }
+        }
+    }
+
+    /// Returns the note starting on the `frame` and moves the cursor past it
+    fn next_note(cursor: &mut TrackCursor, track: &Track, frame: u32) -> Option<&'static SongNote> {
+        let note = track.notes.get(cursor.index).filter(|_| cursor.next_frame <= frame)?;
+        cursor.sounding_until = frame + note.duration as u32;
+        cursor.index += 1;
+        if let Some(next_note) = track.notes.get(cursor.index) {
+            cursor.next_frame += next_note.delay as u32;
+        }
+        Some(note)
+    }
+
+    fn update_voice(audio: &impl ToneOutput,
+                    voice: &mut InstrumentVoice,
+                    cursor: &mut TrackCursor,
+                    track: &Track,
+                    frame: u32,
+                    is_ducked: bool) {
+        if cursor.sounding_until == frame {
+            voice.note_off();
+        }
+        while let Some(note) = Self::next_note(cursor, track, frame) {
+            if note.volume > 0 && note.duration > 0 {
+                voice.note_on(note.note, note.volume);
+            }
+        }
+        if !is_ducked {
+            voice.update(audio);
+        }
+    }
+
+    fn play_note(audio: &impl ToneOutput, track: &Track, note: &SongNote) {
+        if note.volume == 0 || note.duration == 0 {
+            return;
+        }
+        audio.tone(
+            Frequency::note(note.note),
+            ADSRDuration::constant(note.duration),
+            Volume::constant(note.volume),
+            Flags::new(track.channel, track.duty_cycle, track.pan),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+    use crate::audio::Tones;
+
+    /// Tones played in each of the `frames`
+    fn play(player: &mut SongPlayer, frames: usize) -> Vec<Vec<(u32, u32, u32, u32)>> {
+        let tones = Tones::default();
+        (0..frames).map(|_| {
+            player.update(&tones);
+            tones.take()
+        }).collect()
+    }
+
+    fn tone(channel: Channel, midi: u8, duration: u8, volume: u32) -> Vec<(u32, u32, u32, u32)> {
+        let flags = Flags::new(channel, DutyCycle::OneHalf, Pan::Center);
+        alloc::vec![(Frequency::note(Note::from_midi(midi)).into(), ADSRDuration::constant(duration).into(), volume, flags.into())]
+    }
+
+    const fn track(channel: Channel, instrument: Option<&'static Instrument>, notes: &'static [SongNote]) -> Track {
+        Track { channel, duty_cycle: DutyCycle::OneHalf, pan: Pan::Center, instrument, notes }
+    }
+
+    static MELODY: [SongNote; 3] = [SongNote::new(1, 69, 2, 50), SongNote::new(2, 0, 1, 0), SongNote::new(1, 72, 1, 80)];
+    static SONG: Song = Song { length: 5, looping: false, tracks: &[track(Channel::Pulse1, None, &MELODY)] };
+
+    #[test]
+    fn notes_start_after_their_delays_and_rests_are_silent() {
+        let mut player = SongPlayer::new(&SONG);
+        assert!(play(&mut player, 1).concat().is_empty());
+        player.play();
+        assert_eq!(play(&mut player, 5), [Vec::new(), tone(Channel::Pulse1, 69, 2, 50), Vec::new(), Vec::new(), tone(Channel::Pulse1, 72, 1, 80)]);
+        assert!(!player.is_playing());
+        assert_eq!(player.frame(), 0);
+    }
+
+    #[test]
+    fn looping_song_starts_again() {
+        static LOOP: Song = Song { looping: true, ..SONG };
+        let mut player = SongPlayer::new(&LOOP);
+        player.play();
+        let frames = play(&mut player, 7);
+        assert_eq!(frames[6], tone(Channel::Pulse1, 69, 2, 50));
+        assert!(player.is_playing());
+
+        player.pause();
+        assert!(play(&mut player, 1).concat().is_empty());
+        assert_eq!(player.frame(), 2);
+    }
+
+    #[test]
+    fn ducked_note_resumes_for_its_remaining_duration() {
+        static LONG: [SongNote; 1] = [SongNote::new(0, 60, 10, 40)];
+        static SONG: Song = Song { length: 20, looping: false, tracks: &[track(Channel::Triangle, None, &LONG)] };
+        let mut player = SongPlayer::new(&SONG);
+        player.play();
+        player.set_ducked_channels(ChannelSet::of(Channel::Triangle));
+        assert!(play(&mut player, 4).concat().is_empty());
+        player.set_ducked_channels(ChannelSet::PULSE);
+        assert_eq!(play(&mut player, 2), [tone(Channel::Triangle, 60, 6, 40), Vec::new()]);
+    }
+
+    #[test]
+    fn instrument_tracks_play_every_frame_until_the_note_ends() {
+        static NOTES: [SongNote; 1] = [SongNote::new(0, 69, 2, 100)];
+        static SONG: Song = Song { length: 4, looping: false, tracks: &[track(Channel::Pulse2, Some(&Instrument::PLAIN), &NOTES)] };
+        let mut player = SongPlayer::new(&SONG);
+        player.play();
+        let frames = play(&mut player, 4);
+        assert_eq!(frames.iter().map(Vec::len).collect::<Vec<_>>(), [1, 1, 0, 0]);
+        assert_eq!(frames[0][0].0, 440);
+    }
+}
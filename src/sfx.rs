@@ -0,0 +1,283 @@
+use crate::audio::{ADSRDuration, Channel, ChannelSet, DutyCycle, Flags, Frequency, Pan, ToneDuration, ToneOutput, Volume};
+use crate::note::Note;
+
+/// Single tone of a sound effect, the steps of an effect are played one after another
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SoundStep {
+    pub start_hertz: u16,
+    pub end_hertz: u16,
+    /// Duration of the step in frames
+    pub duration: ToneDuration,
+    /// Volume between 0 and 100, silent steps work as pauses
+    pub volume: u8,
+}
+
+impl SoundStep {
+    /// * `hertz` - Wave frequency in hertz
+    /// * `duration` - Duration of the step in frames
+    /// * `volume` - Volume between 0 and 100
+    pub const fn tone(hertz: u16, duration: ToneDuration, volume: u8) -> Self {
+        Self::slide(hertz, hertz, duration, volume)
+    }
+
+    /// Tone sliding linearly between two frequencies
+    pub const fn slide(start_hertz: u16, end_hertz: u16, duration: ToneDuration, volume: u8) -> Self {
+        assert!(volume <= 100, "volume must be between 0 and 100");
+        Self { start_hertz, end_hertz, duration, volume }
+    }
+
+    pub const fn note(note: Note, duration: ToneDuration, volume: u8) -> Self {
+        Self::tone(note.hertz(), duration, volume)
+    }
+
+    /// Silence holding the channel for the `duration`
+    pub const fn pause(duration: ToneDuration) -> Self {
+        Self::tone(0, duration, 0)
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SoundEffect {
+    pub name: &'static str,
+    /// Effects with higher priority take over channels from the ones with equal or lower priority
+    pub priority: u8,
+    /// Channels the effect can be played on, the first free one is chosen
+    pub channels: ChannelSet,
+    pub duty_cycle: DutyCycle,
+    pub pan: Pan,
+    pub steps: &'static [SoundStep],
+}
+
+/// Identifier of a sound effect registered in `SoundEffects`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SoundEffectId(u8);
+
+#[derive(Copy, Clone)]
+struct Voice {
+    effect: SoundEffectId,
+    priority: u8,
+    step: usize,
+    /// Frames left of the current step, 0 when the step hasn't started yet
+    frames_left: u16,
+}
+
+/// Bank of sound effects, playing them on free channels.
+/// The `update` must be called every frame, after the music so the effects take over their channels:
+///
+/// ```ignore
+/// self.music.set_ducked_channels(self.effects.busy_channels());
+/// self.music.update(&audio);
+/// self.effects.update(&audio);
+/// ```
+pub struct SoundEffects<const N: usize> {
+    effects: [Option<&'static SoundEffect>; N],
+    voices: [Option<Voice>; 4],
+}
+
+impl<const N: usize> SoundEffects<N> {
+    pub const fn new() -> Self {
+        Self {
+            effects: [None; N],
+            voices: [None; 4],
+        }
+    }
+
+    /// Adds the `effect` to the bank, panics when the bank is full
+    pub fn register(&mut self, effect: &'static SoundEffect) -> SoundEffectId {
+        let index = self.effects.iter().position(Option::is_none)
+            .expect("sound effects bank is full");
+        self.effects[index] = Some(effect);
+        SoundEffectId(index as u8)
+    }
+
+    pub fn find(&self, name: &str) -> Option<SoundEffectId> {
+        self.effects.iter()
+            .position(|effect| effect.is_some_and(|effect| effect.name == name))
+            .map(|index| SoundEffectId(index as u8))
+    }
+
+    pub fn get(&self, id: SoundEffectId) -> Option<&'static SoundEffect> {
+        self.effects.get(id.0 as usize).copied().flatten()
+    }
+
+    /// Starts the effect on a free compatible channel, or takes one over from an effect
+    /// with equal or lower priority. Returns the chosen channel, or `None` when all are busy.
+    pub fn play(&mut self, id: SoundEffectId) -> Option<Channel> {
+        let effect = self.get(id)?;
+        let channel = effect.channels.iter()
+            .find(|channel| self.voices[*channel as usize].is_none())
+            .or_else(|| {
+                effect.channels.iter()
+                    .filter(|channel| self.voices[*channel as usize]
+                        .is_some_and(|voice| voice.priority <= effect.priority))
+                    .min_by_key(|channel| self.voices[*channel as usize].map(|voice| voice.priority))
+            })?;
+        self.voices[channel as usize] = Some(Voice {
+            effect: id,
+            priority: effect.priority,
+            step: 0,
+            frames_left: 0,
+        });
+        Some(channel)
+    }
+
+    /// Plays the effect registered with the `name`
+    pub fn play_named(&mut self, name: &str) -> Option<Channel> {
+        self.play(self.find(name)?)
+    }
+
+    /// Stops every playing instance of the effect, the current step finishes on its own
+    pub fn stop(&mut self, id: SoundEffectId) {
+        for voice in self.voices.iter_mut() {
+            if voice.is_some_and(|voice| voice.effect == id) {
+                *voice = None;
+            }
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices = [None; 4];
+    }
+
+    pub fn is_busy(&self, channel: Channel) -> bool {
+        self.voices[channel as usize].is_some()
+    }
+
+    pub fn is_playing(&self, id: SoundEffectId) -> bool {
+        self.voices.iter().any(|voice| voice.is_some_and(|voice| voice.effect == id))
+    }
+
+    /// Channels taken by playing effects
+    pub fn busy_channels(&self) -> ChannelSet {
+        Channel::ALL.into_iter()
+            .filter(|channel| self.is_busy(*channel))
+            .fold(ChannelSet::EMPTY, ChannelSet::with)
+    }
+
+    /// Starts the steps scheduled for this frame and frees the channels of finished effects
+    pub fn update(&mut self, audio: &impl ToneOutput) {
+        for channel in Channel::ALL {
+            let Some(mut voice) = self.voices[channel as usize] else { continue };
+            let effect = self.effects[voice.effect.0 as usize].unwrap();
+            if voice.frames_left == 0 {
+                match effect.steps.get(voice.step) {
+                    Some(step) => {
+                        Self::play_step(audio, channel, effect, step);
+                        voice.frames_left = step.duration.max(1) as u16;
+                    }
+                    None => {
+                        self.voices[channel as usize] = None;
+                        continue;
+                    }
+                }
+            }
+            voice.frames_left -= 1;
+            if voice.frames_left == 0 {
+                voice.step += 1;
+                if voice.step >= effect.steps.len() {
+                    self.voices[channel as usize] = None;
+                    continue;
+                }
+            }
+            self.voices[channel as usize] = Some(voice);
+        }
+    }
+
+    fn play_step(audio: &impl ToneOutput, channel: Channel, effect: &SoundEffect, step: &SoundStep) {
+        let frequency = if step.start_hertz == step.end_hertz {
+            Frequency::constant(step.start_hertz)
+        } else {
+            Frequency::linear(step.start_hertz, step.end_hertz)
+        };
+        audio.tone(
+            frequency,
+            ADSRDuration::constant(step.duration),
+            Volume::constant(step.volume),
+            Flags::new(channel, effect.duty_cycle, effect.pan),
+        );
+    }
+}
+
+impl<const N: usize> Default for SoundEffects<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::Tones;
+
+    const STEPS: [SoundStep; 3] = [SoundStep::tone(440, 2, 50), SoundStep::pause(1), SoundStep::slide(200, 300, 1, 80)];
+    const JUMP: SoundEffect = SoundEffect {
+        name: "jump",
+        priority: 1,
+        channels: ChannelSet::PULSE,
+        duty_cycle: DutyCycle::OneQuarter,
+        pan: Pan::Center,
+        steps: &STEPS,
+    };
+    const HIT: SoundEffect = SoundEffect { name: "hit", priority: 2, channels: ChannelSet::of(Channel::Pulse2), ..JUMP };
+
+    #[test]
+    fn effects_are_found_by_name() {
+        let mut effects = SoundEffects::<2>::new();
+        let jump = effects.register(&JUMP);
+        let hit = effects.register(&HIT);
+        assert_eq!(effects.find("hit"), Some(hit));
+        assert_eq!(effects.find("run"), None);
+        assert_eq!(effects.get(jump), Some(&JUMP));
+    }
+
+    #[test]
+    #[should_panic(expected = "sound effects bank is full")]
+    fn full_bank_panics() {
+        let mut effects = SoundEffects::<1>::new();
+        effects.register(&JUMP);
+        effects.register(&HIT);
+    }
+
+    #[test]
+    fn steps_play_one_after_another() {
+        let mut effects = SoundEffects::<1>::new();
+        let jump = effects.register(&JUMP);
+        let tones = Tones::default();
+        assert_eq!(effects.play(jump), Some(Channel::Pulse1));
+        let flags: u32 = Flags::new(Channel::Pulse1, DutyCycle::OneQuarter, Pan::Center).into();
+        effects.update(&tones);
+        assert_eq!(tones.take(), [(440, 2, 50, flags)]);
+        effects.update(&tones);
+        assert!(tones.take().is_empty());
+        effects.update(&tones);
+        assert_eq!(tones.take(), [(0, 1, 0, flags)]);
+        effects.update(&tones);
+        assert_eq!(tones.take(), [(200 | 300 << 16, 1, 80, flags)]);
+        assert!(!effects.is_playing(jump));
+        assert_eq!(effects.busy_channels(), ChannelSet::EMPTY);
+    }
+
+    #[test]
+    fn higher_priority_takes_over_the_channel() {
+        let mut effects = SoundEffects::<2>::new();
+        let jump = effects.register(&JUMP);
+        let hit = effects.register(&HIT);
+        assert_eq!(effects.play(jump), Some(Channel::Pulse1));
+        assert_eq!(effects.play(jump), Some(Channel::Pulse2));
+        assert_eq!(effects.play(hit), Some(Channel::Pulse2));
+        assert_eq!(effects.play_named("jump"), Some(Channel::Pulse1));
+        assert_eq!(effects.busy_channels(), ChannelSet::PULSE);
+        assert!(effects.is_playing(hit));
+
+        let mut effects = SoundEffects::<2>::new();
+        let jump = effects.register(&JUMP);
+        let hit = effects.register(&HIT);
+        effects.play(hit);
+        effects.play(jump);
+        assert_eq!(effects.play(hit), Some(Channel::Pulse2));
+        effects.stop(hit);
+        assert!(!effects.is_busy(Channel::Pulse2) && effects.is_busy(Channel::Pulse1));
+        effects.stop_all();
+        assert!(!effects.is_playing(jump));
+    }
+}
@@ -0,0 +1,134 @@
+//! Text formatted into a fixed-size buffer on the stack, for tracing and drawing
+//! without any allocation.
+//!
+//! ```ignore
+//! let mut score = StackString::<16>::new();
+//! write!(score, "{:05}", self.score).ok();
+//! framebuffer.text(&score, 0, 0);
+//! ```
+
+use core::fmt;
+use core::ops::Deref;
+
+/// String of at most `N` bytes. Text which doesn't fit is cut off at the last whole character.
+#[derive(Copy, Clone)]
+pub struct StackString<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> StackString<N> {
+    pub const fn new() -> Self {
+        Self { bytes: [0; N], len: 0 }
+    }
+
+    /// Formats the `args`, e.g. `StackString::<32>::format(format_args!("x {}", x))`
+    pub fn format(args: fmt::Arguments) -> Self {
+        let mut string = Self::new();
+        // An error only means the text was cut off
+        let _ = fmt::Write::write_fmt(&mut string, args);
+        string
+    }
+
+    pub fn as_str(&self) -> &str {
+        // Only whole strings or prefixes ending on a character boundary are copied in
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len]) }
+    }
+
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Appends as much of the `text` as fits, returns whether all of it did
+    pub fn push_str(&mut self, text: &str) -> bool {
+        let mut end = text.len().min(N - self.len);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.bytes[self.len..self.len + end].copy_from_slice(&text.as_bytes()[..end]);
+        self.len += end;
+        end == text.len()
+    }
+}
+
+impl<const N: usize> Default for StackString<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for StackString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for StackString<N> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        if self.push_str(text) { Ok(()) } else { Err(fmt::Error) }
+    }
+}
+
+impl<const N: usize> fmt::Display for StackString<N> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for StackString<N> {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), formatter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn formats_without_allocating() {
+        let mut score = StackString::<16>::new();
+        write!(score, "{:05}", 42).unwrap();
+        assert_eq!(&*score, "00042");
+        assert_eq!((score.len(), score.capacity()), (5, 16));
+        score.clear();
+        assert!(score.is_empty());
+        assert_eq!(StackString::<8>::format(format_args!("x {}", -3)).as_str(), "x -3");
+    }
+
+    #[test]
+    fn cuts_off_what_does_not_fit() {
+        let mut text = StackString::<4>::new();
+        assert!(text.push_str("ab"));
+        assert!(!text.push_str("cde"));
+        assert_eq!(text.as_str(), "abcd");
+        assert!(write!(text, "f").is_err());
+        assert_eq!(StackString::<6>::format(format_args!("{}", 1234567)).as_str(), "123456");
+    }
+
+    #[test]
+    fn cuts_off_at_a_character_boundary() {
+        let mut text = StackString::<5>::new();
+        assert!(!text.push_str("aé€"));
+        assert_eq!(text.as_str(), "aé");
+        assert_eq!(text.len(), 3);
+        assert!(!text.push_str("€"));
+        assert!(text.push_str("b"));
+        assert_eq!(alloc::format!("{text} {text:?}"), "aéb \"aéb\"");
+    }
+}
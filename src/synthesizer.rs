@@ -0,0 +1,419 @@
+//! Offline emulation of the WASM-4 sound chip, following the reference runtime.
+//!
+//! Meant to run on the host, e.g. in tests or tools previewing the music:
+//!
+//! ```ignore
+//! let synthesizer = Synthesizer::new();
+//! let mut player = SongPlayer::new(&THEME);
+//! player.play();
+//! let samples = synthesizer.render(THEME.length, |output| player.update(output));
+//! std::fs::write("theme.wav", wav(&samples)).unwrap();
+//! ```
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use libm::fabsf;
+
+use crate::audio::{ADSRDuration, Flags, Frequency, ToneOutput, Volume};
+
+/// Samples per second of every channel
+pub const SAMPLE_RATE: u32 = 44100;
+/// Samples rendered for every frame, at 60 frames per second
+pub const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+
+/// Peak amplitude of the pulse and noise channels, about 15% of the 16-bit range
+const MAX_VOLUME: i32 = 0x1333;
+/// Peak amplitude of the triangle channel, about 25% of the 16-bit range
+const MAX_VOLUME_TRIANGLE: i32 = 0x2000;
+
+#[derive(Copy, Clone, Default)]
+struct ChannelState {
+    start_frequency: u16,
+    end_frequency: u16,
+    start_time: u64,
+    attack_time: u64,
+    decay_time: u64,
+    sustain_time: u64,
+    release_time: u64,
+    end_tick: u64,
+    sustain_volume: i32,
+    peak_volume: i32,
+    phase: f32,
+    pan: u8,
+    duty_cycle: f32,
+    noise_seed: u16,
+    noise_last_random: i32,
+}
+
+struct Apu {
+    channels: [ChannelState; 4],
+    /// Samples rendered so far
+    time: u64,
+    /// Frames rendered so far
+    ticks: u64,
+}
+
+/// Renders the tones into 16-bit stereo samples, interleaved left and right
+pub struct Synthesizer {
+    apu: RefCell<Apu>,
+}
+
+impl Synthesizer {
+    pub fn new() -> Self {
+        let channel = ChannelState { noise_seed: 0x0001, ..ChannelState::default() };
+        Self {
+            apu: RefCell::new(Apu { channels: [channel; 4], time: 0, ticks: 0 }),
+        }
+    }
+
+    /// Renders a single frame and appends its samples
+    pub fn render_frame(&self, samples: &mut Vec<i16>) {
+        let mut apu = self.apu.borrow_mut();
+        samples.reserve(SAMPLES_PER_FRAME * 2);
+        for _ in 0..SAMPLES_PER_FRAME {
+            let (left, right) = apu.sample();
+            samples.push(left);
+            samples.push(right);
+            apu.time += 1;
+        }
+        apu.ticks += 1;
+    }
+
+    /// Renders the `frames`, calling `update` at the start of every frame like the runtime does
+    pub fn render(&self, frames: u32, mut update: impl FnMut(&Self)) -> Vec<i16> {
+        let mut samples = Vec::with_capacity(frames as usize * SAMPLES_PER_FRAME * 2);
+        for _ in 0..frames {
+            update(self);
+            self.render_frame(&mut samples);
+        }
+        samples
+    }
+}
+
+impl Default for Synthesizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ToneOutput for Synthesizer {
+    fn tone(&self, frequency: Frequency, duration: ADSRDuration, volume: Volume, flags: Flags) {
+        let (frequency, duration, volume, flags): (u32, u32, u32, u32) =
+            (frequency.into(), duration.into(), volume.into(), flags.into());
+        self.apu.borrow_mut().tone(frequency, duration, volume, flags);
+    }
+}
+
+impl Apu {
+    fn tone(&mut self, frequency: u32, duration: u32, volume: u32, flags: u32) {
+        let frames_to_samples = |frames: u32| (SAMPLE_RATE * (frames & 0xff) / 60) as u64;
+        let sustain = duration & 0xff;
+        let release = (duration >> 8) & 0xff;
+        let decay = (duration >> 16) & 0xff;
+        let attack = (duration >> 24) & 0xff;
+        let sustain_volume = (volume & 0xff).min(100) as i32;
+        let peak_volume = ((volume >> 8) & 0xff).min(100) as i32;
+        let index = (flags & 0b11) as usize;
+        let mode = (flags >> 2) & 0b11;
+        let pan = ((flags >> 4) & 0b11) as u8;
+
+        let (time, ticks) = (self.time, self.ticks);
+        let channel = &mut self.channels[index];
+        // Restart the phase only if the channel isn't already playing, to avoid clicks
+        if time > channel.release_time && ticks != channel.end_tick {
+            channel.phase = if index == 2 { 0.25 } else { 0.0 };
+        }
+        channel.start_frequency = (frequency & 0xffff) as u16;
+        channel.end_frequency = (frequency >> 16) as u16;
+        channel.start_time = time;
+        channel.attack_time = channel.start_time + frames_to_samples(attack);
+        channel.decay_time = channel.attack_time + frames_to_samples(decay);
+        channel.sustain_time = channel.decay_time + frames_to_samples(sustain);
+        channel.release_time = channel.sustain_time + frames_to_samples(release);
+        channel.end_tick = ticks + (attack + decay + sustain + release) as u64;
+
+        let max_volume = if index == 2 { MAX_VOLUME_TRIANGLE } else { MAX_VOLUME };
+        channel.sustain_volume = max_volume * sustain_volume / 100;
+        channel.peak_volume = if peak_volume > 0 { max_volume * peak_volume / 100 } else { max_volume };
+        channel.pan = pan;
+
+        match index {
+            0 | 1 => {
+                channel.duty_cycle = match mode {
+                    0 => 0.125,
+                    1 => 0.25,
+                    2 => 0.5,
+                    _ => 0.75,
+                };
+            }
+            2 if release == 0 => {
+                // Short release for the triangle channel, so hard stops don't pop
+                channel.release_time += SAMPLE_RATE as u64 / 1000;
+            }
+            _ => {}
+        }
+    }
+
+    fn sample(&mut self) -> (i16, i16) {
+        let (time, ticks) = (self.time, self.ticks);
+        let mut left = 0i32;
+        let mut right = 0i32;
+        for (index, channel) in self.channels.iter_mut().enumerate() {
+            if time >= channel.release_time && ticks != channel.end_tick {
+                continue;
+            }
+            let frequency = channel.frequency(time) as f32;
+            let volume = channel.volume(time);
+            let sample = match index {
+                3 => {
+                    channel.phase += frequency * frequency / 1_000_000.0;
+                    while channel.phase > 0.0 {
+                        channel.phase -= 1.0;
+                        channel.noise_seed ^= channel.noise_seed >> 7;
+                        channel.noise_seed ^= channel.noise_seed << 9;
+                        channel.noise_seed ^= channel.noise_seed >> 13;
+                        channel.noise_last_random = 2 * (channel.noise_seed & 0x1) as i32 - 1;
+                    }
+                    volume * channel.noise_last_random
+                }
+                _ => {
+                    let phase_increment = frequency / SAMPLE_RATE as f32;
+                    channel.phase += phase_increment;
+                    if channel.phase >= 1.0 {
+                        channel.phase -= 1.0;
+                    }
+                    if index == 2 {
+                        (volume as f32 * (2.0 * fabsf(2.0 * channel.phase - 1.0) - 1.0)) as i32
+                    } else {
+                        channel.pulse_sample(volume, phase_increment)
+                    }
+                }
+            };
+            if channel.pan != 1 {
+                right += sample;
+            }
+            if channel.pan != 2 {
+                left += sample;
+            }
+        }
+        let clamp = |value: i32| value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        (clamp(left), clamp(right))
+    }
+}
+
+impl ChannelState {
+    fn frequency(&self, time: u64) -> u16 {
+        if self.end_frequency > 0 {
+            ramp(self.start_frequency as i32, self.end_frequency as i32, time, self.start_time, self.release_time) as u16
+        } else {
+            self.start_frequency
+        }
+    }
+
+    fn volume(&self, time: u64) -> i32 {
+        if time >= self.sustain_time {
+            ramp(self.sustain_volume, 0, time, self.sustain_time, self.release_time)
+        } else if time >= self.decay_time {
+            self.sustain_volume
+        } else if time >= self.attack_time {
+            ramp(self.peak_volume, self.sustain_volume, time, self.attack_time, self.decay_time)
+        } else {
+            ramp(0, self.peak_volume, time, self.start_time, self.attack_time)
+        }
+    }
+
+    fn pulse_sample(&self, volume: i32, phase_increment: f32) -> i32 {
+        let (duty_phase, duty_phase_increment, multiplier) = if self.phase < self.duty_cycle {
+            (self.phase / self.duty_cycle, phase_increment / self.duty_cycle, volume)
+        } else {
+            let rest = 1.0 - self.duty_cycle;
+            ((self.phase - self.duty_cycle) / rest, phase_increment / rest, -volume)
+        };
+        (multiplier as f32 * polyblep(duty_phase, duty_phase_increment)) as i32
+    }
+}
+
+fn ramp(from: i32, to: i32, time: u64, start_time: u64, end_time: u64) -> i32 {
+    if time >= end_time {
+        return to;
+    }
+    let progress = (time - start_time) as f32 / (end_time - start_time) as f32;
+    from + (progress * (to - from) as f32) as i32
+}
+
+/// Band-limited step, smoothing the edges of the pulse wave
+fn polyblep(phase: f32, phase_increment: f32) -> f32 {
+    if phase < phase_increment {
+        let t = phase / phase_increment;
+        t + t - t * t
+    } else if phase > 1.0 - phase_increment {
+        let t = (phase - (1.0 - phase_increment)) / phase_increment;
+        1.0 - (t + t - t * t)
+    } else {
+        1.0
+    }
+}
+
+/// Encodes the stereo `samples` as a 16-bit PCM WAV file
+pub fn wav(samples: &[i16]) -> Vec<u8> {
+    const CHANNELS: u16 = 2;
+    const BITS_PER_SAMPLE: u16 = 16;
+    let data_length = (samples.len() * 2) as u32;
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    let mut bytes = Vec::with_capacity(44 + data_length as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_length).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&CHANNELS.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * block_align as u32).to_le_bytes());
+    bytes.extend_from_slice(&block_align.to_le_bytes());
+    bytes.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_length.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{Channel, ChannelSet, DutyCycle, Pan};
+    use crate::instrument::{Instrument, Sequence};
+    use crate::note::Note;
+    use crate::sequencer::{Song, SongNote, SongPlayer, Track};
+    use crate::sfx::{SoundEffect, SoundEffects, SoundStep};
+
+    /// FNV-1a hash of the samples, recorded from the reviewed output
+    fn checksum(samples: &[i16]) -> u64 {
+        samples.iter().flat_map(|sample| sample.to_le_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+    }
+
+    fn frame(samples: &[i16], frame: usize) -> &[i16] {
+        &samples[frame * SAMPLES_PER_FRAME * 2..(frame + 1) * SAMPLES_PER_FRAME * 2]
+    }
+
+    fn peak(samples: &[i16]) -> i32 {
+        samples.iter().map(|sample| (*sample as i32).abs()).max().unwrap_or(0)
+    }
+
+    /// Renders the tone started in the first frame
+    fn render_tone(frames: u32, frequency: Frequency, duration: ADSRDuration, volume: Volume, flags: Flags) -> Vec<i16> {
+        let mut tone = Some((frequency, duration, volume, flags));
+        Synthesizer::new().render(frames, |output| {
+            if let Some((frequency, duration, volume, flags)) = tone.take() {
+                output.tone(frequency, duration, volume, flags);
+            }
+        })
+    }
+
+    #[test]
+    fn pulse_tone() {
+        let samples = render_tone(12, Frequency::note(Note::A4), ADSRDuration::constant(10), Volume::constant(50),
+                                  Flags::new(Channel::Pulse1, DutyCycle::OneHalf, Pan::Center));
+        assert_eq!(samples.len(), 12 * SAMPLES_PER_FRAME * 2);
+        assert!(peak(frame(&samples, 5)) <= MAX_VOLUME / 2);
+        assert!(peak(frame(&samples, 5)) >= MAX_VOLUME / 2 - 1);
+        assert_eq!(peak(&samples[10 * SAMPLES_PER_FRAME * 2..]), 0);
+        assert!(samples.chunks(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(checksum(&samples), 5782289426189257201);
+    }
+
+    #[test]
+    fn triangle_panned_left_with_envelope() {
+        let samples = render_tone(30, Frequency::constant(220), ADSRDuration::new(5, 5, 10, 5), Volume::new(100, 50),
+                                  Flags::new(Channel::Triangle, DutyCycle::OneEighth, Pan::Left));
+        assert!(samples.chunks(2).all(|pair| pair[1] == 0));
+        let left: Vec<i16> = samples.chunks(2).map(|pair| pair[0]).collect();
+        let frame = |index: usize| &left[index * SAMPLES_PER_FRAME..(index + 1) * SAMPLES_PER_FRAME];
+        assert!(peak(frame(0)) < peak(frame(4)));
+        assert!(peak(frame(12)) <= MAX_VOLUME_TRIANGLE / 2 + 1);
+        assert!(peak(frame(4)) > MAX_VOLUME_TRIANGLE * 3 / 4);
+        assert_eq!(peak(frame(26)), 0);
+        assert_eq!(checksum(&samples), 1255680826362367018);
+    }
+
+    #[test]
+    fn noise_and_glide() {
+        let noise = render_tone(8, Frequency::constant(800), ADSRDuration::constant(6), Volume::constant(100),
+                                Flags::new(Channel::Noise, DutyCycle::OneEighth, Pan::Center));
+        assert_eq!(checksum(&noise), 2595454200433090245);
+        let glide = render_tone(20, Frequency::glide(Note::C4, Note::A4), ADSRDuration::constant(16), Volume::constant(80),
+                                Flags::new(Channel::Pulse2, DutyCycle::OneQuarter, Pan::Right));
+        assert!(glide.chunks(2).all(|pair| pair[0] == 0));
+        assert_eq!(checksum(&glide), 9129571203755821976);
+    }
+
+    static ARPEGGIO: Instrument = Instrument {
+        arpeggio: Sequence::repeat(&[0, 4, 7]),
+        volume: Sequence::once(&[100, 80, 60]),
+        ..Instrument::PLAIN
+    };
+
+    static SONG: Song = Song {
+        length: 48,
+        looping: false,
+        tracks: &[
+            Track {
+                channel: Channel::Pulse1,
+                duty_cycle: DutyCycle::OneHalf,
+                pan: Pan::Center,
+                instrument: None,
+                notes: &[SongNote::new(0, 60, 10, 60), SongNote::new(12, 64, 10, 60), SongNote::new(12, 67, 20, 60)],
+            },
+            Track {
+                channel: Channel::Triangle,
+                duty_cycle: DutyCycle::OneHalf,
+                pan: Pan::Center,
+                instrument: Some(&ARPEGGIO),
+                notes: &[SongNote::new(0, 48, 24, 100), SongNote::new(24, 43, 24, 100)],
+            },
+        ],
+    };
+
+    #[test]
+    fn song() {
+        let mut player = SongPlayer::new(&SONG);
+        player.play();
+        let samples = Synthesizer::new().render(SONG.length + 2, |output| player.update(output));
+        assert!(!player.is_playing());
+        assert_eq!(checksum(&samples), 3007266157412367085);
+    }
+
+    static JUMP: SoundEffect = SoundEffect {
+        name: "jump",
+        priority: 1,
+        channels: ChannelSet::of(Channel::Pulse2),
+        duty_cycle: DutyCycle::OneQuarter,
+        pan: Pan::Center,
+        steps: &[SoundStep::slide(300, 900, 6, 70), SoundStep::pause(2), SoundStep::note(Note::A4, 4, 50)],
+    };
+
+    #[test]
+    fn sound_effect_over_ducked_music() {
+        let mut player = SongPlayer::new(&SONG);
+        let mut effects = SoundEffects::<4>::new();
+        let jump = effects.register(&JUMP);
+        player.play();
+        let mut frame = 0;
+        let samples = Synthesizer::new().render(30, |output| {
+            if frame == 10 {
+                effects.play(jump);
+            }
+            player.set_ducked_channels(effects.busy_channels());
+            player.update(output);
+            effects.update(output);
+            frame += 1;
+        });
+        assert!(!effects.is_playing(jump));
+        assert_eq!(checksum(&samples), 5349002608856650509);
+    }
+}
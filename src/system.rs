@@ -15,6 +15,7 @@ pub const MOUSE_X: *const i16 = 0x1a as *const i16;
 pub const MOUSE_Y: *const i16 = 0x1c as *const i16;
 pub const MOUSE_BUTTONS: *const u8 = 0x1e as *const u8;
 pub const SYSTEM_FLAGS: *mut u8 = 0x1f as *mut u8;
+pub const NETPLAY: *const u8 = 0x20 as *const u8;
 pub const FRAMEBUFFER: *mut [u8; 6400] = 0xa0 as *mut [u8; 6400];
 
 pub const CHAR_WIDTH: u32 = 8;
@@ -34,6 +35,9 @@ pub const MOUSE_MIDDLE: u8 = 4;
 pub const SYSTEM_PRESERVE_FRAMEBUFFER: u8 = 1;
 pub const SYSTEM_HIDE_GAMEPAD_OVERLAY: u8 = 2;
 
+pub const NETPLAY_ACTIVE: u8 = 4;
+pub const NETPLAY_PLAYER_MASK: u8 = 3;
+
 pub const BLIT_2BPP: u32 = 1;
 pub const BLIT_1BPP: u32 = 0;
 pub const BLIT_FLIP_X: u32 = 2;
@@ -0,0 +1,296 @@
+//! Formatted tracing done by the runtime with `tracef`, cheaper in cartridge size than `println!`
+//! as it doesn't pull in `core::fmt`. The `tracef!` macro checks at compile time that the
+//! format string has a supported specifier for every argument and that their types match:
+//!
+//! ```ignore
+//! tracef!("player %d at %f, %f", index, x, y);
+//! tracef!("entered %s", c"cave");
+//! tracef!("loaded 100%%");
+//! ```
+//!
+//! | Specifier | Types                        |
+//! |-----------|------------------------------|
+//! | `%d`      | `i8`, `i16`, `i32`, `u8`, `u16` |
+//! | `%x`      | `i32`, `u8`, `u16`, `u32`    |
+//! | `%c`      | `char`, `u8`                 |
+//! | `%f`      | `f32`, `f64`                 |
+//! | `%s`      | `&CStr`                      |
+
+use core::ffi::{c_void, CStr};
+
+use crate::system;
+
+/// Value as laid out on the argument stack of `tracef`
+#[derive(Copy, Clone, Debug)]
+pub enum TracefValue {
+    /// 4 bytes, for `%d`, `%x`, `%c` and the address of a `%s` string
+    Int(i32),
+    /// 8 bytes aligned to 8 bytes, for `%f`
+    Float(f64),
+}
+
+/// Type accepted by the `SPECIFIER` of a `tracef` format, e.g. `TracefArg<b'd'>` for `%d`
+pub trait TracefArg<const SPECIFIER: u8> {
+    fn value(&self) -> TracefValue;
+}
+
+macro_rules! tracef_int {
+    ($specifier:literal: $($ty:ty),*) => {
+        $(
+            impl TracefArg<$specifier> for $ty {
+                fn value(&self) -> TracefValue {
+                    TracefValue::Int(*self as i32)
+                }
+            }
+        )*
+    };
+}
+
+tracef_int!(b'd': i8, i16, i32, u8, u16);
+tracef_int!(b'x': i32, u8, u16, u32);
+tracef_int!(b'c': char, u8);
+
+impl TracefArg<b'f'> for f32 {
+    fn value(&self) -> TracefValue {
+        TracefValue::Float(*self as f64)
+    }
+}
+
+impl TracefArg<b'f'> for f64 {
+    fn value(&self) -> TracefValue {
+        TracefValue::Float(*self)
+    }
+}
+
+impl TracefArg<b's'> for &CStr {
+    fn value(&self) -> TracefValue {
+        TracefValue::Int(self.as_ptr() as usize as i32)
+    }
+}
+
+/// Specifiers of the `format` in order, failing to compile when they aren't `N` supported ones.
+/// `%%` is a literal `%`, not a specifier.
+pub const fn specifiers<const N: usize>(format: &str) -> [u8; N] {
+    let bytes = format.as_bytes();
+    let mut specifiers = [0; N];
+    let mut count = 0;
+    let mut index = 0;
+    while index < bytes.len() {
+        assert!(bytes[index] != 0, "tracef format must not contain a nul byte");
+        if bytes[index] != b'%' {
+            index += 1;
+            continue;
+        }
+        assert!(index + 1 < bytes.len(), "tracef format must not end with %, write %% for a literal one");
+        let specifier = bytes[index + 1];
+        index += 2;
+        if specifier == b'%' {
+            continue;
+        }
+        assert!(matches!(specifier, b'd' | b'x' | b'c' | b'f' | b's'), "tracef supports only %d, %x, %c, %f, %s and %%");
+        assert!(count < N, "tracef format has more specifiers than arguments");
+        specifiers[count] = specifier;
+        count += 1;
+    }
+    assert!(count == N, "tracef format has fewer specifiers than arguments");
+    specifiers
+}
+
+/// Number of `%%` of the `format` before each of its `N` specifiers, and after the last one
+pub const fn escapes<const N: usize>(format: &str) -> ([usize; N], usize) {
+    let bytes = format.as_bytes();
+    let mut before = [0; N];
+    let mut escapes = 0;
+    let mut count = 0;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        if bytes[index] != b'%' {
+            index += 1;
+            continue;
+        }
+        if bytes[index + 1] == b'%' {
+            escapes += 1;
+        } else {
+            before[count] = escapes;
+            escapes = 0;
+            count += 1;
+        }
+        index += 2;
+    }
+    (before, escapes)
+}
+
+/// Number of `%%` of the `format`
+pub const fn escape_count(format: &str) -> usize {
+    let bytes = format.as_bytes();
+    let mut count = 0;
+    let mut index = 0;
+    while index + 1 < bytes.len() {
+        if bytes[index] == b'%' {
+            count += (bytes[index + 1] == b'%') as usize;
+            index += 1;
+        }
+        index += 1;
+    }
+    count
+}
+
+/// The `format` with a nul byte appended and every `%%` turned into a `%c` of a `%`,
+/// so the literal percent signs don't depend on the runtime
+/// * `LENGTH` - Length of the `format` plus 1
+pub const fn escape_format<const LENGTH: usize>(format: &str) -> [u8; LENGTH] {
+    let bytes = format.as_bytes();
+    assert!(bytes.len() + 1 == LENGTH, "LENGTH must be the length of the format plus 1");
+    let mut escaped = [0; LENGTH];
+    let mut index = 0;
+    while index < bytes.len() {
+        escaped[index] = bytes[index];
+        if bytes[index] == b'%' && index + 1 < bytes.len() {
+            escaped[index + 1] = if bytes[index + 1] == b'%' { b'c' } else { bytes[index + 1] };
+            index += 1;
+        }
+        index += 1;
+    }
+    escaped
+}
+
+/// Argument stack of `tracef`, `SIZE` bytes fitting the arguments with their alignment
+#[repr(C, align(8))]
+pub struct TracefArgs<const SIZE: usize> {
+    bytes: [u8; SIZE],
+    len: usize,
+}
+
+impl<const SIZE: usize> TracefArgs<SIZE> {
+    pub const fn new() -> Self {
+        Self { bytes: [0; SIZE], len: 0 }
+    }
+
+    /// Adds the `value` of the argument for the `SPECIFIER`
+    pub fn push<const SPECIFIER: u8, T: TracefArg<SPECIFIER>>(mut self, value: T) -> Self {
+        match value.value() {
+            TracefValue::Int(value) => self.extend(&value.to_le_bytes()),
+            TracefValue::Float(value) => {
+                self.len = (self.len + 7) & !7;
+                self.extend(&value.to_le_bytes());
+            }
+        }
+        self
+    }
+
+    /// Adds a `%` for each of the `count` escapes turned into `%c` by `escape_format`
+    pub fn push_percents(mut self, count: usize) -> Self {
+        for _ in 0..count {
+            self = self.push::<b'c', _>(b'%');
+        }
+        self
+    }
+
+    /// Writes the `format` with the arguments to the console
+    pub fn trace(&self, format: &CStr) {
+        unsafe { system::tracef(format.as_ptr() as *const u8, self.bytes.as_ptr() as *const c_void) }
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+impl<const SIZE: usize> Default for TracefArgs<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes to the console with the `tracef` of the runtime, e.g. `tracef!("x %d, y %d", x, y)`
+#[macro_export]
+macro_rules! tracef {
+    ($format:literal $(, $arg:expr)* $(,)?) => {{
+        const COUNT: usize = 0 $(+ { let _ = stringify!($arg); 1 })*;
+        const SPECIFIERS: [u8; COUNT] = $crate::tracef::specifiers::<COUNT>($format);
+        const ESCAPES: ([usize; COUNT], usize) = $crate::tracef::escapes::<COUNT>($format);
+        const LENGTH: usize = $format.len() + 1;
+        const BYTES: [u8; LENGTH] = $crate::tracef::escape_format::<LENGTH>($format);
+        const FORMAT: &core::ffi::CStr = match core::ffi::CStr::from_bytes_with_nul(&BYTES) {
+            Ok(format) => format,
+            Err(_) => panic!("tracef format must not contain a nul byte"),
+        };
+        // Every argument takes at most 8 bytes plus 4 of padding, every escaped % 4 bytes
+        const SIZE: usize = COUNT * 12 + $crate::tracef::escape_count($format) * 4;
+        $crate::tracef!(@push $crate::tracef::TracefArgs::<SIZE>::new(), 0, $($arg),*)
+            .push_percents(ESCAPES.1)
+            .trace(FORMAT)
+    }};
+    (@push $args:expr, $index:expr, $arg:expr $(, $rest:expr)*) => {
+        $crate::tracef!(@push $args.push_percents(ESCAPES.0[$index]).push::<{ SPECIFIERS[$index] }, _>($arg), $index + 1, $($rest),*)
+    };
+    (@push $args:expr, $index:expr,) => {
+        $args
+    };
+    (@push $args:expr, $index:expr) => {
+        $args
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Never called, compiling it checks the macro with every kind of argument
+    #[allow(dead_code)]
+    fn tracef_compiles(x: i32, y: f32) {
+        crate::tracef!("ready");
+        crate::tracef!("x %d", x);
+        crate::tracef!("y %f", y);
+        crate::tracef!("%s at 100%%", c"cave");
+        crate::tracef!("x %d, y %f, %c%x", x, y, 'a', 0xffu32,);
+    }
+
+    #[test]
+    fn specifiers_skip_escaped_percents() {
+        const SPECIFIERS: [u8; 3] = specifiers::<3>("%d%% of %f, %%%s");
+        assert_eq!(SPECIFIERS, *b"dfs");
+        assert_eq!(specifiers::<0>("plain"), []);
+        assert_eq!(specifiers::<0>("100%%"), []);
+    }
+
+    #[test]
+    #[should_panic(expected = "fewer specifiers than arguments")]
+    fn missing_specifiers_are_rejected() {
+        specifiers::<2>("%d %%");
+    }
+
+    #[test]
+    #[should_panic(expected = "tracef supports only")]
+    fn unsupported_specifiers_are_rejected() {
+        specifiers::<1>("%u");
+    }
+
+    #[test]
+    #[should_panic(expected = "must not end with %")]
+    fn trailing_percent_is_rejected() {
+        specifiers::<0>("100%");
+    }
+
+    #[test]
+    fn escaped_percents_become_characters() {
+        const FORMAT: &str = "%%%d%% and %%%%";
+        assert_eq!(escapes::<1>(FORMAT), ([1], 3));
+        assert_eq!(escape_count(FORMAT), 4);
+        assert_eq!(&escape_format::<16>(FORMAT), b"%c%d%c and %c%c\0");
+        assert_eq!(&escape_format::<6>("%d %s"), b"%d %s\0");
+    }
+
+    #[test]
+    fn floats_are_aligned_to_8_bytes() {
+        let args = TracefArgs::<24>::new()
+            .push::<b'd', _>(-2i16)
+            .push::<b'f', _>(1.5f32)
+            .push_percents(1);
+        assert_eq!(args.len, 20);
+        assert_eq!(args.bytes[..4], (-2i32).to_le_bytes());
+        assert_eq!(args.bytes[8..16], 1.5f64.to_le_bytes());
+        assert_eq!(args.bytes[16..20], (b'%' as i32).to_le_bytes());
+    }
+}
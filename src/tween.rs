@@ -0,0 +1,356 @@
+//! Tweens moving a value from a start to an end over a number of frames, shaped by an easing curve.
+//!
+//! The curves are computed with fixed-point numbers, so they are the same in every runtime.
+
+use crate::color::Color;
+use crate::fixed::Fixed;
+use crate::geometry::{FixedVector, Point};
+use crate::hsl_color::HSLColor;
+
+/// Overshoot of the back curves, 1.70158
+const BACK: Fixed = Fixed::from_bits(111514);
+/// Overshoot of the back curves when easing in and out, 1.70158 * 1.525
+const BACK_IN_OUT: Fixed = Fixed::from_bits(170059);
+
+/// Curve mapping the progress between 0 and 1 to the eased progress,
+/// starting at 0 and ending at 1, possibly overshooting in between
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    /// Eased progress of the progress `t` between 0 and 1
+    pub fn apply(self, t: Fixed) -> Fixed {
+        let t = t.clamp(Fixed::ZERO, Fixed::ONE);
+        let one = Fixed::ONE;
+        let two = Fixed::from_int(2);
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => one - (one - t) * (one - t),
+            Easing::QuadInOut => in_out(t, Easing::QuadIn),
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => {
+                let u = one - t;
+                one - u * u * u
+            }
+            Easing::CubicInOut => in_out(t, Easing::CubicIn),
+            Easing::BackIn => t * t * ((BACK + one) * t - BACK),
+            Easing::BackOut => one - Easing::BackIn.apply(one - t),
+            Easing::BackInOut => {
+                let back_in = |t: Fixed| t * t * ((BACK_IN_OUT + one) * t - BACK_IN_OUT);
+                if t < Fixed::HALF {
+                    back_in(t * 2) / 2
+                } else {
+                    one - back_in((one - t) * 2) / 2
+                }
+            }
+            Easing::ElasticIn => one - Easing::ElasticOut.apply(one - t),
+            Easing::ElasticOut => {
+                if t == Fixed::ZERO || t == one {
+                    return t;
+                }
+                // 2^(-10t) * sin((10t - 0.75) * 2π / 3) + 1
+                let angle = (t * 10 - Fixed::from_ratio(3, 4)) * Fixed::TAU / 3;
+                exp2(-t * 10) * angle.sin() + one
+            }
+            Easing::ElasticInOut => in_out(t, Easing::ElasticIn),
+            Easing::BounceIn => one - Easing::BounceOut.apply(one - t),
+            Easing::BounceOut => {
+                let n = Fixed::from_ratio(121, 16);
+                let d = Fixed::from_ratio(11, 4);
+                if t < one / d {
+                    n * t * t
+                } else if t < two / d {
+                    let t = t - Fixed::from_ratio(3, 2) / d;
+                    n * t * t + Fixed::from_ratio(3, 4)
+                } else if t < Fixed::from_ratio(5, 2) / d {
+                    let t = t - Fixed::from_ratio(9, 4) / d;
+                    n * t * t + Fixed::from_ratio(15, 16)
+                } else {
+                    let t = t - Fixed::from_ratio(21, 8) / d;
+                    n * t * t + Fixed::from_ratio(63, 64)
+                }
+            }
+            Easing::BounceInOut => in_out(t, Easing::BounceIn),
+        }
+    }
+}
+
+/// First half eased in and second half eased out with the `easing_in` curve
+fn in_out(t: Fixed, easing_in: Easing) -> Fixed {
+    if t < Fixed::HALF {
+        easing_in.apply(t * 2) / 2
+    } else {
+        Fixed::ONE - easing_in.apply((Fixed::ONE - t) * 2) / 2
+    }
+}
+
+/// 2 to the power of the `exponent`, approximated between the integer powers
+fn exp2(exponent: Fixed) -> Fixed {
+    let int = exponent.to_int();
+    let fraction = exponent.fract();
+    // 2^f ≈ 1 + f * (0.6565 + 0.3435 * f), exact at 0 and 1
+    let power = Fixed::ONE + fraction * (Fixed::from_bits(43025) + Fixed::from_bits(22511) * fraction);
+    if int >= 0 {
+        Fixed::from_bits(power.to_bits() << int.min(14))
+    } else {
+        Fixed::from_bits(power.to_bits() >> (-int).min(31))
+    }
+}
+
+/// Value that can be moved by a tween
+pub trait Tweenable: Copy {
+    /// Value between `from` and `to`, at `from` when `t` is 0 and at `to` when `t` is 1.
+    /// `t` can be outside of the range for the curves that overshoot.
+    fn interpolate(from: Self, to: Self, t: Fixed) -> Self;
+}
+
+impl Tweenable for i32 {
+    fn interpolate(from: i32, to: i32, t: Fixed) -> i32 {
+        let delta = (to as i64 - from as i64) * t.to_bits() as i64;
+        (from as i64 + ((delta + 0x8000) >> Fixed::FRACTIONAL_BITS)) as i32
+    }
+}
+
+impl Tweenable for u8 {
+    fn interpolate(from: u8, to: u8, t: Fixed) -> u8 {
+        i32::interpolate(from as i32, to as i32, t).clamp(0, u8::MAX as i32) as u8
+    }
+}
+
+impl Tweenable for Fixed {
+    fn interpolate(from: Fixed, to: Fixed, t: Fixed) -> Fixed {
+        from.lerp(to, t)
+    }
+}
+
+impl Tweenable for Point {
+    fn interpolate(from: Point, to: Point, t: Fixed) -> Point {
+        Point::new(i32::interpolate(from.x, to.x, t), i32::interpolate(from.y, to.y, t))
+    }
+}
+
+impl Tweenable for FixedVector {
+    fn interpolate(from: FixedVector, to: FixedVector, t: Fixed) -> FixedVector {
+        FixedVector::new(from.x.lerp(to.x, t), from.y.lerp(to.y, t))
+    }
+}
+
+impl Tweenable for Color {
+    /// Interpolates every channel separately, e.g. for palette fades
+    fn interpolate(from: Color, to: Color, t: Fixed) -> Color {
+        Color::new(
+            u8::interpolate(from.red, to.red, t),
+            u8::interpolate(from.green, to.green, t),
+            u8::interpolate(from.blue, to.blue, t),
+        )
+    }
+}
+
+impl Tweenable for HSLColor {
+    /// Interpolates the hue the shorter way around the color wheel
+    fn interpolate(from: HSLColor, to: HSLColor, t: Fixed) -> HSLColor {
+        let t = t.to_bits() as f32 / Fixed::ONE.to_bits() as f32;
+        let mut hue_delta = to.hue - from.hue;
+        if hue_delta > 180.0 {
+            hue_delta -= 360.0;
+        } else if hue_delta < -180.0 {
+            hue_delta += 360.0;
+        }
+        let mut hue = from.hue + hue_delta * t;
+        if hue < 0.0 {
+            hue += 360.0;
+        } else if hue >= 360.0 {
+            hue -= 360.0;
+        }
+        HSLColor::new(
+            hue,
+            (from.saturation + (to.saturation - from.saturation) * t).clamp(0.0, 1.0),
+            (from.lightness + (to.lightness - from.lightness) * t).clamp(0.0, 1.0),
+        )
+    }
+}
+
+/// What happens when a tween reaches its end
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub enum Repeat {
+    /// Stops at the end
+    #[default]
+    Once,
+    /// Starts over from the start
+    Loop,
+    /// Goes back and forth between the start and the end
+    PingPong,
+}
+
+/// Value moving from `from` to `to` over `duration` frames, advanced by `update` once per frame
+#[derive(Copy, Clone, Debug)]
+pub struct Tween<T: Tweenable> {
+    pub from: T,
+    pub to: T,
+    pub duration: u32,
+    pub easing: Easing,
+    pub repeat: Repeat,
+    frame: u32,
+    backwards: bool,
+}
+
+impl<T: Tweenable> Tween<T> {
+    pub const fn new(from: T, to: T, duration: u32, easing: Easing) -> Self {
+        Self { from, to, duration, easing, repeat: Repeat::Once, frame: 0, backwards: false }
+    }
+
+    pub const fn with_repeat(self, repeat: Repeat) -> Self {
+        Self { repeat, ..self }
+    }
+
+    /// Advances the tween by one frame. Repeating tweens show the end value for a frame
+    /// and go on with the frame after the start, so a loop lasts exactly the duration.
+    pub fn update(&mut self) {
+        if self.frame < self.duration {
+            self.frame += 1;
+            return;
+        }
+        match self.repeat {
+            Repeat::Once => {}
+            Repeat::Loop => self.frame = self.duration.min(1),
+            Repeat::PingPong => {
+                self.frame = self.duration.min(1);
+                self.backwards = !self.backwards;
+            }
+        }
+    }
+
+    /// Progress through the duration between 0 and 1, before the easing
+    pub fn progress(&self) -> Fixed {
+        if self.duration == 0 {
+            return Fixed::ONE;
+        }
+        let progress = Fixed::from_ratio(self.frame as i32, self.duration as i32);
+        if self.backwards { Fixed::ONE - progress } else { progress }
+    }
+
+    /// Current value of the tween
+    pub fn value(&self) -> T {
+        T::interpolate(self.from, self.to, self.easing.apply(self.progress()))
+    }
+
+    /// Whether a tween playing once reached its end, repeating tweens never finish
+    pub fn is_finished(&self) -> bool {
+        self.repeat == Repeat::Once && self.frame >= self.duration
+    }
+
+    /// Starts the tween again from the start
+    pub fn restart(&mut self) {
+        self.frame = 0;
+        self.backwards = false;
+    }
+
+    /// Starts a new tween from the current value to the `to` value, e.g. to retarget a camera pan
+    pub fn retarget(&mut self, to: T, duration: u32) {
+        self.from = self.value();
+        self.to = to;
+        self.duration = duration;
+        self.restart();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    const EASINGS: [Easing; 16] = [
+        Easing::Linear, Easing::QuadIn, Easing::QuadOut, Easing::QuadInOut,
+        Easing::CubicIn, Easing::CubicOut, Easing::CubicInOut, Easing::BackIn,
+        Easing::BackOut, Easing::BackInOut, Easing::ElasticIn, Easing::ElasticOut,
+        Easing::ElasticInOut, Easing::BounceIn, Easing::BounceOut, Easing::BounceInOut,
+    ];
+
+    fn values(tween: &mut Tween<i32>, frames: usize) -> Vec<i32> {
+        (0..frames).map(|_| {
+            tween.update();
+            tween.value()
+        }).collect()
+    }
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        for easing in EASINGS {
+            let start = easing.apply(Fixed::ZERO).to_bits();
+            let end = easing.apply(Fixed::ONE).to_bits();
+            assert!(start.abs() <= 2, "{easing:?} starts at {start}");
+            assert!((end - Fixed::ONE.to_bits()).abs() <= 2, "{easing:?} ends at {end}");
+            assert_eq!(easing.apply(-Fixed::ONE), easing.apply(Fixed::ZERO));
+        }
+        assert_eq!(Easing::QuadIn.apply(Fixed::HALF), Fixed::from_ratio(1, 4));
+        assert_eq!(Easing::QuadInOut.apply(Fixed::HALF), Fixed::HALF);
+        assert!(Easing::BackIn.apply(Fixed::from_ratio(1, 4)).is_negative());
+    }
+
+    #[test]
+    fn once_stops_at_the_end() {
+        let mut tween = Tween::new(0, 100, 4, Easing::Linear);
+        assert_eq!(tween.value(), 0);
+        assert_eq!(values(&mut tween, 6), [25, 50, 75, 100, 100, 100]);
+        assert!(tween.is_finished());
+    }
+
+    #[test]
+    fn loop_shows_the_end_and_keeps_the_period() {
+        let mut tween = Tween::new(0, 100, 4, Easing::Linear).with_repeat(Repeat::Loop);
+        assert_eq!(values(&mut tween, 9), [25, 50, 75, 100, 25, 50, 75, 100, 25]);
+        assert!(!tween.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_turns_at_both_ends() {
+        let mut tween = Tween::new(0, 100, 2, Easing::Linear).with_repeat(Repeat::PingPong);
+        assert_eq!(values(&mut tween, 7), [50, 100, 50, 0, 50, 100, 50]);
+    }
+
+    #[test]
+    fn zero_duration_is_at_the_end() {
+        let mut tween = Tween::new(3, 9, 0, Easing::BounceOut).with_repeat(Repeat::Loop);
+        tween.update();
+        assert_eq!(tween.value(), 9);
+    }
+
+    #[test]
+    fn retarget_starts_from_the_current_value() {
+        let mut tween = Tween::new(Point::new(0, 0), Point::new(40, -40), 4, Easing::Linear);
+        tween.update();
+        tween.retarget(Point::new(10, 10), 2);
+        assert_eq!(tween.from, Point::new(10, -10));
+        tween.update();
+        assert_eq!(tween.value(), Point::new(10, 0));
+    }
+
+    #[test]
+    fn values_interpolate_with_rounding() {
+        assert_eq!(i32::interpolate(0, 3, Fixed::HALF), 2);
+        assert_eq!(i32::interpolate(0, -3, Fixed::HALF), -1);
+        assert_eq!(u8::interpolate(200, 250, Fixed::from_int(2)), 255);
+        assert_eq!(Color::interpolate(Color::new(0, 100, 255), Color::new(100, 100, 55), Fixed::HALF), Color::new(50, 100, 155));
+        let hue = HSLColor::interpolate(HSLColor::new(350.0, 1.0, 0.5), HSLColor::new(30.0, 1.0, 0.5), Fixed::HALF).hue;
+        assert!((hue - 10.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,499 @@
+//! Menus, lists and message boxes navigated with the D-pad, confirmed with `ButtonX`
+//! and cancelled with `ButtonY`, drawn with the built-in font.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::framebuffer::{Framebuffer, PaletteIndex};
+use crate::gamepad::{Gamepad, GamepadButton};
+use crate::geometry::{Point, Rect};
+use crate::system;
+
+const CHAR_WIDTH: i32 = system::CHAR_WIDTH as i32;
+const CHAR_HEIGHT: i32 = system::CHAR_HEIGHT as i32;
+/// Height of a row of a menu or a list, the text and a pixel above and below it
+const ROW_HEIGHT: i32 = CHAR_HEIGHT + 2;
+
+/// Colors of the widgets
+#[derive(Copy, Clone, Debug)]
+pub struct Style {
+    pub text: PaletteIndex,
+    /// Background of the focused row
+    pub highlight: PaletteIndex,
+    /// Text of the focused row
+    pub highlight_text: PaletteIndex,
+    pub disabled_text: PaletteIndex,
+    /// Background and border of message boxes
+    pub panel: PaletteIndex,
+    pub border: PaletteIndex,
+}
+
+impl Style {
+    pub const DEFAULT: Style = Style {
+        text: PaletteIndex::Palette4,
+        highlight: PaletteIndex::Palette3,
+        highlight_text: PaletteIndex::Palette1,
+        disabled_text: PaletteIndex::Palette2,
+        panel: PaletteIndex::Palette1,
+        border: PaletteIndex::Palette4,
+    };
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Item of a `Menu`
+#[derive(Clone, Debug)]
+pub enum Widget {
+    /// Text which can't be focused, e.g. a section title
+    Label(String),
+    Button { label: String, enabled: bool },
+    Checkbox { label: String, checked: bool },
+    /// Value changed with left and right by the `step`
+    Slider { label: String, value: i32, min: i32, max: i32, step: i32 },
+}
+
+impl Widget {
+    pub fn label(text: &str) -> Self {
+        Widget::Label(String::from(text))
+    }
+
+    pub fn button(label: &str) -> Self {
+        Widget::Button { label: String::from(label), enabled: true }
+    }
+
+    pub fn checkbox(label: &str, checked: bool) -> Self {
+        Widget::Checkbox { label: String::from(label), checked }
+    }
+
+    pub fn slider(label: &str, value: i32, min: i32, max: i32, step: i32) -> Self {
+        assert!(min <= max, "min must not exceed max");
+        Widget::Slider { label: String::from(label), value: value.clamp(min, max), min, max, step }
+    }
+
+    fn is_focusable(&self) -> bool {
+        match self {
+            Widget::Label(_) => false,
+            Widget::Button { enabled, .. } => *enabled,
+            Widget::Checkbox { .. } | Widget::Slider { .. } => true,
+        }
+    }
+}
+
+/// Result of the input of a frame
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum MenuEvent {
+    None,
+    /// The focus moved to the item
+    Focused(usize),
+    /// The button was pressed
+    Activated(usize),
+    /// The checkbox or slider changed its value
+    Changed(usize),
+    /// `ButtonY` was pressed, e.g. to go back
+    Cancelled,
+}
+
+/// Vertical menu of widgets, one of them focused
+#[derive(Clone, Debug)]
+pub struct Menu {
+    pub items: Vec<Widget>,
+    /// Top left corner
+    pub position: Point,
+    /// Width in pixels of the rows
+    pub width: u32,
+    pub style: Style,
+    focus: usize,
+}
+
+impl Menu {
+    pub fn new(items: Vec<Widget>, position: Point, width: u32) -> Self {
+        let mut menu = Self { items, position, width, style: Style::DEFAULT, focus: 0 };
+        menu.focus = menu.next_focusable(0, 1).unwrap_or(0);
+        menu
+    }
+
+    pub fn with_style(self, style: Style) -> Self {
+        Self { style, ..self }
+    }
+
+    /// Index of the focused item
+    pub fn focus(&self) -> usize {
+        self.focus
+    }
+
+    pub fn set_focus(&mut self, index: usize) {
+        if self.items.get(index).is_some_and(Widget::is_focusable) {
+            self.focus = index;
+        }
+    }
+
+    /// Whether the checkbox at the `index` is checked
+    pub fn is_checked(&self, index: usize) -> bool {
+        matches!(self.items.get(index), Some(Widget::Checkbox { checked: true, .. }))
+    }
+
+    /// Value of the slider at the `index`
+    pub fn value(&self, index: usize) -> Option<i32> {
+        match self.items.get(index) {
+            Some(Widget::Slider { value, .. }) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Area covered by the menu
+    pub fn bounds(&self) -> Rect {
+        Rect::new(self.position.x, self.position.y, self.width, (self.items.len() as i32 * ROW_HEIGHT) as u32)
+    }
+
+    /// Moves the focus with up and down, changes the value of the focused widget with left and right
+    pub fn update(&mut self, gamepad: &Gamepad) -> MenuEvent {
+        if gamepad.is_pressed(GamepadButton::ButtonY) {
+            return MenuEvent::Cancelled;
+        }
+        let vertical = gamepad.is_pressed(GamepadButton::DPadDown) as i32 - gamepad.is_pressed(GamepadButton::DPadUp) as i32;
+        if vertical != 0 {
+            let start = (self.focus as i32 + vertical).rem_euclid(self.items.len().max(1) as i32) as usize;
+            if let Some(focus) = self.next_focusable(start, vertical).filter(|focus| *focus != self.focus) {
+                self.focus = focus;
+                return MenuEvent::Focused(focus);
+            }
+            return MenuEvent::None;
+        }
+        let horizontal = gamepad.is_pressed(GamepadButton::DPadRight) as i32 - gamepad.is_pressed(GamepadButton::DPadLeft) as i32;
+        let confirm = gamepad.is_pressed(GamepadButton::ButtonX);
+        let focus = self.focus;
+        match self.items.get_mut(focus) {
+            Some(Widget::Button { enabled: true, .. }) if confirm => MenuEvent::Activated(focus),
+            Some(Widget::Checkbox { checked, .. }) if confirm || horizontal != 0 => {
+                *checked = !*checked;
+                MenuEvent::Changed(focus)
+            }
+            Some(Widget::Slider { value, min, max, step, .. }) if horizontal != 0 => {
+                let changed = (*value + horizontal * *step).clamp(*min, *max);
+                if changed == *value {
+                    return MenuEvent::None;
+                }
+                *value = changed;
+                MenuEvent::Changed(focus)
+            }
+            _ => MenuEvent::None,
+        }
+    }
+
+    pub fn render(&self, framebuffer: &Framebuffer) {
+        let draw_colors = framebuffer.get_draw_colors();
+        for (index, item) in self.items.iter().enumerate() {
+            let row = Rect::new(self.position.x, self.position.y + index as i32 * ROW_HEIGHT, self.width, ROW_HEIGHT as u32);
+            let focused = index == self.focus && item.is_focusable();
+            let color = if focused {
+                fill(framebuffer, row, self.style.highlight);
+                self.style.highlight_text
+            } else if matches!(item, Widget::Button { enabled: false, .. }) {
+                self.style.disabled_text
+            } else {
+                self.style.text
+            };
+            let text_position = Point::new(row.x + 1, row.y + 1);
+            match item {
+                Widget::Label(text) | Widget::Button { label: text, .. } => text_at(framebuffer, text, text_position, color),
+                Widget::Checkbox { label, checked } => {
+                    text_at(framebuffer, if *checked { "[x]" } else { "[ ]" }, text_position, color);
+                    text_at(framebuffer, label, Point::new(text_position.x + 4 * CHAR_WIDTH, text_position.y), color);
+                }
+                Widget::Slider { label, value, min, max, .. } => {
+                    text_at(framebuffer, label, text_position, color);
+                    // Bar in the right half of the row, between arrows
+                    let bar_width = self.width as i32 / 2 - 2 * CHAR_WIDTH;
+                    let bar_x = row.right() - bar_width - CHAR_WIDTH - 1;
+                    text_at(framebuffer, "<", Point::new(bar_x - CHAR_WIDTH, text_position.y), color);
+                    text_at(framebuffer, ">", Point::new(bar_x + bar_width, text_position.y), color);
+                    let filled = if max > min { bar_width * (value - min) / (max - min) } else { bar_width };
+                    let bar_y = row.y + ROW_HEIGHT / 2 - 1;
+                    outline(framebuffer, Rect::new(bar_x, bar_y - 1, bar_width.max(0) as u32, 4), color);
+                    fill(framebuffer, Rect::new(bar_x, bar_y, filled.max(0) as u32, 2), color);
+                }
+            }
+        }
+        framebuffer.set_draw_colors(draw_colors.map(Some));
+    }
+
+    /// The first focusable item from the `start` in the `direction`, wrapping around
+    fn next_focusable(&self, start: usize, direction: i32) -> Option<usize> {
+        let count = self.items.len() as i32;
+        (0..count)
+            .map(|offset| (start as i32 + offset * direction.signum()).rem_euclid(count) as usize)
+            .find(|index| self.items[*index].is_focusable())
+    }
+}
+
+/// Selection and scrolling of a list longer than the rows visible at once, e.g. an inventory.
+/// The items are kept by the game and drawn through a function giving their label.
+#[derive(Copy, Clone, Debug)]
+pub struct ListView {
+    /// Area the rows are drawn in
+    pub bounds: Rect,
+    pub style: Style,
+    selected: usize,
+    scroll: usize,
+}
+
+impl ListView {
+    pub const fn new(bounds: Rect) -> Self {
+        Self { bounds, style: Style::DEFAULT, selected: 0, scroll: 0 }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Number of rows fitting in the bounds
+    pub fn visible_rows(&self) -> usize {
+        (self.bounds.height as i32 / ROW_HEIGHT).max(1) as usize
+    }
+
+    /// Selects the item at the `index` and scrolls to it
+    pub fn select(&mut self, index: usize, count: usize) {
+        self.selected = index.min(count.saturating_sub(1));
+        let visible = self.visible_rows();
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + visible {
+            self.scroll = self.selected + 1 - visible;
+        }
+    }
+
+    /// Moves the selection with up and down, and by a page with left and right
+    /// * `count` - Number of items of the list
+    pub fn update(&mut self, gamepad: &Gamepad, count: usize) -> MenuEvent {
+        if count == 0 {
+            return if gamepad.is_pressed(GamepadButton::ButtonY) { MenuEvent::Cancelled } else { MenuEvent::None };
+        }
+        if gamepad.is_pressed(GamepadButton::ButtonY) {
+            return MenuEvent::Cancelled;
+        }
+        if gamepad.is_pressed(GamepadButton::ButtonX) {
+            return MenuEvent::Activated(self.selected);
+        }
+        let page = self.visible_rows() as isize;
+        let offset = if gamepad.is_pressed(GamepadButton::DPadDown) {
+            1
+        } else if gamepad.is_pressed(GamepadButton::DPadUp) {
+            -1
+        } else if gamepad.is_pressed(GamepadButton::DPadRight) {
+            page
+        } else if gamepad.is_pressed(GamepadButton::DPadLeft) {
+            -page
+        } else {
+            return MenuEvent::None;
+        };
+        let selected = (self.selected as isize + offset).clamp(0, count as isize - 1) as usize;
+        if selected == self.selected {
+            return MenuEvent::None;
+        }
+        self.select(selected, count);
+        MenuEvent::Focused(selected)
+    }
+
+    /// Draws the visible rows with a scroll bar when the items don't fit
+    /// * `label` - Text of the item at the index
+    pub fn render<'a>(&self, framebuffer: &Framebuffer, count: usize, label: impl Fn(usize) -> &'a str) {
+        let draw_colors = framebuffer.get_draw_colors();
+        let visible = self.visible_rows();
+        let scrollable = count > visible;
+        let width = if scrollable { self.bounds.width.saturating_sub(3) } else { self.bounds.width };
+        for (row, index) in (self.scroll..count.min(self.scroll + visible)).enumerate() {
+            let rect = Rect::new(self.bounds.x, self.bounds.y + row as i32 * ROW_HEIGHT, width, ROW_HEIGHT as u32);
+            let color = if index == self.selected {
+                fill(framebuffer, rect, self.style.highlight);
+                self.style.highlight_text
+            } else {
+                self.style.text
+            };
+            text_at(framebuffer, label(index), Point::new(rect.x + 1, rect.y + 1), color);
+        }
+        if scrollable {
+            let track = self.bounds.height as usize;
+            let thumb_height = (track * visible / count).max(2).min(track);
+            let thumb_y = (track - thumb_height) * self.scroll / (count - visible);
+            let x = self.bounds.right() - 2;
+            fill(framebuffer, Rect::new(x, self.bounds.y + thumb_y as i32, 2, thumb_height as u32), self.style.text);
+        }
+        framebuffer.set_draw_colors(draw_colors.map(Some));
+    }
+}
+
+/// Panel with a message wrapped into lines and a row of buttons, e.g. "Quit? Yes / No"
+#[derive(Clone, Debug)]
+pub struct MessageBox {
+    pub bounds: Rect,
+    pub style: Style,
+    lines: Vec<String>,
+    buttons: Vec<String>,
+    selected: usize,
+}
+
+impl MessageBox {
+    /// * `buttons` - Labels of the buttons, the first one selected
+    pub fn new(message: &str, buttons: &[&str], bounds: Rect) -> Self {
+        let columns = ((bounds.width as i32 - 4) / CHAR_WIDTH).max(1) as usize;
+        Self {
+            bounds,
+            style: Style::DEFAULT,
+            lines: wrap(message, columns),
+            buttons: buttons.iter().map(|button| String::from(*button)).collect(),
+            selected: 0,
+        }
+    }
+
+    /// Moves between the buttons with left and right, returns `Activated` with the index
+    /// of the chosen button or `Cancelled`
+    pub fn update(&mut self, gamepad: &Gamepad) -> MenuEvent {
+        if gamepad.is_pressed(GamepadButton::ButtonY) {
+            return MenuEvent::Cancelled;
+        }
+        if gamepad.is_pressed(GamepadButton::ButtonX) {
+            return MenuEvent::Activated(self.selected);
+        }
+        let horizontal = gamepad.is_pressed(GamepadButton::DPadRight) as i32 - gamepad.is_pressed(GamepadButton::DPadLeft) as i32;
+        let selected = (self.selected as i32 + horizontal).clamp(0, self.buttons.len().max(1) as i32 - 1) as usize;
+        if selected == self.selected {
+            return MenuEvent::None;
+        }
+        self.selected = selected;
+        MenuEvent::Focused(selected)
+    }
+
+    pub fn render(&self, framebuffer: &Framebuffer) {
+        let draw_colors = framebuffer.get_draw_colors();
+        fill(framebuffer, self.bounds, self.style.panel);
+        outline(framebuffer, self.bounds, self.style.border);
+        for (index, line) in self.lines.iter().enumerate() {
+            let position = Point::new(self.bounds.x + 2, self.bounds.y + 2 + index as i32 * ROW_HEIGHT);
+            text_at(framebuffer, line, position, self.style.text);
+        }
+        // Buttons centered on the bottom row
+        let widths = self.buttons.iter().map(|button| (button.chars().count() as i32 + 1) * CHAR_WIDTH);
+        let total = widths.clone().sum::<i32>();
+        let mut x = self.bounds.x + (self.bounds.width as i32 - total) / 2;
+        let y = self.bounds.bottom() - ROW_HEIGHT - 2;
+        for (index, (button, width)) in self.buttons.iter().zip(widths).enumerate() {
+            let rect = Rect::new(x, y, (width - CHAR_WIDTH + 2) as u32, ROW_HEIGHT as u32);
+            let color = if index == self.selected {
+                fill(framebuffer, rect, self.style.highlight);
+                self.style.highlight_text
+            } else {
+                self.style.text
+            };
+            text_at(framebuffer, button, Point::new(rect.x + 1, rect.y + 1), color);
+            x += width;
+        }
+        framebuffer.set_draw_colors(draw_colors.map(Some));
+    }
+}
+
+/// Splits the `text` into lines of at most `columns` characters, breaking between words
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ').filter(|word| !word.is_empty()) {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > columns {
+                lines.push(core::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn text_at(framebuffer: &Framebuffer, text: &str, position: Point, color: PaletteIndex) {
+    framebuffer.set_draw_colors([Some(color), Some(PaletteIndex::Transparent), None, None]);
+    framebuffer.text_at(text, position);
+}
+
+fn fill(framebuffer: &Framebuffer, rect: Rect, color: PaletteIndex) {
+    framebuffer.set_draw_colors([Some(color), Some(color), None, None]);
+    framebuffer.rectangle_in(rect);
+}
+
+fn outline(framebuffer: &Framebuffer, rect: Rect, color: PaletteIndex) {
+    framebuffer.set_draw_colors([Some(PaletteIndex::Transparent), Some(color), None, None]);
+    framebuffer.rectangle_in(rect);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn gamepad(buttons: &[GamepadButton]) -> Gamepad {
+        let mut gamepad = unsafe { Gamepad::new() };
+        gamepad.early_update(buttons.iter().fold(0, |state, button| state | *button as u8));
+        gamepad
+    }
+
+    #[test]
+    fn menu_skips_what_cannot_be_focused() {
+        let mut menu = Menu::new(vec![
+            Widget::label("Options"),
+            Widget::checkbox("Sound", true),
+            Widget::Button { label: String::from("Online"), enabled: false },
+            Widget::slider("Volume", 5, 0, 10, 3),
+        ], Point::new(0, 0), 80);
+        assert_eq!(menu.focus(), 1);
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::DPadDown])), MenuEvent::Focused(3));
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::DPadDown])), MenuEvent::Focused(1));
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::ButtonX])), MenuEvent::Changed(1));
+        assert!(!menu.is_checked(1));
+        menu.set_focus(2);
+        assert_eq!(menu.focus(), 1);
+        menu.set_focus(3);
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::DPadRight])), MenuEvent::Changed(3));
+        assert_eq!(menu.value(3), Some(8));
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::DPadRight])), MenuEvent::Changed(3));
+        assert_eq!(menu.value(3), Some(10));
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::DPadRight])), MenuEvent::None);
+        assert_eq!(menu.update(&gamepad(&[GamepadButton::ButtonY])), MenuEvent::Cancelled);
+        assert_eq!(menu.bounds(), Rect::new(0, 0, 80, 4 * ROW_HEIGHT as u32));
+    }
+
+    #[test]
+    fn list_scrolls_to_the_selection() {
+        let mut list = ListView::new(Rect::new(0, 0, 60, 3 * ROW_HEIGHT as u32));
+        assert_eq!(list.visible_rows(), 3);
+        assert_eq!(list.update(&gamepad(&[GamepadButton::DPadRight]), 10), MenuEvent::Focused(3));
+        assert_eq!((list.selected(), list.scroll), (3, 1));
+        assert_eq!(list.update(&gamepad(&[GamepadButton::DPadUp]), 10), MenuEvent::Focused(2));
+        assert_eq!(list.scroll, 1);
+        list.select(20, 10);
+        assert_eq!((list.selected(), list.scroll), (9, 7));
+        assert_eq!(list.update(&gamepad(&[GamepadButton::DPadDown]), 10), MenuEvent::None);
+        assert_eq!(list.update(&gamepad(&[GamepadButton::ButtonX]), 10), MenuEvent::Activated(9));
+        assert_eq!(list.update(&gamepad(&[GamepadButton::ButtonX]), 0), MenuEvent::None);
+    }
+
+    #[test]
+    fn message_box_moves_between_its_buttons() {
+        let mut message = MessageBox::new("Quit the game?", &["Yes", "No"], Rect::new(0, 0, 100, 40));
+        assert_eq!(message.update(&gamepad(&[GamepadButton::DPadLeft])), MenuEvent::None);
+        assert_eq!(message.update(&gamepad(&[GamepadButton::DPadRight])), MenuEvent::Focused(1));
+        assert_eq!(message.update(&gamepad(&[GamepadButton::DPadRight])), MenuEvent::None);
+        assert_eq!(message.update(&gamepad(&[GamepadButton::ButtonX])), MenuEvent::Activated(1));
+    }
+
+    #[test]
+    fn wrap_breaks_between_words_by_characters() {
+        assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+        assert_eq!(wrap("a\n\nb", 7), ["a", "", "b"]);
+        assert_eq!(wrap("élan été", 8), ["élan été"]);
+        assert_eq!(wrap("unbreakable", 4), ["unbreakable"]);
+    }
+}